@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS scene_edges;
+DROP TABLE IF EXISTS scene_nodes;
@@ -0,0 +1,27 @@
+CREATE TABLE IF NOT EXISTS scene_nodes
+(
+    id TEXT PRIMARY KEY,
+    scene_id TEXT NOT NULL REFERENCES scenes (id) ON DELETE CASCADE,
+    node_type TEXT NOT NULL CHECK (node_type IN ('source', 'transform', 'sink')),
+    plugin_id TEXT NOT NULL,
+    plugin_version TEXT NOT NULL,
+    config TEXT NOT NULL DEFAULT '{}',
+    create_time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    update_time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX scene_nodes__scene_id_idx ON scene_nodes (scene_id);
+CREATE INDEX scene_nodes__plugin_id_idx ON scene_nodes (plugin_id);
+
+CREATE TABLE IF NOT EXISTS scene_edges
+(
+    id TEXT PRIMARY KEY,
+    scene_id TEXT NOT NULL REFERENCES scenes (id) ON DELETE CASCADE,
+    from_node_id TEXT NOT NULL REFERENCES scene_nodes (id) ON DELETE CASCADE,
+    to_node_id TEXT NOT NULL REFERENCES scene_nodes (id) ON DELETE CASCADE,
+    create_time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (from_node_id, to_node_id)
+);
+
+CREATE INDEX scene_edges__scene_id_idx ON scene_edges (scene_id);
+CREATE INDEX scene_edges__to_node_id_idx ON scene_edges (to_node_id);
@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS plugins
+(
+    id TEXT PRIMARY KEY,
+    "name" TEXT NOT NULL,
+    plugin_type TEXT NOT NULL CHECK (plugin_type IN ('source', 'transform', 'sink')),
+    version TEXT NOT NULL,
+    executable TEXT NOT NULL,
+    args TEXT NOT NULL DEFAULT '[]',
+    in_schema BLOB,
+    out_schema BLOB,
+    schema_time DATETIME,
+    create_time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    update_time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE ("name", version)
+);
+
+CREATE INDEX plugins__name_idx ON plugins (name);
+CREATE INDEX plugins__create_time_idx ON plugins (create_time);
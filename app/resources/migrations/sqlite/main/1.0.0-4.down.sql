@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS scenes__state_idx;
+
+ALTER TABLE scenes DROP COLUMN state_time;
+ALTER TABLE scenes DROP COLUMN state_reason;
+ALTER TABLE scenes DROP COLUMN state;
@@ -0,0 +1,5 @@
+ALTER TABLE scenes ADD COLUMN state TEXT NOT NULL DEFAULT 'draft';
+ALTER TABLE scenes ADD COLUMN state_reason TEXT;
+ALTER TABLE scenes ADD COLUMN state_time DATETIME;
+
+CREATE INDEX scenes__state_idx ON scenes (state);
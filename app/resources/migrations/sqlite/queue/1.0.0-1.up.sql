@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS events
+(
+    id TEXT PRIMARY KEY,
+    scene_id TEXT NOT NULL,
+    edge_id TEXT NOT NULL,
+    target_plugin_id TEXT NOT NULL,
+    payload BLOB NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    create_time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX events__scene_id_idx ON events (scene_id, create_time);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS dead_letters
+(
+    id TEXT PRIMARY KEY,
+    scene_id TEXT NOT NULL,
+    edge_id TEXT NOT NULL,
+    target_plugin_id TEXT NOT NULL,
+    payload BLOB NOT NULL,
+    attempts INTEGER NOT NULL,
+    error TEXT NOT NULL,
+    create_time DATETIME NOT NULL,
+    dead_time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX dead_letters__scene_id_idx ON dead_letters (scene_id, dead_time);
+CREATE INDEX dead_letters__dead_time_idx ON dead_letters (dead_time);
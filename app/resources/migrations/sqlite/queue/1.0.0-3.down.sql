@@ -0,0 +1,5 @@
+ALTER TABLE dead_letters DROP COLUMN trace_id;
+ALTER TABLE dead_letters DROP COLUMN parent_id;
+
+ALTER TABLE events DROP COLUMN trace_id;
+ALTER TABLE events DROP COLUMN parent_id;
@@ -0,0 +1,5 @@
+ALTER TABLE events ADD COLUMN parent_id TEXT NOT NULL DEFAULT '';
+ALTER TABLE events ADD COLUMN trace_id TEXT NOT NULL DEFAULT '';
+
+ALTER TABLE dead_letters ADD COLUMN parent_id TEXT NOT NULL DEFAULT '';
+ALTER TABLE dead_letters ADD COLUMN trace_id TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS recorded_events;
+DROP TABLE IF EXISTS recordings;
@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS recordings
+(
+    id TEXT PRIMARY KEY,
+    scene_id TEXT NOT NULL,
+    "name" TEXT,
+    state TEXT NOT NULL,
+    start_time DATETIME NOT NULL,
+    stop_time DATETIME
+);
+
+CREATE INDEX recordings__scene_id_idx ON recordings (scene_id, start_time);
+CREATE INDEX recordings__start_time_idx ON recordings (start_time);
+
+CREATE TABLE IF NOT EXISTS recorded_events
+(
+    recording_id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    node_id TEXT NOT NULL,
+    plugin_id TEXT NOT NULL,
+    payload BLOB NOT NULL,
+    offset_micros INTEGER NOT NULL,
+    PRIMARY KEY (recording_id, seq)
+);
@@ -0,0 +1,194 @@
+use crate::modules::director::service::DirectorError;
+use crate::modules::recording::service::{ReplayRequest, ReplayState, ServiceError, Timing};
+use crate::modules::{director, recording};
+use clap::{Parser, Subcommand, ValueEnum};
+use flwrs_core::args::CmdArgs;
+use flwrs_core::registry;
+use std::time::Duration;
+use thiserror::Error;
+
+/// How often a command checks on the runtime while it waits for it.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Command given after the options of the runtime, e.g.
+/// `flwrs-app -c config.toml replay <recording-id>`. It runs once the runtime is up.
+#[derive(Parser)]
+#[command(name = "flwrs-app", bin_name = "flwrs-app", no_binary_name = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+pub(crate) enum Command {
+    /// Record the source events of a scene until the app stops.
+    Record {
+        scene_id: String,
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Replay a recording into a scene, starting the scene if it does not run. Once the scene
+    /// took every replayed event, the app stops.
+    Replay {
+        recording_id: String,
+        /// Scene to replay into. Defaults to the recorded scene.
+        #[arg(long)]
+        scene: Option<String>,
+        #[arg(long, value_enum, default_value_t = CliTiming::Original)]
+        timing: CliTiming,
+        /// Node of the scene to replay the events of a recorded node as, as
+        /// `<recorded-node-id>=<node-id>`.
+        #[arg(long = "map-node", value_parser = parse_mapping)]
+        map_node: Vec<(String, String)>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub(crate) enum CliTiming {
+    /// Keep the time that passed between the recorded events.
+    Original,
+    /// Replay the events as fast as the scene takes them.
+    Fast,
+}
+
+impl From<CliTiming> for Timing {
+    fn from(value: CliTiming) -> Self {
+        match value {
+            CliTiming::Original => Timing::Original,
+            CliTiming::Fast => Timing::Fast,
+        }
+    }
+}
+
+fn parse_mapping(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => {
+            Ok((from.to_string(), to.to_string()))
+        }
+        _ => Err(format!(
+            "expected <recorded-node-id>=<node-id>, got [{value}]"
+        )),
+    }
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum CliError {
+    #[error("director error: {0}")]
+    Director(#[from] DirectorError),
+    #[error("recording error: {0}")]
+    Recording(#[from] ServiceError),
+    #[error("replay [{0}] did not complete: {1}")]
+    Replay(String, String),
+}
+
+/// The command on the command line, if any. Exits with the usage if it is not a valid one.
+pub(crate) fn command() -> Option<Command> {
+    let args = CmdArgs::parse();
+    if args.command.is_empty() {
+        return None;
+    }
+    Some(Cli::parse_from(args.command).command)
+}
+
+/// Runs the command once the director restored the scenes. A failing command stops the app.
+pub(crate) async fn run(command: Command) -> Result<(), CliError> {
+    while !director::service().is_restored() {
+        if registry::shutting_down() {
+            return Ok(());
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    let result = match command {
+        Command::Record { scene_id, name } => record(scene_id, name).await,
+        Command::Replay {
+            recording_id,
+            scene,
+            timing,
+            map_node,
+        } => {
+            replay(
+                recording_id,
+                ReplayRequest {
+                    scene_id: scene,
+                    timing: timing.into(),
+                    node_map: map_node.into_iter().collect(),
+                },
+            )
+            .await
+        }
+    };
+    if let Err(e) = result.as_ref() {
+        log::error!("CLI: {e}");
+        registry::request_shutdown();
+    }
+    result
+}
+
+async fn record(scene_id: String, name: Option<String>) -> Result<(), CliError> {
+    let recording = recording::service()
+        .await
+        .start(scene_id.as_str(), name)
+        .await?;
+    log::info!(
+        "CLI: recording scene [{scene_id}] as [{id}] until the app stops",
+        id = recording.id
+    );
+    Ok(())
+}
+
+async fn replay(recording_id: String, request: ReplayRequest) -> Result<(), CliError> {
+    let recordings = recording::service().await;
+    let scene_id = match request.scene_id.as_ref() {
+        Some(scene_id) => scene_id.clone(),
+        None => recordings.get(recording_id.as_str()).await?.scene_id,
+    };
+    if !director::service().is_running(scene_id.as_str()).await {
+        log::info!("CLI: starting scene [{scene_id}] to replay into");
+        director::service().start(scene_id.as_str()).await?;
+    }
+    let replay = recordings
+        .replay(
+            recording_id.as_str(),
+            ReplayRequest {
+                scene_id: Some(scene_id.clone()),
+                ..request
+            },
+        )
+        .await?;
+    let replay = loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        if registry::shutting_down() {
+            return Ok(());
+        }
+        let replay = recordings.get_replay(replay.id.as_str())?;
+        if replay.state != ReplayState::Running {
+            break replay;
+        }
+    };
+    if replay.state != ReplayState::Completed {
+        return Err(CliError::Replay(
+            replay.id,
+            replay
+                .error
+                .unwrap_or_else(|| format!("{:?}", replay.state).to_lowercase()),
+        ));
+    }
+    log::info!(
+        "CLI: replayed [{replayed}] events of recording [{recording_id}] into scene [{scene_id}], dropped [{dropped}], waiting for the scene to take them",
+        replayed = replay.replayed,
+        dropped = replay.dropped
+    );
+    while director::service()
+        .queued(scene_id.as_str())
+        .await
+        .is_some_and(|queued| queued > 0)
+    {
+        if registry::shutting_down() {
+            return Ok(());
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    log::info!("CLI: replay [{id}] done, stopping", id = replay.id);
+    registry::request_shutdown();
+    Ok(())
+}
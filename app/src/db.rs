@@ -1,6 +1,6 @@
 use flwrs_core::db::migrations::MigrationDef;
 use flwrs_core::db::{build_db, Database, DbError};
-use rust_embed::Embed;
+use rust_embed::{Embed, RustEmbed};
 use std::sync::Arc;
 use tokio::sync::OnceCell;
 
@@ -8,7 +8,17 @@ use tokio::sync::OnceCell;
 #[folder = "resources/migrations/sqlite/main/"]
 struct MigrationDefs;
 
+#[derive(Embed)]
+#[folder = "resources/migrations/sqlite/queue/"]
+struct QueueMigrationDefs;
+
+#[derive(Embed)]
+#[folder = "resources/migrations/sqlite/recordings/"]
+struct RecordingMigrationDefs;
+
 static DB: OnceCell<Arc<Database>> = OnceCell::const_new();
+static QUEUE_DB: OnceCell<Arc<Database>> = OnceCell::const_new();
+static RECORDINGS_DB: OnceCell<Arc<Database>> = OnceCell::const_new();
 
 pub(crate) async fn main_db() -> &'static Database {
     DB.get_or_init(|| async {
@@ -24,21 +34,57 @@ pub(crate) async fn main_db() -> &'static Database {
     .await
 }
 
+/// Database of the durable event queue, kept apart from the main DB so queue traffic does
+/// not contend with scene and catalog writes.
+pub(crate) async fn queue_db() -> &'static Database {
+    QUEUE_DB.get_or_init(|| async {
+        let db = match build_named_db::<QueueMigrationDefs>("queue").await {
+            Ok(d) => d,
+            Err(e) => {
+                log::error!("Error: failed to initialise queue DB: {e}");
+                std::process::exit(1);
+            }
+        };
+        Arc::new(db)
+    })
+    .await
+}
+
+/// Database of recorded source events, a file of its own so recordings can be copied to
+/// another runtime and replayed there.
+pub(crate) async fn recordings_db() -> &'static Database {
+    RECORDINGS_DB.get_or_init(|| async {
+        let db = match build_named_db::<RecordingMigrationDefs>("recordings").await {
+            Ok(d) => d,
+            Err(e) => {
+                log::error!("Error: failed to initialise recordings DB: {e}");
+                std::process::exit(1);
+            }
+        };
+        Arc::new(db)
+    })
+    .await
+}
+
 async fn build_main_db() -> Result<Database, DbError> {
+    build_named_db::<MigrationDefs>("main").await
+}
+
+async fn build_named_db<M: RustEmbed>(name: &str) -> Result<Database, DbError> {
     let mut ups = vec![];
-    for file_name in MigrationDefs::iter().filter(|file_name| file_name.ends_with(".up.sql")) {
+    for file_name in M::iter().filter(|file_name| file_name.ends_with(".up.sql")) {
         ups.push(MigrationDef::new(
             file_name.to_string(),
-            MigrationDefs::get(file_name.as_ref()).unwrap(),
+            M::get(file_name.as_ref()).unwrap(),
         )?);
     }
     let mut downs = vec![];
-    for file_name in MigrationDefs::iter().filter(|file_name| file_name.ends_with(".down.sql")) {
+    for file_name in M::iter().filter(|file_name| file_name.ends_with(".down.sql")) {
         downs.push(MigrationDef::new(
             file_name.to_string(),
-            MigrationDefs::get(file_name.as_ref()).unwrap(),
+            M::get(file_name.as_ref()).unwrap(),
         )?);
     }
 
-    build_db("main", ups, downs, None).await
+    build_db(name, ups, downs, None).await
 }
@@ -1,4 +1,4 @@
-use crate::modules::scene;
+use crate::modules::{activity, catalog, hub, queue, recording, scene, supervisor};
 use flwrs_core::http::HttpServer;
 use axum::Router;
 use lazy_static::lazy_static;
@@ -8,7 +8,15 @@ use utoipa_swagger_ui::SwaggerUi;
 
 lazy_static! {
     static ref HTTP_SERVER: Arc<HttpServer> = Arc::new(HttpServer::new(
-        vec![scene::api::Api::build_router(),],
+        vec![
+            scene::api::Api::build_router(),
+            catalog::api::Api::build_router(),
+            hub::api::Api::build_router(),
+            supervisor::api::Api::build_router(),
+            queue::api::Api::build_router(),
+            activity::api::Api::build_router(),
+            recording::api::Api::build_router(),
+        ],
         Some(Router::new().merge(
             SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", OpenApiSpec::openapi())
         ))
@@ -23,6 +31,12 @@ pub fn server() -> &'static HttpServer {
 #[openapi(
     nest(
         (path = "/api/scenes", api = scene::api::Api),
+        (path = "/api/plugins", api = catalog::api::Api),
+        (path = "/api/hub", api = hub::api::Api),
+        (path = "/api/supervisor", api = supervisor::api::Api),
+        (path = "/api/dead-letters", api = queue::api::Api),
+        (path = "/api/activity", api = activity::api::Api),
+        (path = "/api/recordings", api = recording::api::Api),
     )
 )]
 struct OpenApiSpec;
@@ -1,3 +1,4 @@
+mod cli;
 mod db;
 mod modules;
 mod http;
@@ -8,6 +9,7 @@ use crate::registry::build_registry;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let command = cli::command();
     println!("current dir: {}", env::current_dir()?.to_str().unwrap()); // TODO clean this up
 
     let main_logger = flwrs_core::logging::main_logger();
@@ -15,7 +17,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     log::info!("Application is starting up...");
 
     let registry = build_registry().await;
+    let task = command.map(|command| tokio::spawn(cli::run(command)));
     registry.start().await?;
+    if let Some(task) = task {
+        if task.is_finished() {
+            task.await??;
+        } else {
+            task.abort();
+        }
+    }
 
     log::info!("Application stopped");
     Ok(())
@@ -1,2 +1,8 @@
 pub(crate) mod scene;
-mod director;
\ No newline at end of file
+pub(crate) mod catalog;
+pub(crate) mod director;
+pub(crate) mod hub;
+pub(crate) mod supervisor;
+pub(crate) mod queue;
+pub(crate) mod activity;
+pub(crate) mod recording;
\ No newline at end of file
@@ -0,0 +1,183 @@
+use crate::modules::activity;
+use crate::modules::activity::service::{Activity, ActivityKind};
+use crate::modules::queue::api::fields_json;
+use axum::extract::Query;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::get;
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+/// One piece of activity, sent as the data of a stream event named after its kind.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ActivityMessage {
+    pub scene_id: Option<String>,
+    /// Nodes the activity concerns: the nodes a plugin runs as, or both ends of an edge.
+    pub node_ids: Vec<String>,
+    pub plugin_id: Option<String>,
+    pub time: i64,
+    #[serde(flatten)]
+    pub details: ActivityDetails,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum ActivityDetails {
+    SceneState {
+        state: String,
+        reason: Option<String>,
+    },
+    PluginConnected {
+        session_id: String,
+        plugin_version: String,
+    },
+    PluginDisconnected {
+        session_id: String,
+    },
+    /// An event delivered along an edge. Edges are sampled, so not every event shows.
+    EdgeEvent {
+        edge_id: String,
+        from_node_id: String,
+        to_node_id: String,
+        event_id: String,
+        trace_id: String,
+        /// Event payload as a JSON object of its fields.
+        #[schema(value_type = Object)]
+        payload: serde_json::Value,
+    },
+    PluginLog {
+        level: String,
+        message: String,
+    },
+}
+
+impl ActivityDetails {
+    fn name(&self) -> &'static str {
+        match self {
+            ActivityDetails::SceneState { .. } => "scene_state",
+            ActivityDetails::PluginConnected { .. } => "plugin_connected",
+            ActivityDetails::PluginDisconnected { .. } => "plugin_disconnected",
+            ActivityDetails::EdgeEvent { .. } => "edge_event",
+            ActivityDetails::PluginLog { .. } => "plugin_log",
+        }
+    }
+}
+
+impl From<&Activity> for ActivityMessage {
+    fn from(value: &Activity) -> Self {
+        let details = match &value.kind {
+            ActivityKind::SceneState { state, reason } => ActivityDetails::SceneState {
+                state: state.to_string(),
+                reason: reason.clone(),
+            },
+            ActivityKind::PluginConnected {
+                session_id,
+                plugin_version,
+            } => ActivityDetails::PluginConnected {
+                session_id: session_id.clone(),
+                plugin_version: plugin_version.clone(),
+            },
+            ActivityKind::PluginDisconnected { session_id } => {
+                ActivityDetails::PluginDisconnected {
+                    session_id: session_id.clone(),
+                }
+            }
+            ActivityKind::EdgeEvent {
+                edge_id,
+                from_node_id,
+                to_node_id,
+                event_id,
+                trace_id,
+                payload,
+            } => ActivityDetails::EdgeEvent {
+                edge_id: edge_id.clone(),
+                from_node_id: from_node_id.clone(),
+                to_node_id: to_node_id.clone(),
+                event_id: event_id.clone(),
+                trace_id: trace_id.clone(),
+                payload: fields_json(payload),
+            },
+            ActivityKind::PluginLog { level, message } => ActivityDetails::PluginLog {
+                level: level.as_str().to_lowercase(),
+                message: message.clone(),
+            },
+        };
+        Self {
+            scene_id: value.scene_id.clone(),
+            node_ids: value.node_ids.clone(),
+            plugin_id: value.plugin_id.clone(),
+            time: value.time.timestamp_millis(),
+            details,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams, Clone)]
+pub(crate) struct StreamFilters {
+    /// Only activity of this scene.
+    pub scene_id: Option<String>,
+    /// Only activity concerning this node.
+    pub node_id: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/stream",
+    operation_id = "stream-activity",
+    description = "Stream scene state changes, plugin connects and disconnects, sampled \
+        events per edge and plugin log lines as server-sent events, named after their kind. \
+        A watcher falling behind gets a `lagged` event with the number of skipped messages.",
+    summary = "Stream live activity",
+    responses(
+        (status = 200, description = "Activity stream", content_type = "text/event-stream", body = ActivityMessage),
+    ),
+    params(
+        StreamFilters
+    )
+)]
+async fn stream_activity(
+    Query(filters): Query<StreamFilters>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    log::trace!(
+        "Activity API: watcher joined [scene: {scene_id:?}, node: {node_id:?}]",
+        scene_id = filters.scene_id,
+        node_id = filters.node_id
+    );
+    let stream =
+        BroadcastStream::new(activity::service().subscribe()).filter_map(move |activity| {
+            match activity {
+                Ok(activity) => activity
+                    .matches(filters.scene_id.as_deref(), filters.node_id.as_deref())
+                    .then(|| {
+                        let message = ActivityMessage::from(activity.as_ref());
+                        Event::default()
+                            .event(message.details.name())
+                            .json_data(&message)
+                    }),
+                Err(BroadcastStreamRecvError::Lagged(skipped)) => Some(Ok(Event::default()
+                    .event("lagged")
+                    .data(skipped.to_string()))),
+            }
+        });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Activity",
+        description = "Live activity of the runtime, for watching scenes at work",
+    ),
+    paths(stream_activity),
+    components(schemas(ActivityMessage, ActivityDetails))
+)]
+pub(crate) struct Api;
+
+impl Api {
+    pub(crate) fn build_router() -> Router {
+        Router::new().route("/activity/stream", get(stream_activity))
+    }
+}
@@ -0,0 +1,224 @@
+use crate::modules::director;
+use crate::modules::hub::session::Session;
+use crate::modules::scene::service::{Scene, SceneState};
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use flwrs_core::config;
+use flwrs_core::config::main_config;
+use flwrs_core::config::Source;
+use flwrs_core::registry;
+use flwrs_core::registry::RegistryError;
+use flwrs_plugin::schema::schema::PluginPayload;
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Deserialize)]
+pub(crate) struct ActivityConfig {
+    /// Activity kept for each watcher. A watcher falling further behind skips the oldest.
+    #[serde(default = "default_buffer_size")]
+    buffer_size: usize,
+    /// Least time between two events sampled from the same edge. `0` passes every event.
+    #[serde(default = "default_edge_sample_interval_millis")]
+    edge_sample_interval_millis: u64,
+}
+
+fn default_buffer_size() -> usize {
+    1024
+}
+
+fn default_edge_sample_interval_millis() -> u64 {
+    1000
+}
+
+impl ActivityConfig {
+    /// Reads the `[activity]` section, or the defaults if there is none. A section that fails
+    /// to parse is an error, rather than quietly running with the default buffer and sampling.
+    pub(crate) fn read() -> Result<Self, ActivityError> {
+        let main_config = main_config();
+        let path = ["activity".to_string()];
+        match config::read_struct(main_config, &path) {
+            Some(config) => Ok(config),
+            None if main_config.sub(&path).is_some() => Err(ActivityError::Config(
+                "section [activity] is invalid".to_string(),
+            )),
+            None => Ok(Self::defaults()),
+        }
+    }
+
+    fn defaults() -> Self {
+        Self {
+            buffer_size: default_buffer_size(),
+            edge_sample_interval_millis: default_edge_sample_interval_millis(),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum ActivityError {
+    #[error("invalid activity config: {0}")]
+    Config(String),
+}
+
+/// What happened.
+#[derive(Debug, Clone)]
+pub(crate) enum ActivityKind {
+    /// A scene moved to a new state.
+    SceneState {
+        state: SceneState,
+        reason: Option<String>,
+    },
+    PluginConnected {
+        session_id: String,
+        plugin_version: String,
+    },
+    PluginDisconnected {
+        session_id: String,
+    },
+    /// An event delivered along an edge, sampled.
+    EdgeEvent {
+        edge_id: String,
+        from_node_id: String,
+        to_node_id: String,
+        event_id: String,
+        trace_id: String,
+        payload: PluginPayload,
+    },
+    /// A line a plugin logged, or an error it reported.
+    PluginLog {
+        level: log::Level,
+        message: String,
+    },
+}
+
+/// Something that happened in the runtime, as shown to watchers.
+#[derive(Debug, Clone)]
+pub(crate) struct Activity {
+    pub kind: ActivityKind,
+    /// Scene it happened in. Plugins not part of a running scene have none.
+    pub scene_id: Option<String>,
+    /// Nodes it concerns: the nodes a plugin runs as, or both ends of an edge.
+    pub node_ids: Vec<String>,
+    pub plugin_id: Option<String>,
+    pub time: DateTime<Local>,
+}
+
+impl Activity {
+    /// Whether the activity passes the filters of a watcher. A node filter only passes
+    /// activity concerning that node.
+    pub(crate) fn matches(&self, scene_id: Option<&str>, node_id: Option<&str>) -> bool {
+        scene_id.is_none_or(|scene_id| self.scene_id.as_deref() == Some(scene_id))
+            && node_id.is_none_or(|node_id| self.node_ids.iter().any(|id| id == node_id))
+    }
+}
+
+/// Fans activity out to the watchers of the live activity stream. Nothing is kept: activity
+/// happening while no one watches is gone.
+pub(crate) struct Service {
+    config: ActivityConfig,
+    /// Why the configured activity section was rejected. The service then refuses to start.
+    config_error: Option<String>,
+    /// Taken at shutdown, which ends the streams of all watchers.
+    sender: Mutex<Option<broadcast::Sender<Arc<Activity>>>>,
+}
+
+impl Service {
+    pub(crate) fn new(config: Result<ActivityConfig, ActivityError>) -> Self {
+        let (config, config_error) = match config {
+            Ok(config) => (config, None),
+            Err(ActivityError::Config(reason)) => (ActivityConfig::defaults(), Some(reason)),
+        };
+        let (sender, _) = broadcast::channel(config.buffer_size.max(1));
+        Self {
+            config,
+            config_error,
+            sender: Mutex::new(Some(sender)),
+        }
+    }
+
+    /// Receives all activity from now on. The receiver closes when the runtime shuts down.
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<Arc<Activity>> {
+        match self.sender.lock().expect("activity lock poisoned").as_ref() {
+            Some(sender) => sender.subscribe(),
+            None => broadcast::channel(1).1,
+        }
+    }
+
+    /// Whether anyone watches. Activity is only put together while someone does.
+    pub(crate) fn is_watched(&self) -> bool {
+        self.sender
+            .lock()
+            .expect("activity lock poisoned")
+            .as_ref()
+            .is_some_and(|sender| sender.receiver_count() > 0)
+    }
+
+    pub(crate) fn edge_sample_interval(&self) -> Duration {
+        Duration::from_millis(self.config.edge_sample_interval_millis)
+    }
+
+    pub(crate) fn publish(&self, activity: Activity) {
+        if let Some(sender) = self.sender.lock().expect("activity lock poisoned").as_ref() {
+            // fails only while no one watches
+            let _ = sender.send(Arc::new(activity));
+        }
+    }
+
+    pub(crate) fn scene_state(&self, scene: &Scene) {
+        if !self.is_watched() {
+            return;
+        }
+        self.publish(Activity {
+            kind: ActivityKind::SceneState {
+                state: scene.state,
+                reason: scene.state_reason.clone(),
+            },
+            scene_id: Some(scene.id.clone()),
+            node_ids: vec![],
+            plugin_id: None,
+            time: Local::now(),
+        });
+    }
+
+    /// Publishes activity of the plugin of a session, placed in the running scene and nodes
+    /// the plugin runs as.
+    pub(crate) async fn plugin_activity(&self, session: &Session, kind: ActivityKind) {
+        if !self.is_watched() {
+            return;
+        }
+        let (scene_id, node_ids) = match director::service().locate(session).await {
+            Some((scene_id, node_ids)) => (Some(scene_id), node_ids),
+            None => (None, vec![]),
+        };
+        self.publish(Activity {
+            kind,
+            scene_id,
+            node_ids,
+            plugin_id: Some(session.plugin_id.clone()),
+            time: Local::now(),
+        });
+    }
+}
+
+#[async_trait]
+impl registry::Service for Service {
+    fn id(&self) -> String {
+        "activity-service".to_string()
+    }
+
+    async fn start(&self, shutdown_token: CancellationToken) -> Result<(), RegistryError> {
+        if let Some(error) = &self.config_error {
+            return Err(RegistryError::ServiceError {
+                msg: "Activity config error".to_string(),
+                source: Box::new(ActivityError::Config(error.clone())),
+            });
+        }
+        shutdown_token.cancelled().await;
+        log::debug!("Activity: closing live activity streams.");
+        self.sender.lock().expect("activity lock poisoned").take();
+        Ok(())
+    }
+}
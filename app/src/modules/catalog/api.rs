@@ -0,0 +1,383 @@
+use crate::modules::catalog;
+use crate::modules::catalog::service::ServiceError;
+use crate::modules::hub::api::{schema_fields, SchemaField};
+use crate::modules::scene::api::Type;
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json as DbJson;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct Plugin {
+    pub id: String,
+    pub name: String,
+    pub plugin_type: Type,
+    pub version: String,
+    /// `name@version` reference to use in scene nodes.
+    pub reference: String,
+    pub executable: String,
+    pub args: Vec<String>,
+    pub in_schema: Option<Vec<SchemaField>>,
+    pub out_schema: Option<Vec<SchemaField>>,
+    pub schema_time: Option<i64>,
+    pub create_time: i64,
+    pub update_time: i64,
+}
+
+impl From<catalog::service::Plugin> for Plugin {
+    fn from(value: catalog::service::Plugin) -> Self {
+        Self {
+            reference: value.reference(),
+            in_schema: schema_fields(&value.in_schema()),
+            out_schema: schema_fields(&value.out_schema()),
+            id: value.id,
+            name: value.name,
+            plugin_type: Type::from(value.plugin_type),
+            version: value.version,
+            executable: value.executable,
+            args: value.args.0,
+            schema_time: value.schema_time.map(|time| time.timestamp_millis()),
+            create_time: value.create_time.timestamp_millis(),
+            update_time: value.update_time.timestamp_millis(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct PluginRequest {
+    pub name: String,
+    pub plugin_type: Type,
+    pub version: String,
+    pub executable: String,
+    pub args: Option<Vec<String>>,
+}
+
+impl PluginRequest {
+    fn into_plugin(self, id: String) -> catalog::service::Plugin {
+        catalog::service::Plugin {
+            id,
+            name: self.name,
+            plugin_type: self.plugin_type.into(),
+            version: self.version,
+            executable: self.executable,
+            args: DbJson(self.args.unwrap_or_default()),
+            in_schema: None,
+            out_schema: None,
+            schema_time: None,
+            create_time: Local::now(),
+            update_time: Local::now(),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/by-id/{id}",
+    operation_id = "get-plugin",
+    description = "Get catalog plugin by ID",
+    summary = "Get plugin by ID",
+    responses(
+        (status = 200, description = "Plugin", body = Plugin),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the plugin to fetch")
+    )
+)]
+async fn get_plugin(Path(id): Path<String>) -> Result<Json<Plugin>, StatusCode> {
+    log::trace!("Plugins API: getting plugin [{id}]");
+    match catalog::service().await.get_plugin(id.as_str()).await {
+        Ok(plugin) => Ok(Json(Plugin::from(plugin))),
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Plugins API: Failed to get plugin [{id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            _ => {
+                log::error!("Plugins API: Failed to get plugin [{id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "",
+    operation_id = "create-plugin",
+    description = "Register an installed plugin in the catalog",
+    summary = "Register plugin",
+    request_body(
+        content = PluginRequest,
+        description = "Plugin registration request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Registered plugin", body = Plugin),
+        (status = 400, description = "Bad request"),
+        (status = 409, description = "Conflict"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+)]
+async fn create_plugin(Json(plugin): Json<PluginRequest>) -> Result<Json<Plugin>, StatusCode> {
+    log::trace!("Plugins API: registering plugin");
+    match catalog::service()
+        .await
+        .create_plugin(plugin.into_plugin("".to_string()))
+        .await
+    {
+        Ok(plugin) => {
+            log::trace!("Plugins API: registered plugin [{id}]", id = plugin.id);
+            Ok(Json(Plugin::from(plugin)))
+        }
+        Err(e) => match e {
+            ServiceError::Conflict => {
+                log::trace!("Plugins API: Failed to register plugin: conflict");
+                Err(StatusCode::CONFLICT)
+            }
+            ServiceError::Invalid(_) => {
+                log::trace!("Plugins API: Failed to register plugin: {e}");
+                Err(StatusCode::BAD_REQUEST)
+            }
+            _ => {
+                log::error!("Plugins API: Failed to register plugin: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct IntrospectRequest {
+    pub executable: String,
+    pub args: Option<Vec<String>>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/introspect",
+    operation_id = "introspect-plugin",
+    description = "Register a plugin from the manifest its binary prints in describe mode",
+    summary = "Register plugin from its manifest",
+    request_body(
+        content = IntrospectRequest,
+        description = "Plugin binary to introspect",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Registered plugin", body = Plugin),
+        (status = 400, description = "Bad request"),
+        (status = 409, description = "Conflict"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+)]
+async fn introspect_plugin(
+    Json(request): Json<IntrospectRequest>,
+) -> Result<Json<Plugin>, StatusCode> {
+    log::trace!(
+        "Plugins API: introspecting plugin [{executable}]",
+        executable = request.executable
+    );
+    match catalog::service()
+        .await
+        .introspect_plugin(
+            request.executable.as_str(),
+            request.args.unwrap_or_default(),
+        )
+        .await
+    {
+        Ok(plugin) => {
+            log::trace!("Plugins API: registered plugin [{id}]", id = plugin.id);
+            Ok(Json(Plugin::from(plugin)))
+        }
+        Err(e) => match e {
+            ServiceError::Conflict => {
+                log::trace!("Plugins API: Failed to introspect plugin: conflict");
+                Err(StatusCode::CONFLICT)
+            }
+            ServiceError::Invalid(_) => {
+                log::trace!("Plugins API: Failed to introspect plugin: {e}");
+                Err(StatusCode::BAD_REQUEST)
+            }
+            _ => {
+                log::error!("Plugins API: Failed to introspect plugin: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/by-id/{id}",
+    operation_id = "update-plugin",
+    description = "Update a catalog plugin by ID",
+    summary = "Update plugin by ID",
+    request_body(
+        content = PluginRequest,
+        description = "Plugin update request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Updated plugin", body = Plugin),
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Conflict"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the plugin to update")
+    )
+)]
+async fn update_plugin(
+    Path(id): Path<String>,
+    Json(plugin): Json<PluginRequest>,
+) -> Result<Json<Plugin>, StatusCode> {
+    log::trace!("Plugins API: updating plugin [{id}]");
+    match catalog::service()
+        .await
+        .update_plugin(plugin.into_plugin(id.clone()))
+        .await
+    {
+        Ok(plugin) => Ok(Json(Plugin::from(plugin))),
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Plugins API: Failed to update plugin [{id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            ServiceError::Conflict => {
+                log::trace!("Plugins API: Failed to update plugin [{id}]: conflict");
+                Err(StatusCode::CONFLICT)
+            }
+            ServiceError::Invalid(_) => {
+                log::trace!("Plugins API: Failed to update plugin [{id}]: {e}");
+                Err(StatusCode::BAD_REQUEST)
+            }
+            _ => {
+                log::error!("Plugins API: Failed to update plugin [{id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/by-id/{id}",
+    operation_id = "delete-plugin",
+    description = "Remove a plugin from the catalog by ID",
+    summary = "Delete plugin by ID",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the plugin to delete")
+    )
+)]
+async fn delete_plugin(Path(id): Path<String>) -> Result<StatusCode, StatusCode> {
+    log::trace!("Plugins API: deleting plugin [{id}]");
+    match catalog::service().await.delete_plugin(id.as_str()).await {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Plugins API: Failed to delete plugin [{id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            _ => {
+                log::error!("Plugins API: Failed to delete plugin [{id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+const DEFAULT_LIMIT: u32 = 50;
+
+#[derive(Deserialize, IntoParams, Clone)]
+pub(crate) struct ListFilters {
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+impl From<ListFilters> for catalog::service::ListFilters {
+    fn from(value: ListFilters) -> Self {
+        catalog::service::ListFilters::new(
+            i64::from(value.offset.unwrap_or(0)),
+            i64::from(value.limit.unwrap_or(DEFAULT_LIMIT)),
+        )
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ListPluginsResponse {
+    plugins: Vec<Plugin>,
+    has_more: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "",
+    operation_id = "list-plugins",
+    description = "List catalog plugins (paginated)",
+    summary = "List plugins (paginated)",
+    responses(
+        (status = 200, description = "Plugin page", body = ListPluginsResponse),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ListFilters
+    )
+)]
+async fn list_plugins(
+    Query(filters): Query<ListFilters>,
+) -> Result<Json<ListPluginsResponse>, StatusCode> {
+    log::trace!(
+        "Plugins API: listing plugins [{offset}:{limit}]",
+        offset = filters.offset.unwrap_or(0),
+        limit = filters.limit.unwrap_or(DEFAULT_LIMIT)
+    );
+    match catalog::service().await.list_plugins(filters.into()).await {
+        Ok((plugins, has_more)) => Ok(Json(ListPluginsResponse {
+            plugins: plugins.into_iter().map(From::from).collect(),
+            has_more,
+        })),
+        Err(e) => {
+            log::error!("Plugins API: Failed to list plugins: {e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Plugins", description = "Plugin catalog API",),
+    paths(
+        list_plugins,
+        create_plugin,
+        introspect_plugin,
+        get_plugin,
+        update_plugin,
+        delete_plugin,
+    ),
+    components(schemas(Plugin, PluginRequest, IntrospectRequest, ListPluginsResponse,))
+)]
+pub(crate) struct Api;
+
+impl Api {
+    pub(crate) fn build_router() -> Router {
+        Router::new()
+            .route("/plugins", get(list_plugins).post(create_plugin))
+            .route("/plugins/introspect", post(introspect_plugin))
+            .route(
+                "/plugins/by-id/{id}",
+                get(get_plugin).put(update_plugin).delete(delete_plugin),
+            )
+    }
+}
@@ -0,0 +1,341 @@
+mod query_sqlite;
+
+use crate::modules::scene::service::NodeType;
+use chrono::Local;
+use flwrs_core::db::{Database, DbError};
+use flwrs_plugin::plugin::manifest::{Manifest, ManifestPluginType, DESCRIBE_FLAG};
+use flwrs_plugin::schema::schema::SchemaDefinition;
+use prost::Message;
+use sqlx::types::Json;
+use std::process::Stdio;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::process::Command;
+use ulid::Ulid;
+
+/// How long a plugin binary gets to print its manifest.
+const DESCRIBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An installed plugin binary, identified by its name and version.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub(crate) struct Plugin {
+    pub id: String,
+    pub name: String,
+    pub plugin_type: NodeType,
+    pub version: String,
+    pub executable: String,
+    pub args: Json<Vec<String>>,
+    /// Last in-schema announced by the plugin, protobuf encoded.
+    pub in_schema: Option<Vec<u8>>,
+    /// Last out-schema announced by the plugin, protobuf encoded.
+    pub out_schema: Option<Vec<u8>>,
+    pub schema_time: Option<chrono::DateTime<Local>>,
+    pub create_time: chrono::DateTime<Local>,
+    pub update_time: chrono::DateTime<Local>,
+}
+
+impl Plugin {
+    pub(crate) fn in_schema(&self) -> Option<SchemaDefinition> {
+        decode_schema(self.in_schema.as_deref())
+    }
+
+    pub(crate) fn out_schema(&self) -> Option<SchemaDefinition> {
+        decode_schema(self.out_schema.as_deref())
+    }
+
+    /// The `name@version` reference scenes use to point at this plugin.
+    pub(crate) fn reference(&self) -> String {
+        format!("{name}@{version}", name = self.name, version = self.version)
+    }
+}
+
+fn decode_schema(bytes: Option<&[u8]>) -> Option<SchemaDefinition> {
+    bytes.and_then(|bytes| SchemaDefinition::decode(bytes).ok())
+}
+
+/// Splits a `name@version` plugin reference. The version is empty if the reference does
+/// not carry one.
+pub(crate) fn parse_reference(reference: &str) -> (&str, &str) {
+    reference.split_once('@').unwrap_or((reference, ""))
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum ServiceError {
+    #[error("no plugin found")]
+    NotFound,
+    #[error("conflict")]
+    Conflict,
+    #[error("invalid input: {0}")]
+    Invalid(String),
+    #[error("failed to execute query: {0}")]
+    Query(sqlx::Error),
+    #[error("failed to get connection: {0}")]
+    Connection(#[from] DbError),
+    #[error("unknown error occurred: {0}")]
+    Unknown(String),
+}
+
+pub(crate) struct ListFilters {
+    pub(self) offset: i64,
+    pub(self) limit: i64,
+}
+
+impl ListFilters {
+    pub(crate) fn new(offset: i64, limit: i64) -> Self {
+        Self { offset, limit }
+    }
+}
+
+pub(crate) struct Service {
+    db: &'static Database,
+}
+
+impl Service {
+    pub(crate) fn new(db: &'static Database) -> Self {
+        Self { db }
+    }
+
+    pub(crate) async fn get_plugin(&self, id: &str) -> Result<Plugin, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(query_sqlite::get_plugin(&mut conn, id).await?)
+            }
+        }
+    }
+
+    /// Resolves a plugin by name and version. An empty version resolves to the most
+    /// recently registered version of the plugin.
+    pub(crate) async fn find_plugin(
+        &self,
+        name: &str,
+        version: &str,
+    ) -> Result<Plugin, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                let plugin = if version.is_empty() {
+                    query_sqlite::find_latest_plugin(&mut conn, name).await?
+                } else {
+                    query_sqlite::find_plugin(&mut conn, name, version).await?
+                };
+                Ok(plugin)
+            }
+        }
+    }
+
+    pub(crate) async fn create_plugin(&self, plugin: Plugin) -> Result<Plugin, ServiceError> {
+        log::debug!(
+            "Catalog Service: registering plugin [{name}@{version}]",
+            name = plugin.name,
+            version = plugin.version
+        );
+        validate_plugin(&plugin)?;
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                let input = Plugin {
+                    id: Ulid::new().to_string(),
+                    in_schema: None,
+                    out_schema: None,
+                    schema_time: None,
+                    create_time: Local::now(),
+                    update_time: Local::now(),
+                    ..plugin
+                };
+                match query_sqlite::create_plugin(&mut conn, input).await {
+                    Ok(output) => Ok(output),
+                    Err(e) => {
+                        let svc_error = ServiceError::from(e);
+                        if matches!(svc_error, ServiceError::NotFound) {
+                            Err(ServiceError::Unknown(
+                                "insert returned empty result".to_string(),
+                            ))
+                        } else {
+                            Err(svc_error)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn update_plugin(&self, plugin: Plugin) -> Result<Plugin, ServiceError> {
+        validate_plugin(&plugin)?;
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                let input = Plugin {
+                    update_time: Local::now(),
+                    ..plugin
+                };
+                Ok(query_sqlite::update_plugin(&mut conn, input).await?)
+            }
+        }
+    }
+
+    pub(crate) async fn delete_plugin(&self, id: &str) -> Result<(), ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                match query_sqlite::delete_plugin(&mut conn, id).await? {
+                    0 => Err(ServiceError::NotFound),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn list_plugins(
+        &self,
+        filters: ListFilters,
+    ) -> Result<(Vec<Plugin>, bool), ServiceError> {
+        let input = ListFilters {
+            offset: filters.offset,
+            limit: filters.limit + 1,
+        };
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                let plugins = query_sqlite::list_plugins(&mut conn, input).await?;
+                let has_more = plugins.len() > filters.limit as usize;
+                Ok((
+                    plugins.into_iter().take(filters.limit as usize).collect(),
+                    has_more,
+                ))
+            }
+        }
+    }
+
+    /// Registers a plugin from the manifest its binary prints in describe mode, without
+    /// launching it against the hub. The declared schemas are recorded right away.
+    pub(crate) async fn introspect_plugin(
+        &self,
+        executable: &str,
+        args: Vec<String>,
+    ) -> Result<Plugin, ServiceError> {
+        let manifest = describe(executable, args.as_slice()).await?;
+        let (in_schema, out_schema) = match (manifest.in_schema(), manifest.out_schema()) {
+            (Ok(in_schema), Ok(out_schema)) => (in_schema, out_schema),
+            (Err(e), _) | (_, Err(e)) => return Err(ServiceError::Invalid(e.to_string())),
+        };
+        let plugin = self
+            .create_plugin(Plugin {
+                id: "".to_string(),
+                name: manifest.id,
+                plugin_type: manifest.plugin_type.into(),
+                version: manifest.version,
+                executable: executable.to_string(),
+                args: Json(args),
+                in_schema: None,
+                out_schema: None,
+                schema_time: None,
+                create_time: Local::now(),
+                update_time: Local::now(),
+            })
+            .await?;
+        self.record_schema(
+            plugin.name.as_str(),
+            plugin.version.as_str(),
+            in_schema.as_ref(),
+            out_schema.as_ref(),
+        )
+        .await?;
+        self.get_plugin(plugin.id.as_str()).await
+    }
+
+    /// Stores the schemas a plugin announced in its handshake. Plugins missing from the
+    /// catalog are left alone.
+    pub(crate) async fn record_schema(
+        &self,
+        name: &str,
+        version: &str,
+        in_schema: Option<&SchemaDefinition>,
+        out_schema: Option<&SchemaDefinition>,
+    ) -> Result<(), ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                let updated = query_sqlite::update_schema(
+                    &mut conn,
+                    name,
+                    version,
+                    in_schema.map(Message::encode_to_vec),
+                    out_schema.map(Message::encode_to_vec),
+                    Local::now(),
+                )
+                .await?;
+                if updated == 0 {
+                    log::trace!(
+                        "Catalog Service: plugin [{name}@{version}] is not in the catalog, schema not recorded"
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Runs the plugin binary in describe mode and parses the manifest it prints.
+async fn describe(executable: &str, args: &[String]) -> Result<Manifest, ServiceError> {
+    let output = Command::new(executable)
+        .args(args.iter())
+        .arg(DESCRIBE_FLAG)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = match tokio::time::timeout(DESCRIBE_TIMEOUT, output).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            return Err(ServiceError::Invalid(format!(
+                "failed to run [{executable}]: {e}"
+            )));
+        }
+        Err(_) => {
+            return Err(ServiceError::Invalid(format!(
+                "[{executable}] did not describe itself within [{DESCRIBE_TIMEOUT:?}]"
+            )));
+        }
+    };
+    if !output.status.success() {
+        return Err(ServiceError::Invalid(format!(
+            "[{executable}] exited with [{status}]: {stderr}",
+            status = output.status,
+            stderr = String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Manifest::from_toml(String::from_utf8_lossy(&output.stdout).as_ref())
+        .map_err(|e| ServiceError::Invalid(format!("[{executable}]: {e}")))
+}
+
+impl From<ManifestPluginType> for NodeType {
+    fn from(value: ManifestPluginType) -> Self {
+        match value {
+            ManifestPluginType::Source => NodeType::Source,
+            ManifestPluginType::Transform => NodeType::Transform,
+            ManifestPluginType::Sink => NodeType::Sink,
+        }
+    }
+}
+
+fn validate_plugin(plugin: &Plugin) -> Result<(), ServiceError> {
+    if plugin.name.is_empty() {
+        return Err(ServiceError::Invalid("plugin name is missing".to_string()));
+    }
+    if plugin.name.contains('@') {
+        return Err(ServiceError::Invalid(
+            "plugin name cannot contain '@'".to_string(),
+        ));
+    }
+    if plugin.version.is_empty() {
+        return Err(ServiceError::Invalid(
+            "plugin version is missing".to_string(),
+        ));
+    }
+    if plugin.executable.is_empty() {
+        return Err(ServiceError::Invalid(
+            "plugin executable is missing".to_string(),
+        ));
+    }
+    Ok(())
+}
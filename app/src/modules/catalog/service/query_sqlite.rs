@@ -0,0 +1,167 @@
+use crate::modules::catalog::service::{ListFilters, Plugin, ServiceError};
+use chrono::{DateTime, Local};
+use sqlx::error::ErrorKind;
+use sqlx::pool::PoolConnection;
+use sqlx::{Executor, FromRow, Sqlite};
+
+impl From<sqlx::Error> for ServiceError {
+    fn from(error: sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::RowNotFound => ServiceError::NotFound,
+            _ => match error.as_database_error() {
+                None => ServiceError::Query(error),
+                Some(e) => match e.kind() {
+                    ErrorKind::UniqueViolation => ServiceError::Conflict,
+                    ErrorKind::ForeignKeyViolation => ServiceError::Conflict,
+                    ErrorKind::CheckViolation => ServiceError::Conflict,
+                    _ => ServiceError::Query(error),
+                },
+            },
+        }
+    }
+}
+
+pub(super) async fn get_plugin(
+    conn: &mut PoolConnection<Sqlite>,
+    id: &str,
+) -> Result<Plugin, sqlx::Error> {
+    let row = conn
+        .fetch_one(sqlx::query_as::<Sqlite, Plugin>("SELECT * FROM plugins WHERE id = $1").bind(id))
+        .await?;
+    let plugin = Plugin::from_row(&row)?;
+    Ok(plugin)
+}
+
+pub(super) async fn find_plugin(
+    conn: &mut PoolConnection<Sqlite>,
+    name: &str,
+    version: &str,
+) -> Result<Plugin, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, Plugin>(
+                "SELECT * FROM plugins WHERE name = $1 AND version = $2",
+            )
+            .bind(name)
+            .bind(version),
+        )
+        .await?;
+    let plugin = Plugin::from_row(&row)?;
+    Ok(plugin)
+}
+
+pub(super) async fn find_latest_plugin(
+    conn: &mut PoolConnection<Sqlite>,
+    name: &str,
+) -> Result<Plugin, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, Plugin>(
+                "SELECT * FROM plugins WHERE name = $1 ORDER BY create_time DESC, id DESC LIMIT 1",
+            )
+            .bind(name),
+        )
+        .await?;
+    let plugin = Plugin::from_row(&row)?;
+    Ok(plugin)
+}
+
+pub(super) async fn create_plugin(
+    conn: &mut PoolConnection<Sqlite>,
+    plugin: Plugin,
+) -> Result<Plugin, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, Plugin>(
+                "INSERT INTO plugins (id, name, plugin_type, version, executable, args) \
+                    VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+            )
+            .bind(plugin.id)
+            .bind(plugin.name)
+            .bind(plugin.plugin_type)
+            .bind(plugin.version)
+            .bind(plugin.executable)
+            .bind(plugin.args),
+        )
+        .await?;
+    let plugin = Plugin::from_row(&row)?;
+    Ok(plugin)
+}
+
+pub(super) async fn update_plugin(
+    conn: &mut PoolConnection<Sqlite>,
+    plugin: Plugin,
+) -> Result<Plugin, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, Plugin>(
+                "UPDATE plugins SET name = $1, plugin_type = $2, version = $3, executable = $4, \
+                    args = $5, update_time = $6 WHERE id = $7 RETURNING *",
+            )
+            .bind(plugin.name)
+            .bind(plugin.plugin_type)
+            .bind(plugin.version)
+            .bind(plugin.executable)
+            .bind(plugin.args)
+            .bind(plugin.update_time)
+            .bind(plugin.id),
+        )
+        .await?;
+    let plugin = Plugin::from_row(&row)?;
+    Ok(plugin)
+}
+
+pub(super) async fn update_schema(
+    conn: &mut PoolConnection<Sqlite>,
+    name: &str,
+    version: &str,
+    in_schema: Option<Vec<u8>>,
+    out_schema: Option<Vec<u8>>,
+    schema_time: DateTime<Local>,
+) -> Result<u64, sqlx::Error> {
+    let result = conn
+        .execute(
+            sqlx::query(
+                "UPDATE plugins SET in_schema = $1, out_schema = $2, schema_time = $3 \
+                    WHERE name = $4 AND version = $5",
+            )
+            .bind(in_schema)
+            .bind(out_schema)
+            .bind(schema_time)
+            .bind(name)
+            .bind(version),
+        )
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub(super) async fn delete_plugin(
+    conn: &mut PoolConnection<Sqlite>,
+    id: &str,
+) -> Result<u64, sqlx::Error> {
+    let result = conn
+        .execute(sqlx::query("DELETE FROM plugins WHERE id = $1").bind(id))
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub(super) async fn list_plugins(
+    conn: &mut PoolConnection<Sqlite>,
+    filters: ListFilters,
+) -> Result<Vec<Plugin>, sqlx::Error> {
+    let rows = conn
+        .fetch_all(
+            sqlx::query_as::<Sqlite, Plugin>(
+                "SELECT * FROM plugins ORDER BY name, create_time DESC LIMIT $1 OFFSET $2",
+            )
+            .bind(filters.limit)
+            .bind(filters.offset),
+        )
+        .await?;
+    let mut plugins = vec![];
+    for row in rows {
+        plugins.push(Plugin::from_row(&row)?);
+    }
+
+    Ok(plugins)
+}
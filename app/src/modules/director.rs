@@ -1 +1,14 @@
-mod service;
\ No newline at end of file
+use crate::modules::director::service::{DirectorConfig, Service};
+use lazy_static::lazy_static;
+use std::sync::Arc;
+
+pub(crate) mod router;
+pub(crate) mod service;
+
+lazy_static! {
+    static ref SERVICE: Arc<Service> = Arc::new(Service::new(DirectorConfig::read()));
+}
+
+pub(crate) fn service() -> &'static Service {
+    SERVICE.as_ref()
+}
@@ -0,0 +1,1071 @@
+use crate::modules::activity::service::{Activity, ActivityKind};
+use crate::modules::hub::lineage::{Hop, HopKind};
+use crate::modules::hub::session::{RuntimeMessage, Session, Settlement};
+use crate::modules::queue::service::{DeadLetter, QueueLimits, QueuedEvent, ServiceError};
+use crate::modules::scene::service::{Edge, Node};
+use crate::modules::{activity, hub, queue};
+use chrono::{DateTime, Local};
+use flwrs_core::metrics;
+use flwrs_plugin::schema::common::plugin_type::Enum as PluginType;
+use flwrs_plugin::schema::schema::PluginPayload;
+use flwrs_plugin::schema::sink::{
+    runtime_sink_message::Payload as RuntimeSinkPayload, RuntimeSinkMessage, SinkEvent,
+};
+use flwrs_plugin::schema::transform::{
+    runtime_transform_message::Payload as RuntimeTransformPayload, RuntimeTransformMessage,
+    TransformEvent,
+};
+use lazy_static::lazy_static;
+use prometheus::{IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry};
+use prost::Message;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use ulid::Ulid;
+
+/// How often a lane looks for its target plugin while the plugin is not connected.
+const RECONNECT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+lazy_static! {
+    static ref NODE_EVENTS_OUT: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "scene_node_events_out_total",
+            "Events emitted by the plugin of a scene node and routed along its edges",
+        ),
+        &["scene_id", "node_id"],
+    )
+    .expect("node events out metric is valid");
+    static ref NODE_EVENTS_IN: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "scene_node_events_in_total",
+            "Events acked by the plugin of a scene node",
+        ),
+        &["scene_id", "node_id"],
+    )
+    .expect("node events in metric is valid");
+    static ref QUEUE_DEPTH: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "scene_queue_events",
+            "Events of a scene in the durable queue, not acked yet",
+        ),
+        &["scene_id"],
+    )
+    .expect("queue depth metric is valid");
+    static ref EDGE_QUEUE_DEPTH: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "scene_edge_queue_events",
+            "Events waiting in the queue of an edge for the target plugin to take them",
+        ),
+        &["scene_id", "edge_id"],
+    )
+    .expect("edge queue depth metric is valid");
+}
+
+/// Adds the routing metrics to the registry.
+pub(crate) fn register_metrics(registry: &Registry) {
+    metrics::register(registry, NODE_EVENTS_OUT.clone());
+    metrics::register(registry, NODE_EVENTS_IN.clone());
+    metrics::register(registry, QUEUE_DEPTH.clone());
+    metrics::register(registry, EDGE_QUEUE_DEPTH.clone());
+}
+
+/// Tracks one event emitted by a plugin. Every edge the event travels on holds a clone; once
+/// all of them are done with it (acked, dropped or discarded), one credit goes back to the
+/// emitting plugin.
+pub(crate) struct Ticket {
+    source: Arc<Session>,
+    batch: u32,
+}
+
+impl Ticket {
+    pub(crate) fn new(source: Arc<Session>, batch: u32) -> Arc<Self> {
+        Arc::new(Self { source, batch })
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        self.source.return_credit(self.batch);
+    }
+}
+
+/// How events are redelivered until the target plugin acks them.
+#[derive(Clone, Copy)]
+pub(crate) struct DeliverySettings {
+    /// Time a plugin has to ack or nack an event before it is delivered again.
+    pub ack_timeout: Duration,
+    /// Deliveries of one event, after which it is given up on.
+    pub max_attempts: u32,
+    /// Wait before delivering a nacked or timed out event again.
+    pub retry_backoff: Duration,
+}
+
+impl DeliverySettings {
+    /// Whether an event whose delivery failed the given number of times is given up on.
+    fn gives_up(&self, attempts: u32) -> bool {
+        attempts >= self.max_attempts
+    }
+}
+
+/// Lineage of an event emitted by a plugin, which the copies routed along its edges descend
+/// from.
+pub(crate) struct Origin<'a> {
+    pub event_id: &'a str,
+    pub trace_id: &'a str,
+}
+
+/// An event on its way along one edge. The event ID stays the same across redeliveries, so
+/// plugins can tell a redelivered event from a new one.
+struct Delivery {
+    event_id: String,
+    /// Event the plugin emitted, which this one is a copy of.
+    parent_id: String,
+    trace_id: String,
+    payload: PluginPayload,
+    /// Held until the event is acked or given up on. Events replayed from the durable queue
+    /// have none, their plugin session is long gone.
+    _ticket: Option<Arc<Ticket>>,
+    attempts: u32,
+    /// When the event was first routed.
+    create_time: DateTime<Local>,
+}
+
+/// Why the copies of an event were not written to the durable queue.
+#[derive(Error, Debug)]
+enum AppendError {
+    #[error("queue holds [{0}] events")]
+    Full(u64),
+    #[error("failed to persist event: {0}")]
+    Store(#[from] ServiceError),
+}
+
+/// Durable copy of the events of a scene that are not acked yet. Keeps count of them, so the
+/// size limit is checked without asking the database.
+struct Store {
+    scene_id: String,
+    limits: QueueLimits,
+    stored: AtomicU64,
+    depth: IntGauge,
+}
+
+impl Store {
+    fn report_depth(&self) {
+        self.depth.set(self.stored.load(Ordering::Acquire) as i64);
+    }
+
+    /// Writes the copies of an event before they are queued, taking room for them first.
+    /// Fails if the scene has no room left for them or they could not be written: no event is
+    /// routed without its durable copy.
+    async fn append(&self, events: &[QueuedEvent]) -> Result<(), AppendError> {
+        let count = events.len() as u64;
+        let max_events = self.limits.max_events;
+        self.stored
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |stored| {
+                stored
+                    .checked_add(count)
+                    .filter(|stored| *stored <= max_events)
+            })
+            .map_err(|_| AppendError::Full(max_events))?;
+        if let Err(e) = queue::service().await.append(events).await {
+            self.stored.fetch_sub(count, Ordering::AcqRel);
+            return Err(e.into());
+        }
+        self.report_depth();
+        Ok(())
+    }
+
+    async fn remove(&self, event_id: &str) {
+        match queue::service().await.remove(event_id).await {
+            Ok(_) => {
+                self.stored.fetch_sub(1, Ordering::AcqRel);
+                self.report_depth();
+            }
+            Err(queue::service::ServiceError::NotFound) => {}
+            Err(e) => log::error!(
+                "Router: scene [{id}]: failed to remove event [{event_id}] from queue: {e}",
+                id = self.scene_id
+            ),
+        }
+    }
+
+    async fn record_attempt(&self, event_id: &str, attempts: u32) {
+        match queue::service()
+            .await
+            .record_attempt(event_id, attempts)
+            .await
+        {
+            Ok(_) | Err(queue::service::ServiceError::NotFound) => {}
+            Err(e) => log::warn!(
+                "Router: scene [{id}]: failed to record attempt of event [{event_id}]: {e}",
+                id = self.scene_id
+            ),
+        }
+    }
+
+    /// Moves an event that used up its attempts to the dead letters.
+    async fn dead_letter(&self, lane: &LaneContext, delivery: &Delivery, error: String) {
+        self.bury(DeadLetter {
+            id: delivery.event_id.clone(),
+            scene_id: self.scene_id.clone(),
+            edge_id: lane.edge_id.clone(),
+            target_plugin_id: lane.target_plugin_id.clone(),
+            payload: delivery.payload.encode_to_vec(),
+            attempts: delivery.attempts,
+            error,
+            parent_id: delivery.parent_id.clone(),
+            trace_id: delivery.trace_id.clone(),
+            create_time: delivery.create_time,
+            dead_time: Local::now(),
+        })
+        .await;
+    }
+
+    /// Moves a queued event that can never be delivered, e.g. one whose edge is gone, to the
+    /// dead letters.
+    async fn dead_letter_queued(&self, event: QueuedEvent, error: String) {
+        self.bury(DeadLetter {
+            id: event.id,
+            scene_id: event.scene_id,
+            edge_id: event.edge_id,
+            target_plugin_id: event.target_plugin_id,
+            payload: event.payload,
+            attempts: event.attempts,
+            error,
+            parent_id: event.parent_id,
+            trace_id: event.trace_id,
+            create_time: event.create_time,
+            dead_time: Local::now(),
+        })
+        .await;
+    }
+
+    async fn bury(&self, dead_letter: DeadLetter) {
+        let event_id = dead_letter.id.clone();
+        match queue::service().await.dead_letter(dead_letter).await {
+            Ok(_) => {
+                let _ = self
+                    .stored
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |stored| {
+                        Some(stored.saturating_sub(1))
+                    });
+                self.report_depth();
+            }
+            Err(e) => log::error!(
+                "Router: scene [{id}]: failed to dead-letter event [{event_id}]: {e}",
+                id = self.scene_id
+            ),
+        }
+    }
+
+    async fn purge_expired(&self) {
+        match queue::service()
+            .await
+            .purge_expired(self.scene_id.as_str())
+            .await
+        {
+            Ok(0) => {}
+            Ok(purged) => {
+                let _ = self
+                    .stored
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |stored| {
+                        Some(stored.saturating_sub(purged))
+                    });
+                self.report_depth();
+                log::warn!(
+                    "Router: scene [{id}]: purged [{purged}] events past their retention",
+                    id = self.scene_id
+                );
+            }
+            Err(e) => log::error!(
+                "Router: scene [{id}]: failed to purge expired events: {e}",
+                id = self.scene_id
+            ),
+        }
+    }
+}
+
+/// What is paused in a scene: the scene as a whole, or single nodes of it. Edges hold the
+/// events for a paused node until it is resumed.
+#[derive(Default)]
+struct Pauses {
+    scene: bool,
+    nodes: HashSet<String>,
+}
+
+impl Pauses {
+    fn holds(&self, node_id: &str) -> bool {
+        self.scene || self.nodes.contains(node_id)
+    }
+}
+
+/// One edge of a scene graph: a bounded queue drained into the session of the downstream
+/// plugin. Every edge has its own queue, so a slow consumer only holds up its own edges.
+/// Events that find the queue full wait in the overflow of the edge, which feeds them into
+/// the queue as it drains; routing never waits for room.
+struct Lane {
+    edge_id: String,
+    target_plugin_id: String,
+    queue: mpsc::Sender<Delivery>,
+    overflow: mpsc::UnboundedSender<Delivery>,
+    /// Events in the overflow, not in the queue yet.
+    overflowed: Arc<AtomicUsize>,
+    depth: IntGauge,
+}
+
+impl Lane {
+    /// Queues an event without waiting: in the queue while it has room and nothing waits in
+    /// the overflow, which would have to go first, and in the overflow otherwise.
+    fn push(&self, delivery: Delivery) {
+        let delivery = if self.overflowed.load(Ordering::Acquire) == 0 {
+            match self.queue.try_send(delivery) {
+                Ok(_) => {
+                    self.report_depth();
+                    return;
+                }
+                Err(TrySendError::Full(delivery)) => delivery,
+                Err(TrySendError::Closed(_)) => {
+                    log::trace!("Router: edge [{edge_id}] is closed", edge_id = self.edge_id);
+                    return;
+                }
+            }
+        } else {
+            delivery
+        };
+        self.overflowed.fetch_add(1, Ordering::AcqRel);
+        if self.overflow.send(delivery).is_err() {
+            self.overflowed.fetch_sub(1, Ordering::AcqRel);
+            log::trace!("Router: edge [{edge_id}] is closed", edge_id = self.edge_id);
+        }
+        self.report_depth();
+    }
+
+    fn report_depth(&self) {
+        let queued = self.queue.max_capacity() - self.queue.capacity();
+        self.depth
+            .set((queued + self.overflowed.load(Ordering::Acquire)) as i64);
+    }
+}
+
+/// Routes events along the edges of a running scene. An event is copied to every edge
+/// leaving its node (fan-out), and edges ending in the same node all deliver to the same
+/// session (fan-in). Every copy is persisted before it is queued and removed once acked, so
+/// events survive a restart of the runtime.
+pub(crate) struct Router {
+    scene_id: String,
+    /// Edges leaving each node.
+    lanes: HashMap<String, Vec<Arc<Lane>>>,
+    /// Events out counter of each node.
+    events_out: HashMap<String, IntCounter>,
+    /// Lanes by edge ID, to put events back on their edge.
+    edges: HashMap<String, Arc<Lane>>,
+    /// Plugins of the nodes of the scene, by node ID.
+    nodes: HashMap<String, String>,
+    pauses: watch::Sender<Pauses>,
+    store: Arc<Store>,
+    tasks: TaskTracker,
+    stop: CancellationToken,
+}
+
+impl Router {
+    /// Sets up the edges of the scene and replays the events its durable queue still holds.
+    /// A scene set up paused delivers nothing until it is resumed.
+    pub(crate) async fn new(
+        scene_id: &str,
+        nodes: &[Node],
+        edges: &[Edge],
+        queue_size: usize,
+        delivery: DeliverySettings,
+        paused: bool,
+    ) -> Self {
+        let plugins: HashMap<String, String> = nodes
+            .iter()
+            .map(|node| (node.id.clone(), node.plugin_id.clone()))
+            .collect();
+        let tasks = TaskTracker::new();
+        let stop = CancellationToken::new();
+        let pauses = watch::Sender::new(Pauses {
+            scene: paused,
+            ..Pauses::default()
+        });
+        let store = Arc::new(Store {
+            scene_id: scene_id.to_string(),
+            limits: queue::service().await.limits(scene_id),
+            stored: AtomicU64::new(0),
+            depth: QUEUE_DEPTH.with_label_values(&[scene_id]),
+        });
+        store.purge_expired().await;
+        let pending = match queue::service().await.pending(scene_id).await {
+            Ok(pending) => pending,
+            Err(e) => {
+                log::error!("Router: scene [{scene_id}]: failed to load queued events: {e}");
+                vec![]
+            }
+        };
+        store.stored.store(pending.len() as u64, Ordering::Release);
+        store.report_depth();
+        let events_out: HashMap<String, IntCounter> = nodes
+            .iter()
+            .map(|node| {
+                (
+                    node.id.clone(),
+                    NODE_EVENTS_OUT.with_label_values(&[scene_id, node.id.as_str()]),
+                )
+            })
+            .collect();
+        let mut lanes: HashMap<String, Vec<Arc<Lane>>> = HashMap::new();
+        let mut edges_by_id: HashMap<String, Arc<Lane>> = HashMap::new();
+        for edge in edges {
+            let (Some(_), Some(to)) = (
+                plugins.get(edge.from_node_id.as_str()),
+                plugins.get(edge.to_node_id.as_str()),
+            ) else {
+                log::warn!(
+                    "Router: scene [{scene_id}]: edge [{id}] refers to an unknown node",
+                    id = edge.id
+                );
+                continue;
+            };
+            let (queue, queue_rx) = mpsc::channel(queue_size);
+            let (overflow, overflow_rx) = mpsc::unbounded_channel();
+            let overflowed = Arc::new(AtomicUsize::new(0));
+            let depth = EDGE_QUEUE_DEPTH.with_label_values(&[scene_id, edge.id.as_str()]);
+            let lane = Arc::new(LaneContext {
+                scene_id: scene_id.to_string(),
+                edge_id: edge.id.clone(),
+                from_node_id: edge.from_node_id.clone(),
+                to_node_id: edge.to_node_id.clone(),
+                target_plugin_id: to.to_string(),
+                events_in: NODE_EVENTS_IN.with_label_values(&[scene_id, edge.to_node_id.as_str()]),
+                depth: depth.clone(),
+                overflowed: overflowed.clone(),
+                delivery,
+                store: store.clone(),
+                retries: queue.downgrade(),
+                pauses: pauses.subscribe(),
+                tasks: tasks.clone(),
+                stop: stop.child_token(),
+            });
+            tasks.spawn(feed(lane.clone(), queue.clone(), overflow_rx));
+            tasks.spawn(drain(lane, queue_rx));
+            let lane = Arc::new(Lane {
+                edge_id: edge.id.clone(),
+                target_plugin_id: to.to_string(),
+                queue,
+                overflow,
+                overflowed,
+                depth,
+            });
+            edges_by_id.insert(edge.id.clone(), lane.clone());
+            lanes
+                .entry(edge.from_node_id.clone())
+                .or_default()
+                .push(lane);
+        }
+        replay(pending, &edges_by_id, &store).await;
+        tasks.spawn(purge_loop(store.clone(), stop.child_token()));
+        tasks.close();
+        Self {
+            scene_id: scene_id.to_string(),
+            lanes,
+            events_out,
+            edges: edges_by_id,
+            nodes: plugins,
+            pauses,
+            store,
+            tasks,
+            stop,
+        }
+    }
+
+    /// Nodes of the scene, along with the plugin each runs.
+    pub(crate) fn nodes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.nodes
+            .iter()
+            .map(|(node_id, plugin_id)| (node_id.as_str(), plugin_id.as_str()))
+    }
+
+    /// Nodes of the scene the plugin of a session runs as: the node it was launched for, or
+    /// every node of its plugin if it runs as no particular node.
+    pub(crate) fn nodes_of(&self, session: &Session) -> Vec<&str> {
+        self.nodes()
+            .filter(|(node_id, plugin_id)| session.runs_as(node_id, plugin_id))
+            .map(|(node_id, _)| node_id)
+            .collect()
+    }
+
+    pub(crate) fn is_scene_paused(&self) -> bool {
+        self.pauses.borrow().scene
+    }
+
+    /// Whether the node gets no deliveries, because it or the whole scene is paused.
+    pub(crate) fn is_paused(&self, node_id: &str) -> bool {
+        self.pauses.borrow().holds(node_id)
+    }
+
+    /// Pauses or resumes delivery to every node of the scene. Nodes paused on their own stay
+    /// paused when the scene is resumed.
+    pub(crate) fn pause_scene(&self, paused: bool) {
+        self.pauses.send_modify(|pauses| pauses.scene = paused);
+    }
+
+    /// Pauses or resumes delivery to one node of the scene. Its events wait on their edges
+    /// meanwhile, which holds up the nodes upstream once the edges fill.
+    pub(crate) fn pause_node(&self, node_id: &str, paused: bool) {
+        self.pauses.send_modify(|pauses| {
+            if paused {
+                pauses.nodes.insert(node_id.to_string());
+            } else {
+                pauses.nodes.remove(node_id);
+            }
+        });
+    }
+
+    /// Persists and queues the payload on every edge leaving the node, each with its own
+    /// event ID. Never waits for a consumer: events for full edges wait in their overflow.
+    /// If the durable queue of the scene is full or the event cannot be persisted, it is
+    /// dropped on all edges.
+    pub(crate) async fn dispatch(
+        &self,
+        node_id: &str,
+        origin: &Origin<'_>,
+        payload: PluginPayload,
+        ticket: &Arc<Ticket>,
+    ) {
+        let Some(lanes) = self.lanes.get(node_id) else {
+            log::trace!(
+                "Router: scene [{id}]: node [{node_id}] has no outgoing edges",
+                id = self.scene_id
+            );
+            return;
+        };
+        let events = self.copies(lanes, origin, &payload);
+        if let Err(e) = self.store.append(&events).await {
+            log::warn!(
+                "Router: scene [{id}]: dropping event from node [{node_id}]: {e}",
+                id = self.scene_id
+            );
+            return;
+        }
+        if let Some(counter) = self.events_out.get(node_id) {
+            counter.inc();
+        }
+        self.enqueue(lanes, events, &payload, Some(ticket));
+    }
+
+    /// Persists and queues the payload on every edge leaving the node as if its plugin had
+    /// emitted it, e.g. a replayed recording. Returns `false` if the durable queue of the scene
+    /// has no room for it, or it cannot be persisted.
+    pub(crate) async fn inject(
+        &self,
+        node_id: &str,
+        origin: &Origin<'_>,
+        payload: PluginPayload,
+    ) -> bool {
+        let Some(lanes) = self.lanes.get(node_id) else {
+            return true;
+        };
+        let events = self.copies(lanes, origin, &payload);
+        if let Err(e) = self.store.append(&events).await {
+            log::warn!(
+                "Router: scene [{id}]: dropping injected event: {e}",
+                id = self.scene_id
+            );
+            return false;
+        }
+        if let Some(counter) = self.events_out.get(node_id) {
+            counter.inc();
+        }
+        self.enqueue(lanes, events, &payload, None);
+        true
+    }
+
+    /// Queues the persisted copies of an event on their edges. Copies left in an overflow
+    /// once the router stops stay in the durable queue for the next run.
+    fn enqueue(
+        &self,
+        lanes: &[Arc<Lane>],
+        events: Vec<QueuedEvent>,
+        payload: &PluginPayload,
+        ticket: Option<&Arc<Ticket>>,
+    ) {
+        for (lane, event) in lanes.iter().zip(events) {
+            lane.push(Delivery {
+                event_id: event.id,
+                parent_id: event.parent_id,
+                trace_id: event.trace_id,
+                payload: payload.clone(),
+                _ticket: ticket.cloned(),
+                attempts: 0,
+                create_time: event.create_time,
+            });
+        }
+    }
+
+    /// Events of the scene in the durable queue, not acked yet.
+    pub(crate) fn queued(&self) -> u64 {
+        self.store.stored.load(Ordering::Acquire)
+    }
+
+    /// Copies of an event for the durable queue, one per edge.
+    fn copies(
+        &self,
+        lanes: &[Arc<Lane>],
+        origin: &Origin<'_>,
+        payload: &PluginPayload,
+    ) -> Vec<QueuedEvent> {
+        let encoded = payload.encode_to_vec();
+        let create_time = Local::now();
+        lanes
+            .iter()
+            .map(|lane| QueuedEvent {
+                id: Ulid::new().to_string(),
+                scene_id: self.scene_id.clone(),
+                edge_id: lane.edge_id.clone(),
+                target_plugin_id: lane.target_plugin_id.clone(),
+                payload: encoded.clone(),
+                attempts: 0,
+                parent_id: origin.event_id.to_string(),
+                trace_id: origin.trace_id.to_string(),
+                create_time,
+            })
+            .collect()
+    }
+
+    /// Puts events that are back in the durable queue, e.g. replayed dead letters, on their
+    /// edges.
+    pub(crate) async fn requeue(&self, events: Vec<QueuedEvent>) {
+        self.store
+            .stored
+            .fetch_add(events.len() as u64, Ordering::AcqRel);
+        self.store.report_depth();
+        replay(events, &self.edges, &self.store).await;
+    }
+
+    /// Stops all edges. Events not acked yet stay in the durable queue and are delivered
+    /// again once the scene runs.
+    pub(crate) async fn close(&self) {
+        self.stop.cancel();
+        self.tasks.wait().await;
+        let _ = QUEUE_DEPTH.remove_label_values(&[self.scene_id.as_str()]);
+        for edge_id in self.edges.keys() {
+            let _ = EDGE_QUEUE_DEPTH.remove_label_values(&[self.scene_id.as_str(), edge_id]);
+        }
+    }
+}
+
+/// Queues events from the durable queue on their edges again, in the order they were first
+/// routed. Events of edges that no longer exist are dead-lettered.
+async fn replay(pending: Vec<QueuedEvent>, lanes: &HashMap<String, Arc<Lane>>, store: &Store) {
+    if pending.is_empty() {
+        return;
+    }
+    log::info!(
+        "Router: scene [{id}]: replaying [{count}] queued events",
+        id = store.scene_id,
+        count = pending.len()
+    );
+    for event in pending {
+        let Some(lane) = lanes.get(event.edge_id.as_str()) else {
+            log::warn!(
+                "Router: scene [{id}]: dead-lettering queued event [{event_id}]: edge [{edge_id}] no longer exists",
+                id = store.scene_id,
+                event_id = event.id,
+                edge_id = event.edge_id
+            );
+            store
+                .dead_letter_queued(event, "edge no longer exists".to_string())
+                .await;
+            continue;
+        };
+        let payload = match PluginPayload::decode(event.payload.as_slice()) {
+            Ok(payload) => payload,
+            Err(e) => {
+                log::error!(
+                    "Router: scene [{id}]: dead-lettering queued event [{event_id}]: {e}",
+                    id = store.scene_id,
+                    event_id = event.id
+                );
+                store
+                    .dead_letter_queued(event, format!("undecodable payload: {e}"))
+                    .await;
+                continue;
+            }
+        };
+        // the edge queue may be smaller than the backlog, the overflow takes the rest
+        lane.push(Delivery {
+            event_id: event.id,
+            parent_id: event.parent_id,
+            trace_id: event.trace_id,
+            payload,
+            _ticket: None,
+            attempts: event.attempts,
+            create_time: event.create_time,
+        });
+    }
+}
+
+/// Moves the events that found the edge queue full into it as it drains, in the order they
+/// came.
+async fn feed(
+    lane: Arc<LaneContext>,
+    queue: mpsc::Sender<Delivery>,
+    mut overflow: mpsc::UnboundedReceiver<Delivery>,
+) {
+    loop {
+        let delivery = tokio::select! {
+            _ = lane.stop.cancelled() => return,
+            delivery = overflow.recv() => match delivery {
+                Some(delivery) => delivery,
+                None => return,
+            },
+        };
+        tokio::select! {
+            _ = lane.stop.cancelled() => return,
+            sent = queue.send(delivery) => if sent.is_err() {
+                return;
+            },
+        }
+        lane.overflowed.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Drops events past their retention from the durable queue, every purge interval.
+async fn purge_loop(store: Arc<Store>, stop: CancellationToken) {
+    loop {
+        tokio::select! {
+            _ = stop.cancelled() => return,
+            _ = tokio::time::sleep(store.limits.purge_interval) => store.purge_expired().await,
+        }
+    }
+}
+
+/// What the tasks of one edge share.
+struct LaneContext {
+    scene_id: String,
+    edge_id: String,
+    from_node_id: String,
+    to_node_id: String,
+    target_plugin_id: String,
+    /// Events in counter of the node the edge ends in.
+    events_in: IntCounter,
+    depth: IntGauge,
+    /// Events in the overflow of the edge, not in its queue yet.
+    overflowed: Arc<AtomicUsize>,
+    delivery: DeliverySettings,
+    store: Arc<Store>,
+    /// Sends events back into the edge queue for another delivery. Weak, so the queue still
+    /// closes when the router goes away.
+    retries: mpsc::WeakSender<Delivery>,
+    pauses: watch::Receiver<Pauses>,
+    tasks: TaskTracker,
+    stop: CancellationToken,
+}
+
+/// Delivers queued events to the plugin of the target node, one credit each. Events are not
+/// dropped while the plugin is away or paused: the lane waits for it to connect again or be
+/// resumed, which holds up the upstream plugin once the queue fills.
+async fn drain(lane: Arc<LaneContext>, mut queue: mpsc::Receiver<Delivery>) {
+    let (scene_id, target_plugin_id) = (&lane.scene_id, &lane.target_plugin_id);
+    let target_node_id = &lane.to_node_id;
+    let mut pauses = lane.pauses.clone();
+    // when the last event of the edge was shown to activity watchers
+    let mut sampled: Option<Instant> = None;
+    'lane: loop {
+        let delivery = tokio::select! {
+            _ = lane.stop.cancelled() => break,
+            delivery = queue.recv() => match delivery {
+                Some(delivery) => delivery,
+                None => break,
+            },
+        };
+        lane.depth
+            .set((queue.len() + lane.overflowed.load(Ordering::Acquire)) as i64);
+        tokio::select! {
+            _ = lane.stop.cancelled() => break,
+            resumed = pauses.wait_for(|pauses| !pauses.holds(target_node_id)) => {
+                if resumed.is_err() {
+                    break;
+                }
+            }
+        }
+        // wait for the plugin to take more events, which holds the ticket and so keeps the
+        // upstream plugin from sending more
+        let target = loop {
+            let Some(target) = hub::service()
+                .sessions()
+                .find_by_node(target_node_id.as_str(), target_plugin_id.as_str())
+                .await
+            else {
+                log::trace!(
+                    "Router: scene [{scene_id}]: plugin [{target_plugin_id}] of node [{target_node_id}] is not connected, waiting"
+                );
+                tokio::select! {
+                    _ = lane.stop.cancelled() => break 'lane,
+                    _ = tokio::time::sleep(RECONNECT_POLL_INTERVAL) => continue,
+                }
+            };
+            let credited = tokio::select! {
+                _ = lane.stop.cancelled() => break 'lane,
+                credited = target.acquire_delivery_credit() => credited,
+            };
+            if credited {
+                break target;
+            }
+        };
+        let settled = target.expect_settlement(delivery.event_id.as_str());
+        let msg = event_for(&target, &delivery);
+        if let Err(e) = target.send(msg).await {
+            target.forget(delivery.event_id.as_str());
+            lane.tasks.spawn(redeliver(
+                lane.clone(),
+                delivery,
+                format!("send failed: {e}"),
+            ));
+            continue;
+        }
+        hub::service().lineage().record(
+            Some(delivery.trace_id.as_str()),
+            Hop {
+                parent_ids: vec![delivery.parent_id.clone()],
+                edge_id: Some(lane.edge_id.clone()),
+                attempt: Some(delivery.attempts + 1),
+                ..Hop::new(
+                    HopKind::Delivered,
+                    delivery.event_id.as_str(),
+                    target_plugin_id.as_str(),
+                )
+            },
+        );
+        // plugins speaking a protocol version without acks are done with an event once it is
+        // sent
+        if !target.protocol_version.has_acks() {
+            target.settle(delivery.event_id.as_str(), Settlement::Ack);
+        }
+        if activity::service().is_watched()
+            && sampled
+                .is_none_or(|time| time.elapsed() >= activity::service().edge_sample_interval())
+        {
+            sampled = Some(Instant::now());
+            sample(&lane, &delivery);
+        }
+        lane.tasks
+            .spawn(await_settlement(lane.clone(), target, delivery, settled));
+    }
+    log::trace!(
+        "Router: scene [{scene_id}]: edge [{edge_id}] closed",
+        edge_id = lane.edge_id
+    );
+}
+
+/// Shows an event delivered along the edge to activity watchers.
+fn sample(lane: &LaneContext, delivery: &Delivery) {
+    activity::service().publish(Activity {
+        kind: ActivityKind::EdgeEvent {
+            edge_id: lane.edge_id.clone(),
+            from_node_id: lane.from_node_id.clone(),
+            to_node_id: lane.to_node_id.clone(),
+            event_id: delivery.event_id.clone(),
+            trace_id: delivery.trace_id.clone(),
+            payload: delivery.payload.clone(),
+        },
+        scene_id: Some(lane.scene_id.clone()),
+        node_ids: vec![lane.from_node_id.clone(), lane.to_node_id.clone()],
+        plugin_id: Some(lane.target_plugin_id.clone()),
+        time: Local::now(),
+    });
+}
+
+/// Waits for the target plugin to ack the event, which releases its ticket. Anything else
+/// sends the event round again.
+async fn await_settlement(
+    lane: Arc<LaneContext>,
+    target: Arc<Session>,
+    delivery: Delivery,
+    settled: oneshot::Receiver<Settlement>,
+) {
+    let reason = tokio::select! {
+        _ = lane.stop.cancelled() => return,
+        _ = target.closed() => "plugin disconnected".to_string(),
+        settled = tokio::time::timeout(lane.delivery.ack_timeout, settled) => match settled {
+            Ok(Ok(Settlement::Ack)) => {
+                lane.events_in.inc();
+                lane.store.remove(delivery.event_id.as_str()).await;
+                return;
+            }
+            Ok(Ok(Settlement::Nack(message))) => format!("rejected: {message}"),
+            Ok(Err(_)) => "delivery dropped".to_string(),
+            Err(_) => {
+                target.forget(delivery.event_id.as_str());
+                format!(
+                    "not acked within {}ms",
+                    lane.delivery.ack_timeout.as_millis()
+                )
+            }
+        },
+    };
+    redeliver(lane, delivery, reason).await;
+}
+
+/// Puts an event that was not acked back into the edge queue after the backoff, or gives up
+/// on it once it used up its attempts.
+async fn redeliver(lane: Arc<LaneContext>, mut delivery: Delivery, reason: String) {
+    let (scene_id, target_plugin_id) = (&lane.scene_id, &lane.target_plugin_id);
+    delivery.attempts += 1;
+    if lane.delivery.gives_up(delivery.attempts) {
+        log::error!(
+            "Router: scene [{scene_id}]: giving up on event [{event_id}] to plugin [{target_plugin_id}] after [{attempts}] attempts, dead-lettering it: {reason}",
+            event_id = delivery.event_id,
+            attempts = delivery.attempts
+        );
+        lane.store.dead_letter(&lane, &delivery, reason).await;
+        return;
+    }
+    lane.store
+        .record_attempt(delivery.event_id.as_str(), delivery.attempts)
+        .await;
+    log::warn!(
+        "Router: scene [{scene_id}]: event [{event_id}] to plugin [{target_plugin_id}] will be redelivered: {reason}",
+        event_id = delivery.event_id
+    );
+    tokio::select! {
+        _ = lane.stop.cancelled() => {}
+        _ = async {
+            tokio::time::sleep(lane.delivery.retry_backoff).await;
+            if let Some(retries) = lane.retries.upgrade() {
+                let _ = retries.send(delivery).await;
+            }
+        } => {}
+    }
+}
+
+/// Addresses the payload to the target plugin, which checks ID and version on every event.
+fn event_for(target: &Session, delivery: &Delivery) -> RuntimeMessage {
+    let (event_id, payload) = (delivery.event_id.clone(), delivery.payload.clone());
+    let (parent_ids, trace_id) = (vec![delivery.parent_id.clone()], delivery.trace_id.clone());
+    match target.plugin_type {
+        PluginType::Transform => RuntimeMessage::Transform(RuntimeTransformMessage {
+            payload: Some(RuntimeTransformPayload::Event(TransformEvent {
+                plugin_id: target.plugin_id.clone(),
+                plugin_version: target.plugin_version.clone(),
+                payload: Some(payload),
+                event_id,
+                parent_ids,
+                trace_id,
+            })),
+        }),
+        _ => RuntimeMessage::Sink(RuntimeSinkMessage {
+            payload: Some(RuntimeSinkPayload::Event(SinkEvent {
+                plugin_id: target.plugin_id.clone(),
+                plugin_version: target.plugin_version.clone(),
+                payload: Some(payload),
+                event_id,
+                parent_ids,
+                trace_id,
+            })),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivery(event_id: &str) -> Delivery {
+        Delivery {
+            event_id: event_id.to_string(),
+            parent_id: String::new(),
+            trace_id: String::new(),
+            payload: PluginPayload::default(),
+            _ticket: None,
+            attempts: 0,
+            create_time: Local::now(),
+        }
+    }
+
+    fn lane(
+        size: usize,
+    ) -> (
+        Lane,
+        mpsc::Receiver<Delivery>,
+        mpsc::UnboundedReceiver<Delivery>,
+    ) {
+        let (queue, queue_rx) = mpsc::channel(size);
+        let (overflow, overflow_rx) = mpsc::unbounded_channel();
+        let lane = Lane {
+            edge_id: "edge".to_string(),
+            target_plugin_id: "plugin".to_string(),
+            queue,
+            overflow,
+            overflowed: Arc::new(AtomicUsize::new(0)),
+            depth: IntGauge::new("edge_depth", "Edge depth").unwrap(),
+        };
+        (lane, queue_rx, overflow_rx)
+    }
+
+    fn settings(max_attempts: u32) -> DeliverySettings {
+        DeliverySettings {
+            ack_timeout: Duration::from_secs(30),
+            max_attempts,
+            retry_backoff: Duration::from_millis(1000),
+        }
+    }
+
+    /// Deliveries an event gets when every one of them fails, starting from the attempts it
+    /// already used up.
+    fn deliveries(settings: &DeliverySettings, mut attempts: u32) -> u32 {
+        let mut deliveries = 0;
+        loop {
+            deliveries += 1;
+            attempts += 1;
+            if settings.gives_up(attempts) {
+                return deliveries;
+            }
+        }
+    }
+
+    #[test]
+    fn redelivers_until_attempts_are_used_up() {
+        assert_eq!(deliveries(&settings(3), 0), 3);
+        assert_eq!(deliveries(&settings(1), 0), 1);
+    }
+
+    #[test]
+    fn replayed_events_keep_their_attempts() {
+        // an event that failed twice before the restart has one delivery left
+        assert_eq!(deliveries(&settings(3), 2), 1);
+    }
+
+    #[test]
+    fn push_overflows_full_edges_without_waiting() {
+        let (lane, mut queue, mut overflow) = lane(2);
+        for event_id in ["e1", "e2", "e3", "e4"] {
+            lane.push(delivery(event_id));
+        }
+        assert_eq!(lane.overflowed.load(Ordering::Acquire), 2);
+        assert_eq!(lane.depth.get(), 4);
+        assert_eq!(queue.try_recv().unwrap().event_id, "e1");
+        assert_eq!(queue.try_recv().unwrap().event_id, "e2");
+        assert_eq!(overflow.try_recv().unwrap().event_id, "e3");
+        assert_eq!(overflow.try_recv().unwrap().event_id, "e4");
+    }
+
+    #[test]
+    fn push_queues_behind_the_overflow() {
+        let (lane, mut queue, mut overflow) = lane(1);
+        lane.push(delivery("e1"));
+        lane.push(delivery("e2"));
+        assert_eq!(queue.try_recv().unwrap().event_id, "e1");
+
+        // the queue has room again, but e2 still waits in the overflow and goes first
+        lane.push(delivery("e3"));
+        assert!(queue.try_recv().is_err());
+        assert_eq!(overflow.try_recv().unwrap().event_id, "e2");
+        assert_eq!(overflow.try_recv().unwrap().event_id, "e3");
+    }
+}
@@ -1,9 +1,806 @@
-use flwrs_core::registry;
-use flwrs_core::registry::RegistryError;
+use crate::modules::director::router;
+use crate::modules::director::router::{DeliverySettings, Origin, Router, Ticket};
+use crate::modules::hub::session::{HubEvent, PluginEvent, Reconfiguration, Session};
+use crate::modules::queue::service::{Selection, ServiceError as QueueError};
+use crate::modules::scene::service::{ListFilters, Node, Scene, SceneState, ServiceError};
+use crate::modules::supervisor::service::{ProcessSpec, SupervisorError};
+use crate::modules::{catalog, hub, queue, recording, scene, supervisor};
 use async_trait::async_trait;
+use flwrs_core::config;
+use flwrs_core::config::main_config;
+use flwrs_core::config::Source;
+use flwrs_core::registry;
+use flwrs_core::registry::{Health, RegistryError};
+use flwrs_plugin::schema::common::plugin_type::Enum as PluginType;
+use flwrs_plugin::schema::schema::PluginPayload;
+use prometheus::Registry;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::{mpsc, RwLock};
 use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use ulid::Ulid;
+
+const SCENE_PAGE_SIZE: i64 = 100;
+
+#[derive(Deserialize)]
+pub(crate) struct DirectorConfig {
+    #[serde(default = "default_edge_queue_size")]
+    edge_queue_size: usize,
+    /// Events a source or transform may have in flight. Keep it at most `edge_queue_size`,
+    /// so their events fit in the edge queues instead of waiting in memory behind them.
+    #[serde(default = "default_credit_window")]
+    credit_window: u32,
+    /// Seconds a plugin has to ack an event before it is delivered again.
+    #[serde(default = "default_ack_timeout_seconds")]
+    ack_timeout_seconds: u64,
+    /// Deliveries of one event before it is given up on.
+    #[serde(default = "default_max_delivery_attempts")]
+    max_delivery_attempts: u32,
+    /// Wait before a nacked or timed out event is delivered again.
+    #[serde(default = "default_redelivery_backoff_millis")]
+    redelivery_backoff_millis: u64,
+}
+
+fn default_edge_queue_size() -> usize {
+    128
+}
+
+fn default_credit_window() -> u32 {
+    64
+}
+
+fn default_ack_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_max_delivery_attempts() -> u32 {
+    5
+}
+
+fn default_redelivery_backoff_millis() -> u64 {
+    1000
+}
+
+impl DirectorConfig {
+    /// Reads the `[director]` section, or the defaults if there is none. A section that fails
+    /// to parse is an error, rather than quietly running with the default delivery settings.
+    pub(crate) fn read() -> Result<Self, DirectorError> {
+        let main_config = main_config();
+        let path = ["director".to_string()];
+        match config::read_struct(main_config, &path) {
+            Some(config) => Ok(config),
+            None if main_config.sub(&path).is_some() => Err(DirectorError::Config(
+                "section [director] is invalid".to_string(),
+            )),
+            None => Ok(Self::defaults()),
+        }
+    }
+
+    fn defaults() -> Self {
+        Self {
+            edge_queue_size: default_edge_queue_size(),
+            credit_window: default_credit_window(),
+            ack_timeout_seconds: default_ack_timeout_seconds(),
+            max_delivery_attempts: default_max_delivery_attempts(),
+            redelivery_backoff_millis: default_redelivery_backoff_millis(),
+        }
+    }
+
+    fn delivery(&self) -> DeliverySettings {
+        DeliverySettings {
+            ack_timeout: Duration::from_secs(self.ack_timeout_seconds),
+            max_attempts: self.max_delivery_attempts.max(1),
+            retry_backoff: Duration::from_millis(self.redelivery_backoff_millis),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum DirectorError {
+    #[error("invalid director config: {0}")]
+    Config(String),
+    #[error("scene error: {0}")]
+    Scene(#[from] ServiceError),
+    #[error("supervisor error: {0}")]
+    Supervisor(#[from] SupervisorError),
+    #[error("queue error: {0}")]
+    Queue(#[from] QueueError),
+    #[error("scene [{0}] is already running")]
+    AlreadyRunning(String),
+    #[error("scene [{0}] is not running")]
+    NotRunning(String),
+    #[error("hub events are already consumed")]
+    NoHubEvents,
+    #[error("invalid config: {0}")]
+    InvalidConfig(String),
+    #[error("plugin [{0}] rejected the config: {1}")]
+    ConfigRejected(String, String),
+}
+
+/// An event a plugin emitted into a running scene, on its way to the routing task of the
+/// scene.
+struct Emitted {
+    session: Arc<Session>,
+    event_id: String,
+    trace_id: String,
+    payload: PluginPayload,
+    ticket: Arc<Ticket>,
+}
+
+/// A scene run by the director, along with the plugin processes launched for its nodes and
+/// the router connecting them.
+pub(crate) struct RunningScene {
+    pub scene_id: String,
+    pub plugins: Vec<ProcessSpec>,
+    /// Also tracks what is paused: paused scenes and nodes keep their plugins running, but
+    /// get no deliveries.
+    router: Router,
+    /// Events waiting to be routed, so a slow scene never holds up the director. Unbounded, as
+    /// routing only waits for the durable queue.
+    emitted: mpsc::UnboundedSender<Emitted>,
+    routing: CancellationToken,
+    tasks: TaskTracker,
+}
 
-pub(crate) struct Service;
+impl RunningScene {
+    /// Starts routing the events emitted into the scene, on a task of its own.
+    fn new(scene_id: String, plugins: Vec<ProcessSpec>, router: Router) -> Arc<Self> {
+        let (emitted, emitted_rx) = mpsc::unbounded_channel();
+        let running = Arc::new(Self {
+            scene_id,
+            plugins,
+            router,
+            emitted,
+            routing: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+        });
+        running.tasks.spawn(route_loop(running.clone(), emitted_rx));
+        running.tasks.close();
+        running
+    }
+
+    fn has_node(&self, node_id: &str) -> bool {
+        self.router.nodes().any(|(id, _)| id == node_id)
+    }
+
+    fn emit(&self, emitted: Emitted) {
+        if self.emitted.send(emitted).is_err() {
+            log::trace!(
+                "Director: scene [{id}] no longer routes events",
+                id = self.scene_id
+            );
+        }
+    }
+
+    /// Records an event and dispatches it along the edges of every node of the scene its
+    /// plugin runs as.
+    async fn route(&self, emitted: Emitted) {
+        let origin = Origin {
+            event_id: emitted.event_id.as_str(),
+            trace_id: emitted.trace_id.as_str(),
+        };
+        let session = &emitted.session;
+        for node_id in self.router.nodes_of(session) {
+            if session.plugin_type == PluginType::Source {
+                recording::service()
+                    .await
+                    .record(
+                        self.scene_id.as_str(),
+                        node_id,
+                        session.plugin_id.as_str(),
+                        &emitted.payload,
+                    )
+                    .await;
+            }
+            self.router
+                .dispatch(node_id, &origin, emitted.payload.clone(), &emitted.ticket)
+                .await;
+        }
+    }
+
+    /// Stops routing and closes the router. Events the plugins emitted that were not routed
+    /// yet are dropped, along with their tickets.
+    async fn close(&self) {
+        self.routing.cancel();
+        self.router.close().await;
+        self.tasks.wait().await;
+    }
+}
+
+/// Routes the events emitted into a scene, in the order they arrived, until the scene stops.
+/// Routing waits for the durable copies of an event to be written, never for room on an edge:
+/// a full edge holds up neither the other edges nor the events whose acks would drain it.
+async fn route_loop(running: Arc<RunningScene>, mut emitted: mpsc::UnboundedReceiver<Emitted>) {
+    loop {
+        let next = tokio::select! {
+            biased;
+            _ = running.routing.cancelled() => break,
+            next = emitted.recv() => match next {
+                Some(next) => next,
+                None => break,
+            },
+        };
+        running.route(next).await;
+    }
+}
+
+/// Whether the plugin of a node is paused.
+pub(crate) struct PauseState {
+    pub paused: bool,
+    /// Whether the plugin knows, which it only does if it is connected and speaks a protocol
+    /// version with pausing. Plugins that don't still get no deliveries while paused, and
+    /// sources speaking a version with credit stop once they run out of it.
+    pub notified: bool,
+}
+
+/// Scenes currently run by the director, indexed by scene ID.
+#[derive(Default)]
+pub(crate) struct SceneSet {
+    scenes: RwLock<HashMap<String, Arc<RunningScene>>>,
+}
+
+impl SceneSet {
+    async fn insert(&self, scene: Arc<RunningScene>) -> Result<(), DirectorError> {
+        let mut scenes = self.scenes.write().await;
+        if scenes.contains_key(scene.scene_id.as_str()) {
+            return Err(DirectorError::AlreadyRunning(scene.scene_id.clone()));
+        }
+        scenes.insert(scene.scene_id.clone(), scene);
+        Ok(())
+    }
+
+    async fn get(&self, scene_id: &str) -> Option<Arc<RunningScene>> {
+        self.scenes.read().await.get(scene_id).cloned()
+    }
+
+    async fn remove(&self, scene_id: &str) -> Option<Arc<RunningScene>> {
+        self.scenes.write().await.remove(scene_id)
+    }
+
+    async fn list(&self) -> Vec<Arc<RunningScene>> {
+        self.scenes.read().await.values().cloned().collect()
+    }
+
+    /// The scene the plugin of a session runs a node of.
+    async fn find_by_session(&self, session: &Session) -> Option<Arc<RunningScene>> {
+        self.scenes
+            .read()
+            .await
+            .values()
+            .find(|scene| !scene.router.nodes_of(session).is_empty())
+            .cloned()
+    }
+}
+
+/// Runs scenes: launches their plugins and routes events between them.
+pub(crate) struct Service {
+    config: DirectorConfig,
+    /// Why the configured director section was rejected. The director then refuses to start.
+    config_error: Option<String>,
+    scenes: SceneSet,
+    /// Whether the scenes that were running at shutdown are running again.
+    restored: AtomicBool,
+}
+
+impl Service {
+    pub(crate) fn new(config: Result<DirectorConfig, DirectorError>) -> Self {
+        let (config, config_error) = match config {
+            Ok(config) => (config, None),
+            Err(DirectorError::Config(reason)) => (DirectorConfig::defaults(), Some(reason)),
+            Err(e) => (DirectorConfig::defaults(), Some(e.to_string())),
+        };
+        if config.credit_window as usize > config.edge_queue_size {
+            log::warn!(
+                "Director: credit window [{window}] is larger than the edge queues [{size}], events may wait in memory behind full edges",
+                window = config.credit_window,
+                size = config.edge_queue_size
+            );
+        }
+        Self {
+            config,
+            config_error,
+            scenes: SceneSet::default(),
+            restored: AtomicBool::new(false),
+        }
+    }
+
+    /// Starts a scene, or resumes it if it is paused, and records the outcome as the scene
+    /// state. A scene that fails to start is left `failed`, with the error as the reason.
+    pub(crate) async fn start(&self, scene_id: &str) -> Result<Scene, DirectorError> {
+        let scenes = scene::service().await;
+        let scene = scenes.get_scene(scene_id).await?;
+        if scene.state == SceneState::Paused {
+            return self.resume(scene_id).await;
+        }
+        let scene = scenes
+            .transition(scene_id, SceneState::Starting, None)
+            .await?;
+        self.run_scene(&scene, false).await
+    }
+
+    /// Stops a running or paused scene and marks it `stopped`.
+    pub(crate) async fn stop(&self, scene_id: &str) -> Result<Scene, DirectorError> {
+        let scenes = scene::service().await;
+        scenes
+            .transition(scene_id, SceneState::Stopping, None)
+            .await?;
+        match self.stop_scene(scene_id).await {
+            Ok(_) | Err(DirectorError::NotRunning(_)) => {}
+            Err(e) => {
+                scenes
+                    .transition(scene_id, SceneState::Failed, Some(e.to_string()))
+                    .await?;
+                return Err(e);
+            }
+        }
+        Ok(scenes
+            .transition(scene_id, SceneState::Stopped, None)
+            .await?)
+    }
+
+    /// Pauses a running scene: its plugins stay up, but sources stop emitting and events
+    /// wait on their edges instead of being delivered.
+    pub(crate) async fn pause(&self, scene_id: &str) -> Result<Scene, DirectorError> {
+        let scene = scene::service()
+            .await
+            .transition(scene_id, SceneState::Paused, None)
+            .await?;
+        if let Some(running) = self.scenes.get(scene_id).await {
+            running.router.pause_scene(true);
+            for (node_id, plugin_id) in running.router.nodes() {
+                self.sync_pause(&running, node_id, plugin_id).await;
+            }
+        }
+        log::info!("Director: scene [{scene_id}] paused");
+        Ok(scene)
+    }
+
+    /// Resumes a paused scene. Nodes paused on their own stay paused.
+    pub(crate) async fn resume(&self, scene_id: &str) -> Result<Scene, DirectorError> {
+        let scene = scene::service()
+            .await
+            .transition(scene_id, SceneState::Running, None)
+            .await?;
+        if let Some(running) = self.scenes.get(scene_id).await {
+            running.router.pause_scene(false);
+            for (node_id, plugin_id) in running.router.nodes() {
+                self.sync_pause(&running, node_id, plugin_id).await;
+            }
+        }
+        log::info!("Director: scene [{scene_id}] resumed");
+        Ok(scene)
+    }
+
+    /// Pauses or resumes the plugin of a node of a running scene, e.g. to take the API a
+    /// sink writes to down for maintenance. Events for the node wait on its edges meanwhile.
+    /// Pauses last until the scene stops; a node of a paused scene stays paused until the
+    /// scene is resumed too.
+    pub(crate) async fn pause_node(
+        &self,
+        scene_id: &str,
+        node_id: &str,
+        paused: bool,
+    ) -> Result<PauseState, DirectorError> {
+        let node = scene::service().await.get_node(scene_id, node_id).await?;
+        let running = self
+            .scenes
+            .get(scene_id)
+            .await
+            .ok_or_else(|| DirectorError::NotRunning(scene_id.to_string()))?;
+        running.router.pause_node(node_id, paused);
+        let state = PauseState {
+            paused: running.router.is_paused(node_id),
+            notified: self
+                .sync_pause(&running, node_id, node.plugin_id.as_str())
+                .await,
+        };
+        log::info!(
+            "Director: plugin [{plugin_id}] of node [{node_id}] {action}",
+            plugin_id = node.plugin_id,
+            action = if paused { "paused" } else { "resumed" }
+        );
+        Ok(state)
+    }
+
+    /// Brings the session of the plugin of a node of a running scene in line with whether the
+    /// router holds the deliveries to the node. Returns whether the plugin knows it is paused
+    /// or not.
+    async fn sync_pause(&self, running: &RunningScene, node_id: &str, plugin_id: &str) -> bool {
+        let Some(session) = hub::service()
+            .sessions()
+            .find_by_node(node_id, plugin_id)
+            .await
+        else {
+            return false;
+        };
+        let paused = running.router.is_paused(node_id);
+        if session.is_paused() == paused {
+            return session.protocol_version.has_pause();
+        }
+        hub::service().pause(&session, paused).await
+    }
+
+    /// Puts the selected dead letters back on their edges with fresh attempts. Events of
+    /// scenes that are not running wait in the queue until their scene runs. Returns how
+    /// many were replayed.
+    pub(crate) async fn replay_dead_letters(
+        &self,
+        selection: &Selection,
+    ) -> Result<usize, DirectorError> {
+        let events = queue::service()
+            .await
+            .requeue_dead_letters(selection)
+            .await?;
+        let count = events.len();
+        let mut by_scene: HashMap<String, Vec<_>> = HashMap::new();
+        for event in events {
+            by_scene
+                .entry(event.scene_id.clone())
+                .or_default()
+                .push(event);
+        }
+        for (scene_id, events) in by_scene {
+            if let Some(running) = self.scenes.get(scene_id.as_str()).await {
+                running.router.requeue(events).await;
+            }
+        }
+        log::info!("Director: replayed [{count}] dead letters");
+        Ok(count)
+    }
+
+    /// Updates a node. If its scene is running and the node keeps its plugin, the new config
+    /// is checked against the options the plugin declares and pushed to the running plugin
+    /// first; the node is only updated if the plugin does not reject it. Returns the updated
+    /// node, and how the running plugin took the config if it was pushed.
+    pub(crate) async fn update_node(
+        &self,
+        node: Node,
+    ) -> Result<(Node, Option<Reconfiguration>), DirectorError> {
+        let scenes = scene::service().await;
+        let current = scenes
+            .get_node(node.scene_id.as_str(), node.id.as_str())
+            .await?;
+        let (name, _) = catalog::service::parse_reference(node.plugin_id.as_str());
+        let running = match self.scenes.get(node.scene_id.as_str()).await {
+            Some(running) if name == current.plugin_id && running.has_node(node.id.as_str()) => {
+                running
+            }
+            _ => return Ok((scenes.update_node(node).await?, None)),
+        };
+        let plugin_id = current.plugin_id;
+        let config = supervisor::service().scene_config(running.scene_id.as_str(), &node);
+        let outcome = match hub::service()
+            .sessions()
+            .find_by_node(node.id.as_str(), plugin_id.as_str())
+            .await
+        {
+            Some(session) => {
+                let fields = hub::service()
+                    .check_config(&session, &config)
+                    .map_err(|report| DirectorError::InvalidConfig(report.to_string()))?;
+                let outcome = hub::service().reconfigure(&session, fields).await;
+                if let Reconfiguration::Rejected(message) = outcome {
+                    log::info!(
+                        "Director: plugin [{plugin_id}] rejected the config of node [{id}]: {message}",
+                        id = node.id
+                    );
+                    return Err(DirectorError::ConfigRejected(plugin_id, message));
+                }
+                Some(outcome)
+            }
+            // delivered when the plugin connects
+            None => None,
+        };
+        let node = scenes.update_node(node).await?;
+        match supervisor::service()
+            .set_plugin_config(node.id.as_str(), config)
+            .await
+        {
+            Ok(_) | Err(SupervisorError::NotFound(_)) => {}
+            Err(e) => return Err(DirectorError::from(e)),
+        }
+        match &outcome {
+            Some(Reconfiguration::Applied) => log::info!(
+                "Director: plugin [{plugin_id}] of node [{id}] reconfigured",
+                id = node.id
+            ),
+            Some(Reconfiguration::Unsupported(reason)) | Some(Reconfiguration::Failed(reason)) => {
+                log::warn!(
+                    "Director: plugin [{plugin_id}] of node [{id}] was not reconfigured, the config applies when it restarts: {reason}",
+                    id = node.id
+                )
+            }
+            _ => {}
+        }
+        Ok((node, outcome))
+    }
+
+    /// Brings up a scene already moved to an active state and settles its state: `running`
+    /// (or `paused`, if restored paused) on success, `failed` otherwise.
+    async fn run_scene(&self, scene: &Scene, paused: bool) -> Result<Scene, DirectorError> {
+        let scenes = scene::service().await;
+        if let Err(e) = self.start_scene(scene, paused).await {
+            scenes
+                .transition(scene.id.as_str(), SceneState::Failed, Some(e.to_string()))
+                .await?;
+            return Err(e);
+        }
+        if scene.state != SceneState::Starting {
+            return Ok(scenes.get_scene(scene.id.as_str()).await?);
+        }
+        match scenes
+            .transition(scene.id.as_str(), SceneState::Running, None)
+            .await
+        {
+            Ok(scene) => Ok(scene),
+            Err(e) => {
+                // the scene was stopped while starting
+                self.stop_scene(scene.id.as_str()).await?;
+                Err(DirectorError::from(e))
+            }
+        }
+    }
+
+    async fn start_scene(&self, scene: &Scene, paused: bool) -> Result<(), DirectorError> {
+        log::debug!("Director: starting scene [{id}]", id = scene.id);
+        let nodes = scene::service().await.list_nodes(scene.id.as_str()).await?;
+        let edges = scene::service().await.list_edges(scene.id.as_str()).await?;
+        if edges.is_empty() {
+            log::warn!(
+                "Director: scene [{id}] has no edges, no events will be routed",
+                id = scene.id
+            );
+        }
+        let running = RunningScene::new(
+            scene.id.clone(),
+            supervisor::service()
+                .scene_specs(scene.id.as_str(), &nodes)
+                .await,
+            Router::new(
+                scene.id.as_str(),
+                &nodes,
+                &edges,
+                self.config.edge_queue_size,
+                self.config.delivery(),
+                paused,
+            )
+            .await,
+        );
+        self.scenes.insert(running.clone()).await?;
+        for spec in running.plugins.iter() {
+            if let Err(e) = supervisor::service().launch(spec.clone()).await {
+                log::error!(
+                    "Director: failed to launch plugin [{spec}] for scene [{id}]: {e}",
+                    id = scene.id
+                );
+                self.stop_scene(scene.id.as_str()).await?;
+                return Err(DirectorError::from(e));
+            }
+        }
+        log::info!(
+            "Director: scene [{id}] started with [{count}] plugins",
+            id = scene.id,
+            count = running.plugins.len()
+        );
+        Ok(())
+    }
+
+    async fn stop_scene(&self, scene_id: &str) -> Result<(), DirectorError> {
+        log::debug!("Director: stopping scene [{scene_id}]");
+        let running = match self.scenes.remove(scene_id).await {
+            Some(running) => running,
+            None => return Err(DirectorError::NotRunning(scene_id.to_string())),
+        };
+        running.close().await;
+        for spec in running.plugins.iter() {
+            match supervisor::service().stop(spec.key()).await {
+                Ok(_) | Err(SupervisorError::NotFound(_)) => {}
+                Err(e) => {
+                    log::warn!(
+                        "Director: failed to stop plugin [{spec}] of scene [{scene_id}]: {e}"
+                    );
+                }
+            }
+        }
+        log::info!("Director: scene [{scene_id}] stopped");
+        Ok(())
+    }
+
+    /// Brings a scene back to the state it was left in before the app stopped.
+    async fn restore_scene(&self, scene: &Scene) {
+        let result = match scene.state {
+            state if state.is_active() => self
+                .run_scene(scene, state == SceneState::Paused)
+                .await
+                .map(|_| ()),
+            SceneState::Stopping => scene::service()
+                .await
+                .transition(scene.id.as_str(), SceneState::Stopped, None)
+                .await
+                .map(|_| ())
+                .map_err(DirectorError::from),
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            log::error!(
+                "Director: failed to restore scene [{id}]: {e}",
+                id = scene.id
+            );
+        }
+    }
+
+    /// Number of finished events to grant back to a plugin at once.
+    fn batch(&self) -> u32 {
+        (self.config.credit_window / 2).max(1)
+    }
+
+    async fn load_scenes(&self) -> Result<Vec<Scene>, DirectorError> {
+        let mut scenes = vec![];
+        loop {
+            let (page, has_more) = scene::service()
+                .await
+                .list_scenes(ListFilters::new(scenes.len() as i64, SCENE_PAGE_SIZE))
+                .await?;
+            scenes.extend(page);
+            if !has_more {
+                break Ok(scenes);
+            }
+        }
+    }
+
+    async fn handle(&self, event: HubEvent) {
+        match event {
+            HubEvent::Connected(session) => {
+                if let Err(e) = catalog::service()
+                    .await
+                    .record_schema(
+                        session.plugin_id.as_str(),
+                        session.plugin_version.as_str(),
+                        session.in_schema.as_ref(),
+                        session.out_schema.as_ref(),
+                    )
+                    .await
+                {
+                    log::warn!(
+                        "Director: failed to record schema of plugin [{plugin_id}]: {e}",
+                        plugin_id = session.plugin_id
+                    );
+                }
+                let running = self.scenes.find_by_session(&session).await;
+                // a plugin paused before it reconnected stays paused
+                if let Some(running) = running.as_ref()
+                    && running
+                        .router
+                        .nodes_of(&session)
+                        .iter()
+                        .any(|node_id| running.router.is_paused(node_id))
+                {
+                    hub::service().pause(&session, true).await;
+                }
+                if session.plugin_type != PluginType::Sink
+                    && let Err(e) = session.grant_credits(self.config.credit_window).await
+                {
+                    log::warn!(
+                        "Director: failed to grant credit to plugin [{plugin_id}]: {e}",
+                        plugin_id = session.plugin_id
+                    );
+                }
+                if let Some(running) = running {
+                    log::debug!(
+                        "Director: plugin [{plugin_id}] of scene [{id}] connected",
+                        plugin_id = session.plugin_id,
+                        id = running.scene_id
+                    );
+                }
+            }
+            HubEvent::Disconnected(session) => {
+                if let Some(running) = self.scenes.find_by_session(&session).await {
+                    log::debug!(
+                        "Director: plugin [{plugin_id}] of scene [{id}] disconnected",
+                        plugin_id = session.plugin_id,
+                        id = running.scene_id
+                    );
+                }
+            }
+            HubEvent::Event(session, event) => self.route(session, event).await,
+        }
+    }
+
+    /// Whether the scenes that were running at shutdown are running again.
+    pub(crate) fn is_restored(&self) -> bool {
+        self.restored.load(Ordering::Acquire)
+    }
+
+    pub(crate) async fn is_running(&self, scene_id: &str) -> bool {
+        self.scenes.get(scene_id).await.is_some()
+    }
+
+    /// Events of a running scene in its durable queue, not acked yet.
+    pub(crate) async fn queued(&self, scene_id: &str) -> Option<u64> {
+        Some(self.scenes.get(scene_id).await?.router.queued())
+    }
+
+    /// Routes a payload into a running scene as if the plugin of the node had emitted it, as
+    /// a new event with IDs of its own. Waits for room on the edges of the node. Returns
+    /// `false` if the durable queue of the scene is full and the event was dropped.
+    pub(crate) async fn inject(
+        &self,
+        scene_id: &str,
+        node_id: &str,
+        payload: PluginPayload,
+    ) -> Result<bool, DirectorError> {
+        let running = self
+            .scenes
+            .get(scene_id)
+            .await
+            .ok_or_else(|| DirectorError::NotRunning(scene_id.to_string()))?;
+        let (event_id, trace_id) = (Ulid::new().to_string(), Ulid::new().to_string());
+        let origin = Origin {
+            event_id: event_id.as_str(),
+            trace_id: trace_id.as_str(),
+        };
+        Ok(running.router.inject(node_id, &origin, payload).await)
+    }
+
+    /// Scene and nodes the plugin of a session runs as, if it is part of a running scene.
+    pub(crate) async fn locate(&self, session: &Session) -> Option<(String, Vec<String>)> {
+        let running = self.scenes.find_by_session(session).await?;
+        let node_ids = running
+            .router
+            .nodes_of(session)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        Some((running.scene_id.clone(), node_ids))
+    }
+
+    /// Hands an event to the routing task of the running scene the plugin emits into, paused
+    /// or not: the edges of a paused scene hold the event until it is resumed. The plugin
+    /// gets its credit back once every edge is done with the event, right away if the event
+    /// goes nowhere.
+    async fn route(&self, session: Arc<Session>, event: PluginEvent) {
+        let ticket = Ticket::new(session.clone(), self.batch());
+        let targets: Vec<Arc<RunningScene>> = self
+            .scenes
+            .list()
+            .await
+            .into_iter()
+            .filter(|running| !running.router.nodes_of(&session).is_empty())
+            .collect();
+        if targets.is_empty() {
+            log::trace!(
+                "Director: dropping event from plugin [{plugin_id}]: not part of a running scene",
+                plugin_id = session.plugin_id
+            );
+            return;
+        }
+        let (event_id, trace_id) = (event.event_id().to_string(), event.trace_id().to_string());
+        let payload = match event.into_payload() {
+            Some(payload) => payload,
+            None => {
+                log::warn!(
+                    "Director: dropping event from plugin [{plugin_id}]: no payload",
+                    plugin_id = session.plugin_id
+                );
+                return;
+            }
+        };
+        for running in targets {
+            running.emit(Emitted {
+                session: session.clone(),
+                event_id: event_id.clone(),
+                trace_id: trace_id.clone(),
+                payload: payload.clone(),
+                ticket: ticket.clone(),
+            });
+        }
+    }
+}
 
 #[async_trait]
 impl registry::Service for Service {
@@ -11,9 +808,74 @@ impl registry::Service for Service {
         "director-service".to_string()
     }
 
+    fn register_metrics(&self, registry: &Registry) {
+        router::register_metrics(registry);
+    }
+
+    async fn health(&self) -> Health {
+        if !self.restored.load(Ordering::Acquire) {
+            return Health::down("restoring scenes");
+        }
+        let scenes = self.scenes.list().await;
+        let paused = scenes
+            .iter()
+            .filter(|running| running.router.is_scene_paused())
+            .count();
+        Health::up()
+            .with_detail("running_scenes", scenes.len() - paused)
+            .with_detail("paused_scenes", paused)
+    }
+
     async fn start(&self, shutdown_token: CancellationToken) -> Result<(), RegistryError> {
-        todo!()
+        if let Some(error) = &self.config_error {
+            return Err(RegistryError::ServiceError {
+                msg: "Director config error".to_string(),
+                source: Box::new(DirectorError::Config(error.clone())),
+            });
+        }
+        log::debug!("Starting director.");
+        let mut events = match hub::service().take_events() {
+            Some(events) => events,
+            None => {
+                return Err(RegistryError::ServiceError {
+                    msg: "Director start error".to_string(),
+                    source: Box::new(DirectorError::NoHubEvents),
+                });
+            }
+        };
+
+        match self.load_scenes().await {
+            Ok(scenes) => {
+                for scene in scenes.iter() {
+                    self.restore_scene(scene).await;
+                }
+            }
+            Err(e) => {
+                log::error!("Director: failed to load scenes: {e}");
+            }
+        }
+        self.restored.store(true, Ordering::Release);
+
+        loop {
+            tokio::select! {
+                _ = shutdown_token.cancelled() => break,
+                event = events.recv() => match event {
+                    Some(event) => self.handle(event).await,
+                    None => break,
+                },
+            }
+        }
+
+        log::debug!("Director shutting down.");
+        for running in self.scenes.list().await {
+            if let Err(e) = self.stop_scene(running.scene_id.as_str()).await {
+                log::warn!(
+                    "Director: failed to stop scene [{id}]: {e}",
+                    id = running.scene_id
+                );
+            }
+        }
+        log::debug!("Director exited");
+        Ok(())
     }
 }
-
-pub(crate) struct ScreenSet;
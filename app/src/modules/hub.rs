@@ -0,0 +1,20 @@
+use crate::modules::hub::service::{HubConfig, Service};
+use lazy_static::lazy_static;
+use std::sync::Arc;
+
+pub(crate) mod api;
+pub(crate) mod compat;
+pub(crate) mod lineage;
+pub(crate) mod listener;
+pub(crate) mod plugin_config;
+pub(crate) mod service;
+pub(crate) mod session;
+pub(crate) mod tls;
+
+lazy_static! {
+    static ref SERVICE: Arc<Service> = Arc::new(Service::new(HubConfig::read()));
+}
+
+pub(crate) fn service() -> &'static Service {
+    SERVICE.as_ref()
+}
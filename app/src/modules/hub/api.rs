@@ -0,0 +1,225 @@
+use crate::modules::hub;
+use crate::modules::hub::lineage;
+use crate::modules::hub::session::Session;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use flwrs_plugin::schema::schema::{FieldDefinition, SchemaDefinition};
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub(crate) struct SchemaField {
+    pub key: String,
+    pub description: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[schema(no_recursion)]
+    pub nested_type_definition: Option<Box<SchemaField>>,
+    #[schema(no_recursion)]
+    pub object_fields: Vec<SchemaField>,
+}
+
+impl From<&FieldDefinition> for SchemaField {
+    fn from(value: &FieldDefinition) -> Self {
+        Self {
+            key: value.key.clone(),
+            description: value.description.clone(),
+            type_: value.r#type().as_str_name().to_string(),
+            nested_type_definition: value
+                .nested_type_definition
+                .as_ref()
+                .map(|nested| Box::new(SchemaField::from(nested.as_ref()))),
+            object_fields: value.object_fields.iter().map(From::from).collect(),
+        }
+    }
+}
+
+pub(crate) fn schema_fields(schema: &Option<SchemaDefinition>) -> Option<Vec<SchemaField>> {
+    schema
+        .as_ref()
+        .map(|schema| schema.fields.iter().map(From::from).collect())
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct PluginSession {
+    pub id: String,
+    pub plugin_id: String,
+    /// Scene node the plugin runs as, if it runs as a particular one.
+    pub node_id: Option<String>,
+    pub plugin_version: String,
+    pub plugin_type: String,
+    /// Protocol version agreed on with the plugin.
+    pub protocol_version: String,
+    pub peer_addr: String,
+    pub connect_time: i64,
+    /// When the plugin last sent a message, answers to heartbeats included.
+    pub last_seen_time: i64,
+    /// False once the plugin stopped answering heartbeats.
+    pub responsive: bool,
+    pub in_schema: Option<Vec<SchemaField>>,
+    pub out_schema: Option<Vec<SchemaField>>,
+}
+
+impl From<&Session> for PluginSession {
+    fn from(value: &Session) -> Self {
+        Self {
+            id: value.id.clone(),
+            plugin_id: value.plugin_id.clone(),
+            node_id: value.node_id.clone(),
+            plugin_version: value.plugin_version.clone(),
+            plugin_type: value.plugin_type.as_str_name().to_string(),
+            protocol_version: value.protocol_version.to_string(),
+            peer_addr: value.peer_addr.clone(),
+            connect_time: value.connect_time.timestamp_millis(),
+            last_seen_time: value.last_seen().timestamp_millis(),
+            responsive: value.is_responsive(),
+            in_schema: schema_fields(&value.in_schema),
+            out_schema: schema_fields(&value.out_schema),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ListSessionsResponse {
+    sessions: Vec<PluginSession>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions",
+    operation_id = "list-plugin-sessions",
+    description = "List plugins currently connected to the hub",
+    summary = "List plugin sessions",
+    responses(
+        (status = 200, description = "Plugin sessions", body = ListSessionsResponse),
+    ),
+)]
+async fn list_sessions() -> Json<ListSessionsResponse> {
+    log::trace!("Hub API: listing sessions");
+    let sessions = hub::service().sessions().list().await;
+    Json(ListSessionsResponse {
+        sessions: sessions
+            .iter()
+            .map(|session| PluginSession::from(session.as_ref()))
+            .collect(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions/by-id/{id}",
+    operation_id = "get-plugin-session",
+    description = "Get a plugin session by ID",
+    summary = "Get plugin session by ID",
+    responses(
+        (status = 200, description = "Plugin session", body = PluginSession),
+        (status = 404, description = "Not found"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the session to fetch")
+    )
+)]
+async fn get_session(Path(id): Path<String>) -> Result<Json<PluginSession>, StatusCode> {
+    log::trace!("Hub API: getting session [{id}]");
+    match hub::service().sessions().get(id.as_str()).await {
+        Some(session) => Ok(Json(PluginSession::from(session.as_ref()))),
+        None => {
+            log::trace!("Hub API: Failed to get session [{id}]: not found");
+            Err(StatusCode::NOT_FOUND)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct Hop {
+    /// `emitted`, `delivered`, `acked` or `nacked`.
+    pub kind: String,
+    pub event_id: String,
+    pub parent_ids: Vec<String>,
+    /// Plugin that emitted the event, or the target plugin of a delivery.
+    pub plugin_id: String,
+    pub edge_id: Option<String>,
+    /// Delivery attempt, counting from 1.
+    pub attempt: Option<u32>,
+    /// Why the target plugin rejected the event.
+    pub message: Option<String>,
+    pub time: i64,
+    /// Milliseconds since the first hop of the trace.
+    pub elapsed_ms: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct LineageResponse {
+    trace_id: String,
+    hops: Vec<Hop>,
+}
+
+impl LineageResponse {
+    fn new(trace_id: String, hops: Vec<lineage::Hop>) -> Self {
+        let start = hops.first().map(|hop| hop.time);
+        Self {
+            trace_id,
+            hops: hops
+                .into_iter()
+                .map(|hop| Hop {
+                    kind: hop.kind.as_str().to_string(),
+                    elapsed_ms: start
+                        .map(|start| (hop.time - start).num_milliseconds())
+                        .unwrap_or_default(),
+                    event_id: hop.event_id,
+                    parent_ids: hop.parent_ids,
+                    plugin_id: hop.plugin_id,
+                    edge_id: hop.edge_id,
+                    attempt: hop.attempt,
+                    message: hop.message,
+                    time: hop.time.timestamp_millis(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/lineage/by-event-id/{id}",
+    operation_id = "get-event-lineage",
+    description = "Get every recorded hop of the trace an event belongs to, with timings. Only \
+        recent traces are kept, in memory.",
+    summary = "Get event lineage",
+    responses(
+        (status = 200, description = "Event lineage", body = LineageResponse),
+        (status = 404, description = "Not found"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of any event of the trace")
+    )
+)]
+async fn get_lineage(Path(id): Path<String>) -> Result<Json<LineageResponse>, StatusCode> {
+    log::trace!("Hub API: getting lineage of event [{id}]");
+    match hub::service().lineage().trace_of(id.as_str()) {
+        Some((trace_id, hops)) => Ok(Json(LineageResponse::new(trace_id, hops))),
+        None => {
+            log::trace!("Hub API: Failed to get lineage of event [{id}]: not found");
+            Err(StatusCode::NOT_FOUND)
+        }
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Hub", description = "Plugin hub API",),
+    paths(list_sessions, get_session, get_lineage,),
+    components(schemas(PluginSession, SchemaField, ListSessionsResponse, Hop, LineageResponse,))
+)]
+pub(crate) struct Api;
+
+impl Api {
+    pub(crate) fn build_router() -> Router {
+        Router::new()
+            .route("/hub/sessions", get(list_sessions))
+            .route("/hub/sessions/by-id/{id}", get(get_session))
+            .route("/hub/lineage/by-event-id/{id}", get(get_lineage))
+    }
+}
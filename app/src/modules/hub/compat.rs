@@ -0,0 +1,319 @@
+use flwrs_plugin::schema::schema::{
+    field_type::Enum as FieldType, FieldDefinition, SchemaDefinition,
+};
+use std::fmt::{Display, Formatter};
+
+/// A single reason why an upstream schema cannot satisfy a downstream one.
+#[derive(Debug, Clone)]
+pub(crate) enum Mismatch {
+    /// The downstream plugin expects a field the upstream plugin never emits.
+    MissingField { path: String, expected: FieldType },
+    /// Both plugins know the field, but disagree on its type.
+    WrongType {
+        path: String,
+        expected: FieldType,
+        actual: FieldType,
+    },
+    /// The downstream plugin describes the element type of an array or map, the upstream
+    /// plugin does not.
+    MissingNestedType { path: String },
+}
+
+impl Mismatch {
+    pub(crate) fn path(&self) -> &str {
+        match self {
+            Mismatch::MissingField { path, .. }
+            | Mismatch::WrongType { path, .. }
+            | Mismatch::MissingNestedType { path } => path.as_str(),
+        }
+    }
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::MissingField { path, expected } => write!(
+                f,
+                "field [{path}] of type [{expected}] is missing",
+                expected = expected.as_str_name()
+            ),
+            Mismatch::WrongType {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "field [{path}] is [{actual}], expected [{expected}]",
+                actual = actual.as_str_name(),
+                expected = expected.as_str_name()
+            ),
+            Mismatch::MissingNestedType { path } => {
+                write!(f, "field [{path}] has no element type definition")
+            }
+        }
+    }
+}
+
+/// Everything that keeps an upstream out-schema from satisfying a downstream in-schema.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SchemaDiff {
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl SchemaDiff {
+    pub(crate) fn is_compatible(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl Display for SchemaDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (idx, mismatch) in self.mismatches.iter().enumerate() {
+            if idx > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{mismatch}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that every field the downstream plugin expects is emitted by the upstream plugin
+/// with the same type. Extra upstream fields are fine, they are simply ignored downstream.
+pub(crate) fn check(upstream: &SchemaDefinition, downstream: &SchemaDefinition) -> SchemaDiff {
+    let mut diff = SchemaDiff::default();
+    check_fields(&upstream.fields, &downstream.fields, "", &mut diff);
+    diff
+}
+
+fn check_fields(
+    upstream: &[FieldDefinition],
+    downstream: &[FieldDefinition],
+    prefix: &str,
+    diff: &mut SchemaDiff,
+) {
+    for expected in downstream {
+        let path = if prefix.is_empty() {
+            expected.key.clone()
+        } else {
+            format!("{prefix}.{key}", key = expected.key)
+        };
+        match upstream.iter().find(|field| field.key == expected.key) {
+            Some(actual) => check_field(actual, expected, path, diff),
+            None => diff.mismatches.push(Mismatch::MissingField {
+                path,
+                expected: expected.r#type(),
+            }),
+        }
+    }
+}
+
+fn check_field(
+    actual: &FieldDefinition,
+    expected: &FieldDefinition,
+    path: String,
+    diff: &mut SchemaDiff,
+) {
+    if actual.r#type() != expected.r#type() {
+        diff.mismatches.push(Mismatch::WrongType {
+            path,
+            expected: expected.r#type(),
+            actual: actual.r#type(),
+        });
+        return;
+    }
+    match expected.r#type() {
+        FieldType::Array | FieldType::Map => {
+            let Some(expected_nested) = expected.nested_type_definition.as_deref() else {
+                return;
+            };
+            let path = match expected.r#type() {
+                FieldType::Array => format!("{path}[]"),
+                _ => format!("{path}{{}}"),
+            };
+            match actual.nested_type_definition.as_deref() {
+                Some(actual_nested) => check_field(actual_nested, expected_nested, path, diff),
+                None => diff.mismatches.push(Mismatch::MissingNestedType { path }),
+            }
+        }
+        FieldType::Object => check_fields(
+            &actual.object_fields,
+            &expected.object_fields,
+            path.as_str(),
+            diff,
+        ),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(key: &str, r#type: FieldType) -> FieldDefinition {
+        FieldDefinition {
+            key: key.to_string(),
+            r#type: r#type as i32,
+            ..Default::default()
+        }
+    }
+
+    fn nested(key: &str, r#type: FieldType, element: FieldDefinition) -> FieldDefinition {
+        FieldDefinition {
+            nested_type_definition: Some(Box::new(element)),
+            ..field(key, r#type)
+        }
+    }
+
+    fn object(key: &str, fields: Vec<FieldDefinition>) -> FieldDefinition {
+        FieldDefinition {
+            object_fields: fields,
+            ..field(key, FieldType::Object)
+        }
+    }
+
+    fn schema(fields: Vec<FieldDefinition>) -> SchemaDefinition {
+        SchemaDefinition { fields }
+    }
+
+    fn paths(diff: &SchemaDiff) -> Vec<&str> {
+        diff.mismatches.iter().map(Mismatch::path).collect()
+    }
+
+    #[test]
+    fn extra_upstream_fields_are_compatible() {
+        let upstream = schema(vec![
+            field("url", FieldType::String),
+            field("status", FieldType::U32),
+        ]);
+        let downstream = schema(vec![field("url", FieldType::String)]);
+        assert!(check(&upstream, &downstream).is_compatible());
+    }
+
+    #[test]
+    fn missing_field() {
+        let upstream = schema(vec![field("url", FieldType::String)]);
+        let downstream = schema(vec![
+            field("url", FieldType::String),
+            field("status", FieldType::U32),
+        ]);
+        let diff = check(&upstream, &downstream);
+        assert!(matches!(
+            diff.mismatches.as_slice(),
+            [Mismatch::MissingField { path, expected: FieldType::U32 }] if path == "status"
+        ));
+    }
+
+    #[test]
+    fn wrong_type() {
+        let upstream = schema(vec![field("status", FieldType::String)]);
+        let downstream = schema(vec![field("status", FieldType::U32)]);
+        let diff = check(&upstream, &downstream);
+        assert!(matches!(
+            diff.mismatches.as_slice(),
+            [Mismatch::WrongType {
+                path,
+                expected: FieldType::U32,
+                actual: FieldType::String,
+            }] if path == "status"
+        ));
+    }
+
+    #[test]
+    fn array_without_element_type_downstream_takes_any_array() {
+        let upstream = schema(vec![nested(
+            "tags",
+            FieldType::Array,
+            field("", FieldType::String),
+        )]);
+        let downstream = schema(vec![field("tags", FieldType::Array)]);
+        assert!(check(&upstream, &downstream).is_compatible());
+    }
+
+    #[test]
+    fn array_without_element_type_upstream() {
+        let upstream = schema(vec![field("tags", FieldType::Array)]);
+        let downstream = schema(vec![nested(
+            "tags",
+            FieldType::Array,
+            field("", FieldType::String),
+        )]);
+        let diff = check(&upstream, &downstream);
+        assert!(matches!(
+            diff.mismatches.as_slice(),
+            [Mismatch::MissingNestedType { path }] if path == "tags[]"
+        ));
+    }
+
+    #[test]
+    fn array_with_wrong_element_type() {
+        let upstream = schema(vec![nested(
+            "tags",
+            FieldType::Array,
+            field("", FieldType::I32),
+        )]);
+        let downstream = schema(vec![nested(
+            "tags",
+            FieldType::Array,
+            field("", FieldType::String),
+        )]);
+        assert_eq!(paths(&check(&upstream, &downstream)), vec!["tags[]"]);
+    }
+
+    #[test]
+    fn map_with_element_type() {
+        let headers = || nested("headers", FieldType::Map, field("", FieldType::String));
+        let upstream = schema(vec![headers()]);
+        let downstream = schema(vec![headers()]);
+        assert!(check(&upstream, &downstream).is_compatible());
+
+        let upstream = schema(vec![field("headers", FieldType::Map)]);
+        let diff = check(&upstream, &downstream);
+        assert!(matches!(
+            diff.mismatches.as_slice(),
+            [Mismatch::MissingNestedType { path }] if path == "headers{}"
+        ));
+    }
+
+    #[test]
+    fn nested_object_fields() {
+        let upstream = schema(vec![object(
+            "request",
+            vec![
+                field("method", FieldType::String),
+                object("body", vec![field("size", FieldType::I32)]),
+            ],
+        )]);
+        let downstream = schema(vec![object(
+            "request",
+            vec![
+                field("method", FieldType::String),
+                field("url", FieldType::String),
+                object("body", vec![field("size", FieldType::U32)]),
+            ],
+        )]);
+        let diff = check(&upstream, &downstream);
+        assert_eq!(paths(&diff), vec!["request.url", "request.body.size"]);
+        assert_eq!(
+            diff.to_string(),
+            "field [request.url] of type [STRING] is missing; \
+             field [request.body.size] is [I32], expected [U32]"
+        );
+    }
+
+    #[test]
+    fn objects_in_arrays() {
+        let item = |r#type| object("", vec![field("id", r#type)]);
+        let upstream = schema(vec![nested(
+            "items",
+            FieldType::Array,
+            item(FieldType::I32),
+        )]);
+        let downstream = schema(vec![nested(
+            "items",
+            FieldType::Array,
+            item(FieldType::String),
+        )]);
+        assert_eq!(paths(&check(&upstream, &downstream)), vec!["items[].id"]);
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Hops kept per trace. A trace fanning out further stops being recorded.
+const MAX_HOPS_PER_TRACE: usize = 1000;
+
+/// What happened to an event at one hop.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum HopKind {
+    /// A plugin emitted the event.
+    Emitted,
+    /// The runtime sent a copy of the event along an edge.
+    Delivered,
+    /// The target plugin acked the event.
+    Acked,
+    /// The target plugin rejected the event.
+    Nacked,
+}
+
+impl HopKind {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            HopKind::Emitted => "emitted",
+            HopKind::Delivered => "delivered",
+            HopKind::Acked => "acked",
+            HopKind::Nacked => "nacked",
+        }
+    }
+}
+
+/// One step of an event through a scene.
+#[derive(Clone, Debug)]
+pub(crate) struct Hop {
+    pub kind: HopKind,
+    pub event_id: String,
+    pub parent_ids: Vec<String>,
+    /// Plugin that emitted the event, or the target plugin of a delivery.
+    pub plugin_id: String,
+    pub edge_id: Option<String>,
+    /// Delivery attempt, counting from 1.
+    pub attempt: Option<u32>,
+    /// Why the target plugin rejected the event.
+    pub message: Option<String>,
+    pub time: DateTime<Local>,
+}
+
+impl Hop {
+    pub(crate) fn new(kind: HopKind, event_id: &str, plugin_id: &str) -> Self {
+        Self {
+            kind,
+            event_id: event_id.to_string(),
+            parent_ids: vec![],
+            plugin_id: plugin_id.to_string(),
+            edge_id: None,
+            attempt: None,
+            message: None,
+            time: Local::now(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Traces {
+    hops: HashMap<String, Vec<Hop>>,
+    /// Trace IDs, oldest first, to evict traces once the store is full.
+    order: VecDeque<String>,
+    /// Trace ID by event ID.
+    events: HashMap<String, String>,
+}
+
+/// Recent hops of the events routed by the runtime, grouped by trace. Kept in memory only and
+/// bounded: once it holds `capacity` traces, the oldest trace is dropped for each new one.
+pub(crate) struct Lineage {
+    capacity: usize,
+    traces: Mutex<Traces>,
+}
+
+impl Lineage {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            traces: Mutex::new(Traces::default()),
+        }
+    }
+
+    /// Records a hop of a trace. Hops without a trace are recorded on the trace of their
+    /// event, if that is known.
+    pub(crate) fn record(&self, trace_id: Option<&str>, hop: Hop) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut traces = self.traces.lock().expect("lineage lock poisoned");
+        let trace_id = match trace_id {
+            Some(trace_id) if !trace_id.is_empty() => trace_id.to_string(),
+            _ => match traces.events.get(hop.event_id.as_str()) {
+                Some(trace_id) => trace_id.clone(),
+                None => return,
+            },
+        };
+        if !traces.hops.contains_key(trace_id.as_str()) {
+            while traces.order.len() >= self.capacity {
+                let Some(evicted) = traces.order.pop_front() else {
+                    break;
+                };
+                for evicted_hop in traces.hops.remove(evicted.as_str()).unwrap_or_default() {
+                    traces.events.remove(evicted_hop.event_id.as_str());
+                }
+            }
+            traces.order.push_back(trace_id.clone());
+        }
+        let hops = traces.hops.entry(trace_id.clone()).or_default();
+        if hops.len() >= MAX_HOPS_PER_TRACE {
+            return;
+        }
+        let event_id = hop.event_id.clone();
+        hops.push(hop);
+        traces.events.insert(event_id, trace_id);
+    }
+
+    /// The trace an event belongs to and all its recorded hops, in the order they happened.
+    pub(crate) fn trace_of(&self, event_id: &str) -> Option<(String, Vec<Hop>)> {
+        let traces = self.traces.lock().expect("lineage lock poisoned");
+        let trace_id = traces.events.get(event_id)?;
+        let mut hops = traces.hops.get(trace_id.as_str())?.clone();
+        hops.sort_by_key(|hop| hop.time);
+        Some((trace_id.clone(), hops))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emitted(event_id: &str) -> Hop {
+        Hop::new(HopKind::Emitted, event_id, "plugin")
+    }
+
+    #[test]
+    fn hops_without_trace_join_the_trace_of_their_event() {
+        let lineage = Lineage::new(2);
+        lineage.record(Some("t1"), emitted("e1"));
+        lineage.record(None, Hop::new(HopKind::Acked, "e1", "sink"));
+        lineage.record(None, emitted("unknown"));
+
+        let (trace_id, hops) = lineage.trace_of("e1").expect("trace of e1");
+        assert_eq!(trace_id, "t1");
+        assert_eq!(hops.len(), 2);
+        assert!(lineage.trace_of("unknown").is_none());
+    }
+
+    #[test]
+    fn oldest_trace_is_evicted_when_full() {
+        let lineage = Lineage::new(2);
+        lineage.record(Some("t1"), emitted("e1"));
+        lineage.record(Some("t2"), emitted("e2"));
+        lineage.record(Some("t1"), emitted("e1b"));
+        lineage.record(Some("t3"), emitted("e3"));
+
+        assert!(lineage.trace_of("e1").is_none());
+        assert!(lineage.trace_of("e1b").is_none());
+        assert_eq!(lineage.trace_of("e2").expect("trace of e2").0, "t2");
+        assert_eq!(lineage.trace_of("e3").expect("trace of e3").0, "t3");
+
+        // Events of an evicted trace no longer lead anywhere.
+        lineage.record(None, emitted("e1"));
+        assert!(lineage.trace_of("e1").is_none());
+    }
+
+    #[test]
+    fn hops_past_the_limit_are_not_recorded() {
+        let lineage = Lineage::new(1);
+        for idx in 0..=MAX_HOPS_PER_TRACE {
+            lineage.record(Some("t1"), emitted(format!("e{idx}").as_str()));
+        }
+        let (_, hops) = lineage.trace_of("e0").expect("trace of e0");
+        assert_eq!(hops.len(), MAX_HOPS_PER_TRACE);
+        assert!(lineage
+            .trace_of(format!("e{MAX_HOPS_PER_TRACE}").as_str())
+            .is_none());
+    }
+
+    #[test]
+    fn nothing_is_recorded_without_capacity() {
+        let lineage = Lineage::new(0);
+        lineage.record(Some("t1"), emitted("e1"));
+        assert!(lineage.trace_of("e1").is_none());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::modules::hub::service::HubTlsConfig;
+use crate::modules::hub::tls;
+use flwrs_plugin::plugin::core::UNIX_SCHEME;
+use std::io;
+#[cfg(unix)]
+use std::path::PathBuf;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tokio_rustls::TlsAcceptor;
+
+/// A plugin connection, over TCP or a Unix domain socket.
+pub(crate) trait PluginStream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T> PluginStream for T where T: AsyncRead + AsyncWrite + Send + Unpin {}
+
+/// Socket the hub accepts plugin connections on.
+pub(crate) enum Listener {
+    Tcp(TcpListener, Option<TlsAcceptor>),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    /// Listens on `host:port`, or on a Unix domain socket if `host` starts with
+    /// [`UNIX_SCHEME`]. A socket file left behind by a previous run is replaced. TLS only
+    /// applies to TCP, a Unix domain socket is protected by its file permissions instead.
+    pub(crate) async fn bind(
+        host: &str,
+        port: u16,
+        tls: Option<&HubTlsConfig>,
+    ) -> io::Result<Self> {
+        match host.strip_prefix(UNIX_SCHEME) {
+            Some(path) => {
+                if tls.is_some() {
+                    log::warn!("Hub: TLS is not used on Unix domain socket [{path}]");
+                }
+                Self::bind_unix(path).await
+            }
+            None => {
+                let acceptor = tls.map(tls::acceptor).transpose()?;
+                Ok(Listener::Tcp(
+                    TcpListener::bind(format!("{host}:{port}")).await?,
+                    acceptor,
+                ))
+            }
+        }
+    }
+
+    /// Acceptor to secure accepted connections with, if the listener serves TLS.
+    pub(crate) fn acceptor(&self) -> Option<TlsAcceptor> {
+        match self {
+            Listener::Tcp(_, acceptor) => acceptor.clone(),
+            #[cfg(unix)]
+            Listener::Unix(..) => None,
+        }
+    }
+
+    #[cfg(unix)]
+    async fn bind_unix(path: &str) -> io::Result<Self> {
+        let path = PathBuf::from(path);
+        if path.exists() {
+            if UnixStream::connect(&path).await.is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("socket [{}] is in use", path.display()),
+                ));
+            }
+            std::fs::remove_file(&path)?;
+        }
+        Ok(Listener::Unix(UnixListener::bind(&path)?, path))
+    }
+
+    #[cfg(not(unix))]
+    async fn bind_unix(_path: &str) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Unix domain sockets are not supported on this platform",
+        ))
+    }
+
+    /// Waits for the next plugin connection. Returns the stream, not secured yet, and the
+    /// address of the peer.
+    pub(crate) async fn accept(&self) -> io::Result<(Box<dyn PluginStream>, String)> {
+        match self {
+            Listener::Tcp(listener, _) => {
+                let (stream, addr) = listener.accept().await?;
+                Ok((Box::new(stream), addr.to_string()))
+            }
+            // peers of a Unix socket are unnamed, the socket itself identifies them best
+            #[cfg(unix)]
+            Listener::Unix(listener, path) => {
+                let (stream, _) = listener.accept().await?;
+                Ok((Box::new(stream), format!("{UNIX_SCHEME}{}", path.display())))
+            }
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix(_, path) = self
+            && let Err(e) = std::fs::remove_file(path.as_path())
+        {
+            log::warn!(
+                "Hub: failed to remove socket [{path}]: {e}",
+                path = path.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use flwrs_plugin::schema::schema::{
+    field_type::Enum as FieldType, field_value::Value, ArrayValue, ConfigOption, Field, FieldValue,
+    MapValue,
+};
+use serde_json::{Map, Value as Json};
+use std::fmt::{Display, Formatter};
+
+/// A single reason why a node config does not fit the options its plugin declares.
+#[derive(Debug, Clone)]
+pub(crate) enum ConfigMismatch {
+    /// The node sets an option the plugin does not declare.
+    UnknownOption { key: String },
+    /// The plugin requires an option the node leaves out, and declares no default for it.
+    MissingOption { key: String },
+    /// The value cannot be read as the declared type.
+    InvalidValue {
+        key: String,
+        expected: FieldType,
+        reason: String,
+    },
+    /// The default the plugin declares cannot be read as the declared type.
+    InvalidDefault {
+        key: String,
+        expected: FieldType,
+        reason: String,
+    },
+}
+
+impl Display for ConfigMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigMismatch::UnknownOption { key } => write!(f, "option [{key}] is unknown"),
+            ConfigMismatch::MissingOption { key } => {
+                write!(f, "option [{key}] is required")
+            }
+            ConfigMismatch::InvalidValue {
+                key,
+                expected,
+                reason,
+            } => write!(
+                f,
+                "option [{key}] is not a valid [{expected}]: {reason}",
+                expected = expected.as_str_name()
+            ),
+            ConfigMismatch::InvalidDefault {
+                key,
+                expected,
+                reason,
+            } => write!(
+                f,
+                "default of option [{key}] is not a valid [{expected}]: {reason}",
+                expected = expected.as_str_name()
+            ),
+        }
+    }
+}
+
+/// Everything that keeps a node config from fitting the options of its plugin.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConfigReport {
+    pub mismatches: Vec<ConfigMismatch>,
+}
+
+impl ConfigReport {
+    pub(crate) fn is_valid(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl Display for ConfigReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mismatches: Vec<String> = self.mismatches.iter().map(ToString::to_string).collect();
+        f.write_str(mismatches.join("; ").as_str())
+    }
+}
+
+/// Types a node config by the options its plugin declares, filling in the defaults of the
+/// options it leaves out. `null` counts as left out.
+pub(crate) fn resolve(
+    options: &[ConfigOption],
+    config: &Map<String, Json>,
+) -> Result<Vec<Field>, ConfigReport> {
+    let mut report = ConfigReport::default();
+    for key in config.keys() {
+        if !options.iter().any(|option| option.key == *key) {
+            report
+                .mismatches
+                .push(ConfigMismatch::UnknownOption { key: key.clone() });
+        }
+    }
+    let mut fields = Vec::with_capacity(options.len());
+    for option in options {
+        let expected = option.r#type();
+        let value = match config.get(option.key.as_str()) {
+            Some(value) if !value.is_null() => {
+                field_value(expected, value).map_err(|reason| ConfigMismatch::InvalidValue {
+                    key: option.key.clone(),
+                    expected,
+                    reason,
+                })
+            }
+            _ if !option.default_value.is_empty() => field_value(
+                expected,
+                &default_json(expected, option.default_value.as_str()),
+            )
+            .map_err(|reason| ConfigMismatch::InvalidDefault {
+                key: option.key.clone(),
+                expected,
+                reason,
+            }),
+            _ if option.required => Err(ConfigMismatch::MissingOption {
+                key: option.key.clone(),
+            }),
+            _ => continue,
+        };
+        match value {
+            Ok(value) => fields.push(Field {
+                key: option.key.clone(),
+                value: Some(value),
+            }),
+            Err(mismatch) => report.mismatches.push(mismatch),
+        }
+    }
+    if report.is_valid() {
+        Ok(fields)
+    } else {
+        Err(report)
+    }
+}
+
+/// Defaults are declared as text. Text that is not JSON is taken as a string, so that string,
+/// date-time and bytes defaults need no quotes.
+fn default_json(expected: FieldType, default: &str) -> Json {
+    match expected {
+        FieldType::String => Json::from(default),
+        _ => serde_json::from_str(default).unwrap_or_else(|_| Json::from(default)),
+    }
+}
+
+/// Reads a JSON value as the given type. Date-times are RFC 3339 strings and bytes hex strings;
+/// elements of arrays, maps and objects carry no declared type and are typed by their JSON
+/// value.
+fn field_value(expected: FieldType, value: &Json) -> Result<FieldValue, String> {
+    let value = match (expected, value) {
+        (FieldType::String, Json::String(s)) => Value::String(s.clone()),
+        (FieldType::I32, Json::Number(n)) => Value::I32(
+            n.as_i64()
+                .and_then(|n| i32::try_from(n).ok())
+                .ok_or_else(|| format!("[{n}] is out of range"))?,
+        ),
+        (FieldType::U32, Json::Number(n)) => Value::U32(
+            n.as_u64()
+                .and_then(|n| u32::try_from(n).ok())
+                .ok_or_else(|| format!("[{n}] is out of range"))?,
+        ),
+        (FieldType::F32, Json::Number(n)) => Value::F32(n.as_f64().unwrap_or_default() as f32),
+        (FieldType::Bool, Json::Bool(b)) => Value::Bool(*b),
+        (FieldType::Datetime, Json::String(s)) => {
+            let time = chrono::DateTime::parse_from_rfc3339(s).map_err(|e| e.to_string())?;
+            Value::DateTime(prost_types::Timestamp {
+                seconds: time.timestamp(),
+                nanos: time.timestamp_subsec_nanos() as i32,
+            })
+        }
+        (FieldType::Bytes, Json::String(s)) => Value::Bytes(hex_bytes(s)?),
+        (FieldType::Array, Json::Array(_))
+        | (FieldType::Map, Json::Object(_))
+        | (FieldType::Object, Json::Object(_)) => return Ok(infer(value)),
+        (_, value) => return Err(format!("got [{value}]")),
+    };
+    Ok(FieldValue { value: Some(value) })
+}
+
+fn infer(value: &Json) -> FieldValue {
+    let value = match value {
+        Json::Null => None,
+        Json::Bool(b) => Some(Value::Bool(*b)),
+        Json::Number(n) => Some(
+            match (
+                n.as_i64().and_then(|n| i32::try_from(n).ok()),
+                n.as_u64().and_then(|n| u32::try_from(n).ok()),
+            ) {
+                (Some(n), _) => Value::I32(n),
+                (None, Some(n)) => Value::U32(n),
+                (None, None) => Value::F32(n.as_f64().unwrap_or_default() as f32),
+            },
+        ),
+        Json::String(s) => Some(Value::String(s.clone())),
+        Json::Array(items) => Some(Value::Array(ArrayValue {
+            value: items.iter().map(infer).collect(),
+        })),
+        Json::Object(entries) => Some(Value::Map(MapValue {
+            value: entries
+                .iter()
+                .map(|(key, item)| (key.clone(), infer(item)))
+                .collect(),
+        })),
+    };
+    FieldValue { value }
+}
+
+fn hex_bytes(s: &str) -> Result<Vec<u8>, String> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return Err(format!("[{s}] is not hex encoded"));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| format!("[{s}] is not hex encoded"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn option(key: &str, r#type: FieldType, required: bool, default: &str) -> ConfigOption {
+        ConfigOption {
+            key: key.to_string(),
+            r#type: r#type as i32,
+            required,
+            default_value: default.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn config(value: Json) -> Map<String, Json> {
+        match value {
+            Json::Object(config) => config,
+            _ => panic!("config is not an object"),
+        }
+    }
+
+    fn value_of<'a>(fields: &'a [Field], key: &str) -> Option<&'a Value> {
+        fields
+            .iter()
+            .find(|field| field.key == key)
+            .and_then(|field| field.value.as_ref())
+            .and_then(|value| value.value.as_ref())
+    }
+
+    #[test]
+    fn types_values_and_fills_in_defaults() {
+        let options = [
+            option("url", FieldType::String, true, ""),
+            option("retries", FieldType::U32, false, "3"),
+            option("method", FieldType::String, false, "POST"),
+            option("verbose", FieldType::Bool, false, ""),
+        ];
+        let fields = resolve(
+            &options,
+            &config(json!({"url": "http://localhost", "method": null})),
+        )
+        .expect("config is valid");
+
+        assert_eq!(fields.len(), 3);
+        assert_eq!(
+            value_of(&fields, "url"),
+            Some(&Value::String("http://localhost".to_string()))
+        );
+        assert_eq!(value_of(&fields, "retries"), Some(&Value::U32(3)));
+        assert_eq!(
+            value_of(&fields, "method"),
+            Some(&Value::String("POST".to_string()))
+        );
+        assert!(value_of(&fields, "verbose").is_none());
+    }
+
+    #[test]
+    fn reports_every_mismatch() {
+        let options = [
+            option("url", FieldType::String, true, ""),
+            option("retries", FieldType::U32, false, ""),
+            option("timeout", FieldType::I32, false, "soon"),
+        ];
+        let report = resolve(&options, &config(json!({"retries": -1, "extra": true})))
+            .expect_err("config is invalid");
+
+        assert_eq!(
+            report.to_string(),
+            "option [extra] is unknown; \
+             option [url] is required; \
+             option [retries] is not a valid [U32]: [-1] is out of range; \
+             default of option [timeout] is not a valid [I32]: got [\"soon\"]"
+        );
+    }
+
+    #[test]
+    fn reads_scalars() {
+        assert_eq!(
+            field_value(FieldType::I32, &json!(-7)).unwrap().value,
+            Some(Value::I32(-7))
+        );
+        assert!(field_value(FieldType::I32, &json!(i64::MAX)).is_err());
+        assert!(field_value(FieldType::U32, &json!(1.5)).is_err());
+        assert_eq!(
+            field_value(FieldType::F32, &json!(1.5)).unwrap().value,
+            Some(Value::F32(1.5))
+        );
+        assert!(field_value(FieldType::Bool, &json!("true")).is_err());
+    }
+
+    #[test]
+    fn reads_datetimes_and_bytes() {
+        assert_eq!(
+            field_value(FieldType::Datetime, &json!("1970-01-01T00:00:01.5Z"))
+                .unwrap()
+                .value,
+            Some(Value::DateTime(prost_types::Timestamp {
+                seconds: 1,
+                nanos: 500_000_000,
+            }))
+        );
+        assert!(field_value(FieldType::Datetime, &json!("yesterday")).is_err());
+        assert_eq!(
+            field_value(FieldType::Bytes, &json!("00ff")).unwrap().value,
+            Some(Value::Bytes(vec![0x00, 0xff]))
+        );
+        assert!(field_value(FieldType::Bytes, &json!("0ff")).is_err());
+        assert!(field_value(FieldType::Bytes, &json!("zz")).is_err());
+    }
+
+    #[test]
+    fn infers_elements_of_collections() {
+        let value = field_value(FieldType::Array, &json!([1, 3_000_000_000u32, "a", null]))
+            .unwrap()
+            .value;
+        let Some(Value::Array(array)) = value else {
+            panic!("not an array: {value:?}");
+        };
+        let elements: Vec<_> = array.value.into_iter().map(|item| item.value).collect();
+        assert_eq!(
+            elements,
+            vec![
+                Some(Value::I32(1)),
+                Some(Value::U32(3_000_000_000)),
+                Some(Value::String("a".to_string())),
+                None,
+            ]
+        );
+
+        let value = field_value(FieldType::Map, &json!({"k": true}))
+            .unwrap()
+            .value;
+        let Some(Value::Map(map)) = value else {
+            panic!("not a map: {value:?}");
+        };
+        assert_eq!(map.value["k"].value, Some(Value::Bool(true)));
+        assert!(field_value(FieldType::Map, &json!([])).is_err());
+    }
+}
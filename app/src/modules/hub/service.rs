@@ -0,0 +1,1210 @@
+use crate::modules::activity::service::ActivityKind;
+use crate::modules::hub::lineage::{Hop, HopKind, Lineage};
+use crate::modules::hub::listener::{Listener, PluginStream};
+use crate::modules::hub::plugin_config;
+use crate::modules::hub::plugin_config::ConfigReport;
+use crate::modules::hub::session::{
+    Handshake, HubEvent, PluginEvent, PluginMessage, Reconfiguration, RuntimeMessage, Session,
+    SessionSet, Settlement,
+};
+use crate::modules::{activity, supervisor};
+use async_trait::async_trait;
+use flwrs_core::config;
+use flwrs_core::config::main_config;
+use flwrs_core::config::Source;
+use flwrs_core::metrics;
+use flwrs_core::registry;
+use flwrs_core::registry::{Health, RegistryError};
+use flwrs_plugin::plugin::framing;
+use flwrs_plugin::plugin::framing::ProtocolVersion;
+use flwrs_plugin::schema::common::log_level::Enum as LogLevel;
+use flwrs_plugin::schema::common::plugin_type::Enum as PluginType;
+use flwrs_plugin::schema::common::{ErrorEvent, LogEvent, Pause, Ping, Reconfigure, Resume};
+use flwrs_plugin::schema::schema::Field;
+use flwrs_plugin::schema::sink::{
+    runtime_sink_message::Payload as RuntimeSinkPayload, sink_message::Payload as SinkPayload,
+    RuntimeSinkMessage,
+};
+use flwrs_plugin::schema::source::{
+    runtime_source_message::Payload as RuntimeSourcePayload,
+    source_message::Payload as SourcePayload, RuntimeSourceMessage,
+};
+use flwrs_plugin::schema::transform::{
+    runtime_transform_message::Payload as RuntimeTransformPayload,
+    transform_message::Payload as TransformPayload, RuntimeTransformMessage,
+};
+use flwrs_plugin::schema::{sink, source, transform};
+use lazy_static::lazy_static;
+use prometheus::{IntCounter, IntCounterVec, IntGauge, Opts, Registry};
+use serde::Deserialize;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use ulid::Ulid;
+
+lazy_static! {
+    static ref HANDSHAKE_FAILURES: IntCounter = IntCounter::new(
+        "hub_handshake_failures_total",
+        "Plugin connections closed because their handshake failed",
+    )
+    .expect("handshake failures metric is valid");
+    static ref SESSIONS: IntGauge = IntGauge::new("hub_sessions", "Plugins connected to the hub")
+        .expect("sessions metric is valid");
+    static ref EVENTS_RECEIVED: IntCounterVec = IntCounterVec::new(
+        Opts::new("hub_events_received_total", "Events emitted by a plugin"),
+        &["plugin_id"],
+    )
+    .expect("events received metric is valid");
+    static ref UNRESPONSIVE_PLUGINS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "hub_unresponsive_plugins_total",
+            "Sessions closed because the plugin stopped answering heartbeats",
+        ),
+        &["plugin_id"],
+    )
+    .expect("unresponsive plugins metric is valid");
+}
+
+#[derive(Deserialize)]
+pub(crate) struct HubConfig {
+    /// Host name or IP to listen on, or a Unix domain socket address such as
+    /// `unix:///run/flwrs/hub.sock`.
+    host: String,
+    /// TCP port to listen on. Not used with a Unix domain socket.
+    #[serde(default = "default_port")]
+    port: u16,
+    #[serde(default = "default_handshake_timeout_seconds")]
+    handshake_timeout_seconds: u64,
+    #[serde(default = "default_outbound_queue_size")]
+    outbound_queue_size: usize,
+    #[serde(default = "default_event_queue_size")]
+    event_queue_size: usize,
+    /// Traces whose hops are kept for lineage queries. 0 turns lineage off.
+    #[serde(default = "default_lineage_capacity")]
+    lineage_capacity: usize,
+    /// How often a plugin is pinged. 0 turns heartbeats off.
+    #[serde(default = "default_heartbeat_interval_seconds")]
+    heartbeat_interval_seconds: u64,
+    /// How long a plugin may stay silent before it is considered hung and restarted.
+    #[serde(default = "default_heartbeat_timeout_seconds")]
+    heartbeat_timeout_seconds: u64,
+    /// How long a running plugin gets to accept or reject a new config.
+    #[serde(default = "default_reconfigure_timeout_seconds")]
+    reconfigure_timeout_seconds: u64,
+    /// Token accepted from any plugin, for plugins that are not launched by the supervisor.
+    #[serde(default)]
+    shared_token: Option<String>,
+    /// Accepts plugins without a valid token. Only meant for development.
+    #[serde(default)]
+    allow_unauthenticated: bool,
+    /// Serves plugin connections over TLS. Only applies to TCP.
+    #[serde(default)]
+    tls: Option<HubTlsConfig>,
+}
+
+/// TLS with client certificates for plugin connections. All files are PEM encoded.
+#[derive(Deserialize)]
+pub(crate) struct HubTlsConfig {
+    /// Certificate chain the hub presents.
+    pub cert_path: String,
+    pub key_path: String,
+    /// CA certificates that plugins verify the hub with, and that must have signed the client
+    /// certificate a plugin presents.
+    pub ca_path: String,
+    /// Client certificate handed to the plugins launched by the supervisor.
+    #[serde(default)]
+    pub plugin_cert_path: Option<String>,
+    #[serde(default)]
+    pub plugin_key_path: Option<String>,
+    /// Name plugins expect in the hub certificate, if it's not the hub host.
+    #[serde(default)]
+    pub server_name: Option<String>,
+}
+
+fn default_port() -> u16 {
+    7300
+}
+
+fn default_handshake_timeout_seconds() -> u64 {
+    10
+}
+
+fn default_outbound_queue_size() -> usize {
+    256
+}
+
+fn default_event_queue_size() -> usize {
+    1024
+}
+
+fn default_lineage_capacity() -> usize {
+    10_000
+}
+
+fn default_heartbeat_interval_seconds() -> u64 {
+    10
+}
+
+fn default_heartbeat_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_reconfigure_timeout_seconds() -> u64 {
+    10
+}
+
+impl HubConfig {
+    /// Reads the `[hub]` section, or the defaults if there is none. A section that fails to
+    /// parse is an error: falling back to the defaults would quietly drop its TLS and token.
+    pub(crate) fn read() -> Result<Self, HubError> {
+        let main_config = main_config();
+        let path = ["hub".to_string()];
+        match config::read_struct(main_config, &path) {
+            Some(config) => Ok(config),
+            None if main_config.sub(&path).is_some() => {
+                Err(HubError::Config("section [hub] is invalid".to_string()))
+            }
+            None => Ok(Self::defaults()),
+        }
+    }
+
+    fn defaults() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: default_port(),
+            handshake_timeout_seconds: default_handshake_timeout_seconds(),
+            outbound_queue_size: default_outbound_queue_size(),
+            event_queue_size: default_event_queue_size(),
+            lineage_capacity: default_lineage_capacity(),
+            heartbeat_interval_seconds: default_heartbeat_interval_seconds(),
+            heartbeat_timeout_seconds: default_heartbeat_timeout_seconds(),
+            reconfigure_timeout_seconds: default_reconfigure_timeout_seconds(),
+            shared_token: None,
+            allow_unauthenticated: false,
+            tls: None,
+        }
+    }
+
+    pub(crate) fn host(&self) -> &str {
+        self.host.as_str()
+    }
+
+    pub(crate) fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub(crate) fn tls(&self) -> Option<&HubTlsConfig> {
+        self.tls.as_ref()
+    }
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum HubError {
+    #[error("invalid config: {0}")]
+    Config(String),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid message: {0}")]
+    Decode(#[from] prost::DecodeError),
+    #[error("handshake failed: {0}")]
+    Handshake(String),
+    #[error("session [{0}] is closed")]
+    SessionClosed(String),
+    #[error("plugin of session [{0}] stopped answering heartbeats")]
+    Unresponsive(String),
+    #[error("plugin [{0}] failed to authenticate")]
+    Unauthenticated(String),
+    #[error("config of plugin [{0}] is invalid")]
+    InvalidConfig(String),
+    #[error("session [{0}] sent a message in protocol version [{1}], which it didn't agree to")]
+    ProtocolVersion(String, ProtocolVersion),
+}
+
+/// Accepts plugin connections and keeps track of their sessions.
+pub(crate) struct Service {
+    config: HubConfig,
+    /// Why the configured hub section was rejected. The hub then refuses to start.
+    config_error: Option<String>,
+    sessions: SessionSet,
+    events: mpsc::Sender<HubEvent>,
+    events_rx: Mutex<Option<mpsc::Receiver<HubEvent>>>,
+    lineage: Lineage,
+    listening: AtomicBool,
+}
+
+impl Service {
+    pub(crate) fn new(config: Result<HubConfig, HubError>) -> Self {
+        let (config, config_error) = match config {
+            Ok(config) => (config, None),
+            Err(HubError::Config(reason)) => (HubConfig::defaults(), Some(reason)),
+            Err(e) => (HubConfig::defaults(), Some(e.to_string())),
+        };
+        let (events, events_rx) = mpsc::channel(config.event_queue_size);
+        Self {
+            lineage: Lineage::new(config.lineage_capacity),
+            config,
+            config_error,
+            sessions: SessionSet::default(),
+            events,
+            events_rx: Mutex::new(Some(events_rx)),
+            listening: AtomicBool::new(false),
+        }
+    }
+
+    /// Hands out the stream of hub events. There is a single consumer, so only the first
+    /// call gets the receiver.
+    pub(crate) fn take_events(&self) -> Option<mpsc::Receiver<HubEvent>> {
+        self.events_rx.lock().expect("events lock poisoned").take()
+    }
+
+    async fn publish(&self, event: HubEvent) {
+        if self.events.send(event).await.is_err() {
+            log::trace!("Hub: no consumer for hub events");
+        }
+    }
+
+    pub(crate) fn config(&self) -> &HubConfig {
+        &self.config
+    }
+
+    pub(crate) fn sessions(&self) -> &SessionSet {
+        &self.sessions
+    }
+
+    pub(crate) fn lineage(&self) -> &Lineage {
+        &self.lineage
+    }
+
+    async fn handle_connection(
+        &self,
+        stream: Box<dyn PluginStream>,
+        peer_addr: String,
+        tls: Option<TlsAcceptor>,
+        token: CancellationToken,
+    ) -> Result<(), HubError> {
+        let stream = match tls {
+            Some(acceptor) => match self.accept_tls(acceptor, stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    HANDSHAKE_FAILURES.inc();
+                    return Err(e);
+                }
+            },
+            None => stream,
+        };
+        let (mut reader, writer) = tokio::io::split(stream);
+
+        log::trace!("Hub: waiting for handshake from [{peer_addr}]");
+        let handshake = match self.read_handshake(&mut reader).await {
+            Ok(handshake) => handshake,
+            Err(e) => {
+                HANDSHAKE_FAILURES.inc();
+                return Err(e);
+            }
+        };
+        let node_id = match self.authenticate(&handshake).await {
+            Ok(node_id) => node_id,
+            Err(reason) => {
+                HANDSHAKE_FAILURES.inc();
+                log_rejected_session(ErrorEvent {
+                    plugin_id: handshake.plugin_id.clone(),
+                    plugin_type: handshake.plugin_type as i32,
+                    message: format!("session from [{peer_addr}] rejected: {reason}"),
+                    details: vec![],
+                });
+                return Err(HubError::Unauthenticated(handshake.plugin_id));
+            }
+        };
+        let config = match self.configure(&handshake, node_id.as_deref()).await {
+            Ok(config) => config,
+            Err(report) => {
+                HANDSHAKE_FAILURES.inc();
+                log_rejected_session(ErrorEvent {
+                    plugin_id: handshake.plugin_id.clone(),
+                    plugin_type: handshake.plugin_type as i32,
+                    message: format!("session from [{peer_addr}] rejected: {report}"),
+                    details: vec![],
+                });
+                return Err(HubError::InvalidConfig(handshake.plugin_id));
+            }
+        };
+
+        let (outbound_tx, outbound_rx) = mpsc::channel(self.config.outbound_queue_size);
+        let session = Arc::new(Session::new(
+            Ulid::new().to_string(),
+            handshake,
+            node_id,
+            peer_addr,
+            outbound_tx,
+        ));
+        log::info!(
+            "Hub: plugin [{plugin_id}] version [{version}] of node [{node_id}] connected from [{peer_addr}] as session [{session_id}], protocol version [{protocol_version}]",
+            plugin_id = session.plugin_id,
+            node_id = session.node_id.as_deref().unwrap_or("-"),
+            version = session.plugin_version,
+            protocol_version = session.protocol_version,
+            peer_addr = session.peer_addr,
+            session_id = session.id,
+        );
+
+        let write_loop = tokio::spawn(Self::write_loop(session.clone(), outbound_rx, writer));
+        session
+            .send(initialize_response(session.plugin_type, config))
+            .await?;
+        // plugins speaking a version without heartbeats would never answer, and be restarted
+        if self.config.heartbeat_interval_seconds > 0 && session.protocol_version.has_heartbeats() {
+            tokio::spawn(super::service().heartbeat_loop(session.clone()));
+        }
+        self.sessions.insert(session.clone()).await;
+        SESSIONS.inc();
+        self.publish(HubEvent::Connected(session.clone())).await;
+        activity::service()
+            .plugin_activity(
+                &session,
+                ActivityKind::PluginConnected {
+                    session_id: session.id.clone(),
+                    plugin_version: session.plugin_version.clone(),
+                },
+            )
+            .await;
+
+        let result = self.read_loop(&session, &mut reader, token).await;
+
+        self.sessions.remove(session.id.as_str()).await;
+        SESSIONS.dec();
+        activity::service()
+            .plugin_activity(
+                &session,
+                ActivityKind::PluginDisconnected {
+                    session_id: session.id.clone(),
+                },
+            )
+            .await;
+        self.publish(HubEvent::Disconnected(session.clone())).await;
+        log::info!(
+            "Hub: plugin [{plugin_id}] disconnected, session [{session_id}] closed",
+            plugin_id = session.plugin_id,
+            session_id = session.id,
+        );
+        session.close();
+        let _ = write_loop.await;
+        result
+    }
+
+    async fn accept_tls(
+        &self,
+        acceptor: TlsAcceptor,
+        stream: Box<dyn PluginStream>,
+    ) -> Result<Box<dyn PluginStream>, HubError> {
+        match tokio::time::timeout(
+            Duration::from_secs(self.config.handshake_timeout_seconds),
+            acceptor.accept(stream),
+        )
+        .await
+        {
+            Ok(stream) => Ok(Box::new(stream.map_err(|e| {
+                HubError::Handshake(format!("TLS handshake failed: {e}"))
+            })?)),
+            Err(_) => Err(HubError::Handshake("TLS handshake timed out".to_string())),
+        }
+    }
+
+    async fn read_handshake<R>(&self, reader: &mut R) -> Result<Handshake, HubError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        match tokio::time::timeout(
+            Duration::from_secs(self.config.handshake_timeout_seconds),
+            framing::read_frame(reader),
+        )
+        .await
+        {
+            Ok(frame) => match frame? {
+                Some((version, bytes)) => Handshake::decode(version, bytes.as_ref()),
+                None => Err(HubError::Handshake(
+                    "connection closed before handshake".to_string(),
+                )),
+            },
+            Err(_) => Err(HubError::Handshake("handshake timed out".to_string())),
+        }
+    }
+
+    /// Checks the token a plugin opened its session with against the tokens the supervisor
+    /// issued to its processes, and settles the node the session runs as.
+    async fn authenticate(&self, handshake: &Handshake) -> Result<Option<String>, String> {
+        let issued = supervisor::service()
+            .tokens(handshake.plugin_id.as_str())
+            .await;
+        check_token(&self.config, handshake, &issued)
+    }
+
+    /// Types a config by the options the plugin of a session declares, the way it would be
+    /// delivered to the plugin. Plugins that declare no options take no config, so there is
+    /// nothing to check.
+    pub(crate) fn check_config(
+        &self,
+        session: &Session,
+        config: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Vec<Field>, ConfigReport> {
+        if session.config_options.is_empty() {
+            return Ok(vec![]);
+        }
+        plugin_config::resolve(&session.config_options, config)
+    }
+
+    /// Pushes a new config to the plugin of a session and waits for it to accept or reject
+    /// it.
+    pub(crate) async fn reconfigure(
+        &self,
+        session: &Arc<Session>,
+        config: Vec<Field>,
+    ) -> Reconfiguration {
+        if !session.protocol_version.has_reconfigure() {
+            return Reconfiguration::Unsupported(format!(
+                "plugin speaks protocol version [{version}]",
+                version = session.protocol_version
+            ));
+        }
+        if session.config_options.is_empty() {
+            return Reconfiguration::Unsupported("plugin declares no config options".to_string());
+        }
+        let request_id = Ulid::new().to_string();
+        let answer = session.expect_reconfigured(request_id.as_str());
+        let request = Reconfigure {
+            request_id: request_id.clone(),
+            config,
+        };
+        if let Err(e) = session
+            .send(reconfigure(session.plugin_type, request))
+            .await
+        {
+            session.forget_reconfigured(request_id.as_str());
+            return Reconfiguration::Failed(e.to_string());
+        }
+        let timeout = Duration::from_secs(self.config.reconfigure_timeout_seconds);
+        let outcome = tokio::select! {
+            answer = tokio::time::timeout(timeout, answer) => match answer {
+                Ok(Ok(answer)) if answer.accepted => Reconfiguration::Applied,
+                Ok(Ok(answer)) => Reconfiguration::Rejected(answer.message),
+                Ok(Err(_)) => Reconfiguration::Failed("request was dropped".to_string()),
+                Err(_) => Reconfiguration::Failed(format!(
+                    "plugin did not answer within [{timeout}] seconds",
+                    timeout = self.config.reconfigure_timeout_seconds
+                )),
+            },
+            _ = session.closed() => {
+                Reconfiguration::Failed(HubError::SessionClosed(session.id.clone()).to_string())
+            }
+        };
+        session.forget_reconfigured(request_id.as_str());
+        outcome
+    }
+
+    /// Asks the plugin of a session to shut down.
+    pub(crate) async fn shut_down(&self, session: &Session) {
+        match session.send(shutdown(session.plugin_type)).await {
+            Ok(_) => log::debug!("Hub: shutting down session [{id}]", id = session.id),
+            Err(e) => log::debug!(
+                "Hub: failed to shut down session [{id}]: {e}",
+                id = session.id
+            ),
+        }
+    }
+
+    /// Pauses or resumes the plugin of a session: it gets no credit back while paused, and is
+    /// told so if its protocol version has pausing. Returns whether the plugin was told.
+    pub(crate) async fn pause(&self, session: &Arc<Session>, paused: bool) -> bool {
+        session.set_paused(paused);
+        if !session.protocol_version.has_pause() {
+            return false;
+        }
+        let msg = if paused {
+            pause(session.plugin_type)
+        } else {
+            resume(session.plugin_type)
+        };
+        match session.send(msg).await {
+            Ok(_) => {
+                log::debug!(
+                    "Hub: session [{id}] {action}",
+                    id = session.id,
+                    action = if paused { "paused" } else { "resumed" }
+                );
+                true
+            }
+            Err(e) => {
+                log::warn!(
+                    "Hub: failed to {action} session [{id}]: {e}",
+                    id = session.id,
+                    action = if paused { "pause" } else { "resume" }
+                );
+                false
+            }
+        }
+    }
+
+    /// Config delivered to the plugin: the config the supervisor launched it with, typed by
+    /// the options the plugin declares. Plugins that declare no options get none.
+    async fn configure(
+        &self,
+        handshake: &Handshake,
+        node_id: Option<&str>,
+    ) -> Result<Vec<Field>, ConfigReport> {
+        let config = supervisor::service()
+            .plugin_config(node_id.unwrap_or(handshake.plugin_id.as_str()))
+            .await
+            .unwrap_or_default();
+        if handshake.config_options.is_empty() {
+            if !config.is_empty() {
+                log::warn!(
+                    "Hub: plugin [{plugin_id}] declares no config options, its config is not delivered",
+                    plugin_id = handshake.plugin_id
+                );
+            }
+            return Ok(vec![]);
+        }
+        plugin_config::resolve(&handshake.config_options, &config)
+    }
+
+    async fn read_loop<R>(
+        &self,
+        session: &Arc<Session>,
+        reader: &mut R,
+        token: CancellationToken,
+    ) -> Result<(), HubError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        loop {
+            let frame = tokio::select! {
+                _ = token.cancelled() => {
+                    log::debug!("Hub: shutting down session [{id}]", id = session.id);
+                    session.send(shutdown(session.plugin_type)).await?;
+                    return Ok(());
+                }
+                _ = session.closed() => {
+                    return Err(HubError::Unresponsive(session.id.clone()));
+                }
+                frame = framing::read_frame(reader) => frame?,
+            };
+            let bytes = match frame {
+                // until the plugin reads the answer to its handshake, it may still send frames
+                // in the version it opened the session with
+                Some((version, _)) if version > session.protocol_version => {
+                    return Err(HubError::ProtocolVersion(session.id.clone(), version));
+                }
+                Some((_, bytes)) => bytes,
+                None => return Ok(()),
+            };
+            session.touch();
+            let msg = match PluginMessage::decode(session.plugin_type, bytes.as_ref()) {
+                Ok(Some(msg)) => msg,
+                Ok(None) => {
+                    log::warn!(
+                        "Hub: session [{id}]: message payload is missing",
+                        id = session.id
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Hub: session [{id}]: failed to decode message: {e}",
+                        id = session.id
+                    );
+                    continue;
+                }
+            };
+            match msg {
+                PluginMessage::Source(SourcePayload::Initialize(_))
+                | PluginMessage::Sink(SinkPayload::Initialize(_))
+                | PluginMessage::Transform(TransformPayload::Initialize(_)) => {
+                    log::warn!(
+                        "Hub: session [{id}]: repeated initialize message ignored",
+                        id = session.id
+                    );
+                }
+                PluginMessage::Source(SourcePayload::Pong(pong))
+                | PluginMessage::Sink(SinkPayload::Pong(pong))
+                | PluginMessage::Transform(TransformPayload::Pong(pong)) => {
+                    log::trace!(
+                        "Hub: session [{id}]: pong [{nonce}]",
+                        id = session.id,
+                        nonce = pong.nonce
+                    );
+                }
+                PluginMessage::Source(SourcePayload::Reconfigured(answer))
+                | PluginMessage::Sink(SinkPayload::Reconfigured(answer))
+                | PluginMessage::Transform(TransformPayload::Reconfigured(answer)) => {
+                    log::trace!(
+                        "Hub: session [{id}]: config [{request_id}] answered",
+                        id = session.id,
+                        request_id = answer.request_id
+                    );
+                    session.reconfigured(answer);
+                }
+                PluginMessage::Source(SourcePayload::Log(evt))
+                | PluginMessage::Sink(SinkPayload::Log(evt))
+                | PluginMessage::Transform(TransformPayload::Log(evt)) => {
+                    let level = log_plugin_event(session, &evt);
+                    activity::service()
+                        .plugin_activity(
+                            session,
+                            ActivityKind::PluginLog {
+                                level,
+                                message: evt.message,
+                            },
+                        )
+                        .await;
+                }
+                PluginMessage::Source(SourcePayload::Error(evt))
+                | PluginMessage::Sink(SinkPayload::Error(evt))
+                | PluginMessage::Transform(TransformPayload::Error(evt)) => {
+                    log_plugin_error(session, &evt);
+                    activity::service()
+                        .plugin_activity(
+                            session,
+                            ActivityKind::PluginLog {
+                                level: log::Level::Error,
+                                message: evt.message,
+                            },
+                        )
+                        .await;
+                }
+                PluginMessage::Source(SourcePayload::Exit(exit)) => {
+                    log::info!(
+                        "Hub: plugin [{plugin_id}] exited with [{code:?}]: {msg}",
+                        plugin_id = session.plugin_id,
+                        code = exit.code(),
+                        msg = exit.message,
+                    );
+                    return Ok(());
+                }
+                PluginMessage::Sink(SinkPayload::Exit(exit)) => {
+                    log::info!(
+                        "Hub: plugin [{plugin_id}] exited with [{code:?}]: {msg}",
+                        plugin_id = session.plugin_id,
+                        code = exit.code(),
+                        msg = exit.message,
+                    );
+                    return Ok(());
+                }
+                PluginMessage::Transform(TransformPayload::Exit(exit)) => {
+                    log::info!(
+                        "Hub: plugin [{plugin_id}] exited with [{code:?}]: {msg}",
+                        plugin_id = session.plugin_id,
+                        code = exit.code(),
+                        msg = exit.message,
+                    );
+                    return Ok(());
+                }
+                PluginMessage::Sink(SinkPayload::Credit(credit))
+                | PluginMessage::Transform(TransformPayload::Credit(credit)) => {
+                    log::trace!(
+                        "Hub: session [{id}]: received [{amount}] credit",
+                        id = session.id,
+                        amount = credit.amount
+                    );
+                    session.add_delivery_credits(credit);
+                }
+                PluginMessage::Sink(SinkPayload::Ack(ack))
+                | PluginMessage::Transform(TransformPayload::Ack(ack)) => {
+                    log::trace!(
+                        "Hub: session [{id}]: event [{event_id}] acked",
+                        id = session.id,
+                        event_id = ack.event_id
+                    );
+                    self.lineage.record(
+                        None,
+                        Hop::new(HopKind::Acked, ack.event_id.as_str(), &session.plugin_id),
+                    );
+                    session.settle(ack.event_id.as_str(), Settlement::Ack);
+                }
+                PluginMessage::Sink(SinkPayload::Nack(nack))
+                | PluginMessage::Transform(TransformPayload::Nack(nack)) => {
+                    log::debug!(
+                        "Hub: session [{id}]: event [{event_id}] nacked: {msg}",
+                        id = session.id,
+                        event_id = nack.event_id,
+                        msg = nack.message
+                    );
+                    self.lineage.record(
+                        None,
+                        Hop {
+                            message: Some(nack.message.clone()),
+                            ..Hop::new(HopKind::Nacked, nack.event_id.as_str(), &session.plugin_id)
+                        },
+                    );
+                    session.settle(nack.event_id.as_str(), Settlement::Nack(nack.message));
+                }
+                PluginMessage::Source(SourcePayload::Event(evt)) => {
+                    log::trace!("Hub: session [{id}]: received event", id = session.id);
+                    EVENTS_RECEIVED
+                        .with_label_values(&[session.plugin_id.as_str()])
+                        .inc();
+                    let event = self.trace(session, PluginEvent::Source(evt));
+                    self.publish(HubEvent::Event(session.clone(), event)).await;
+                }
+                PluginMessage::Transform(TransformPayload::Event(evt)) => {
+                    log::trace!("Hub: session [{id}]: received event", id = session.id);
+                    EVENTS_RECEIVED
+                        .with_label_values(&[session.plugin_id.as_str()])
+                        .inc();
+                    let event = self.trace(session, PluginEvent::Transform(evt));
+                    self.publish(HubEvent::Event(session.clone(), event)).await;
+                }
+            }
+        }
+    }
+
+    /// Assigns an event ID to an emitted event that carries none, starts a new trace if the
+    /// event is not part of one, and records the emission.
+    fn trace(&self, session: &Session, mut event: PluginEvent) -> PluginEvent {
+        let ids = event.ids_mut();
+        if ids.event_id.is_empty() {
+            *ids.event_id = Ulid::new().to_string();
+        }
+        if ids.trace_id.is_empty() {
+            *ids.trace_id = ids.event_id.clone();
+        }
+        self.lineage.record(
+            Some(ids.trace_id.as_str()),
+            Hop {
+                parent_ids: ids.parent_ids.to_vec(),
+                ..Hop::new(HopKind::Emitted, ids.event_id.as_str(), &session.plugin_id)
+            },
+        );
+        event
+    }
+
+    /// Pings the plugin of a session until the session closes. A plugin that sent nothing for
+    /// longer than the heartbeat timeout is marked unresponsive, its session is closed and the
+    /// supervisor restarts its process.
+    async fn heartbeat_loop(&self, session: Arc<Session>) {
+        let timeout = Duration::from_secs(self.config.heartbeat_timeout_seconds);
+        let mut ticker =
+            tokio::time::interval(Duration::from_secs(self.config.heartbeat_interval_seconds));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut nonce = 0;
+        loop {
+            tokio::select! {
+                _ = session.closed() => return,
+                _ = ticker.tick() => {}
+            }
+            if session.silence() > timeout {
+                break;
+            }
+            nonce += 1;
+            match session.try_send(ping(session.plugin_type, nonce)) {
+                Ok(true) => {}
+                // a plugin that doesn't drain its messages is caught by the timeout
+                Ok(false) => log::trace!(
+                    "Hub: session [{id}]: outbound queue full, ping skipped",
+                    id = session.id
+                ),
+                Err(_) => return,
+            }
+        }
+
+        session.mark_unresponsive();
+        UNRESPONSIVE_PLUGINS
+            .with_label_values(&[session.plugin_id.as_str()])
+            .inc();
+        log::warn!(
+            "Hub: plugin [{plugin_id}] sent nothing for more than [{timeout}s], closing session [{session_id}]",
+            plugin_id = session.plugin_id,
+            timeout = self.config.heartbeat_timeout_seconds,
+            session_id = session.id,
+        );
+        session.close();
+        if let Err(e) = supervisor::service()
+            .restart(session.process_id(), "plugin stopped answering heartbeats")
+            .await
+        {
+            log::warn!(
+                "Hub: failed to restart unresponsive plugin [{plugin_id}]: {e}",
+                plugin_id = session.plugin_id
+            );
+        }
+    }
+
+    async fn write_loop<W>(
+        session: Arc<Session>,
+        mut outbound: mpsc::Receiver<RuntimeMessage>,
+        mut writer: W,
+    ) where
+        W: AsyncWrite + Unpin,
+    {
+        loop {
+            let msg = tokio::select! {
+                msg = outbound.recv() => msg,
+                // flush whatever is still queued once the session is closed
+                _ = session.closed() => outbound.try_recv().ok(),
+            };
+            let msg = match msg {
+                Some(msg) => msg,
+                None => break,
+            };
+            let Some(bytes) = msg.encode(session.protocol_version) else {
+                continue;
+            };
+            if let Err(e) =
+                framing::write_frame(&mut writer, session.protocol_version, bytes.as_slice()).await
+            {
+                log::warn!(
+                    "Hub: session [{id}]: failed to write message: {e}",
+                    id = session.id
+                );
+                break;
+            }
+        }
+        log::trace!("Hub: session [{id}]: write loop exited", id = session.id);
+    }
+}
+
+#[async_trait]
+impl registry::Service for Service {
+    fn id(&self) -> String {
+        "hub-service".to_string()
+    }
+
+    fn register_metrics(&self, registry: &Registry) {
+        metrics::register(registry, HANDSHAKE_FAILURES.clone());
+        metrics::register(registry, SESSIONS.clone());
+        metrics::register(registry, EVENTS_RECEIVED.clone());
+        metrics::register(registry, UNRESPONSIVE_PLUGINS.clone());
+    }
+
+    async fn health(&self) -> Health {
+        if !self.listening.load(Ordering::Acquire) {
+            return Health::down("not listening");
+        }
+        Health::up().with_detail("sessions", self.sessions.list().await.len())
+    }
+
+    async fn start(&self, shutdown_token: CancellationToken) -> Result<(), RegistryError> {
+        if let Some(error) = &self.config_error {
+            return Err(RegistryError::ServiceError {
+                msg: "Hub config error".to_string(),
+                source: Box::new(HubError::Config(error.clone())),
+            });
+        }
+        let host = self.config.host.clone();
+        let port = self.config.port;
+        log::debug!("Starting plugin hub.");
+        let listener = match Listener::bind(host.as_str(), port, self.config.tls()).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                return Err(RegistryError::ServiceError {
+                    msg: "Net socket error".to_string(),
+                    source: Box::new(HubError::from(e)),
+                });
+            }
+        };
+        match &listener {
+            Listener::Tcp(_, acceptor) => log::info!(
+                "Plugin hub listening on host = [{host}], port = [{port}], tls = [{tls}]",
+                tls = acceptor.is_some()
+            ),
+            #[cfg(unix)]
+            Listener::Unix(..) => log::info!("Plugin hub listening on socket = [{host}]"),
+        }
+        self.listening.store(true, Ordering::Release);
+
+        let tracker = TaskTracker::new();
+        loop {
+            let (stream, peer_addr) = tokio::select! {
+                _ = shutdown_token.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        log::warn!("Hub: failed to accept connection: {e}");
+                        continue;
+                    }
+                },
+            };
+            let token = shutdown_token.child_token();
+            let tls = listener.acceptor();
+            let service = super::service();
+            tracker.spawn(async move {
+                if let Err(e) = service
+                    .handle_connection(stream, peer_addr, tls, token)
+                    .await
+                {
+                    log::warn!("Hub: plugin connection failed: {e}");
+                }
+            });
+        }
+
+        log::debug!("Plugin hub shutting down.");
+        self.listening.store(false, Ordering::Release);
+        drop(listener);
+        tracker.close();
+        tracker.wait().await;
+        log::debug!("Plugin hub exited");
+        Ok(())
+    }
+}
+
+fn initialize_response(plugin_type: PluginType, config: Vec<Field>) -> RuntimeMessage {
+    match plugin_type {
+        PluginType::Source => RuntimeMessage::Source(RuntimeSourceMessage {
+            payload: Some(RuntimeSourcePayload::Initialize(
+                source::InitializeResponse { config },
+            )),
+        }),
+        PluginType::Transform => RuntimeMessage::Transform(RuntimeTransformMessage {
+            payload: Some(RuntimeTransformPayload::Initialize(
+                transform::InitializeResponse { config },
+            )),
+        }),
+        _ => RuntimeMessage::Sink(RuntimeSinkMessage {
+            payload: Some(RuntimeSinkPayload::Initialize(sink::InitializeResponse {
+                config,
+            })),
+        }),
+    }
+}
+
+fn reconfigure(plugin_type: PluginType, request: Reconfigure) -> RuntimeMessage {
+    match plugin_type {
+        PluginType::Source => RuntimeMessage::Source(RuntimeSourceMessage {
+            payload: Some(RuntimeSourcePayload::Reconfigure(request)),
+        }),
+        PluginType::Transform => RuntimeMessage::Transform(RuntimeTransformMessage {
+            payload: Some(RuntimeTransformPayload::Reconfigure(request)),
+        }),
+        _ => RuntimeMessage::Sink(RuntimeSinkMessage {
+            payload: Some(RuntimeSinkPayload::Reconfigure(request)),
+        }),
+    }
+}
+
+fn pause(plugin_type: PluginType) -> RuntimeMessage {
+    match plugin_type {
+        PluginType::Source => RuntimeMessage::Source(RuntimeSourceMessage {
+            payload: Some(RuntimeSourcePayload::Pause(Pause {})),
+        }),
+        PluginType::Transform => RuntimeMessage::Transform(RuntimeTransformMessage {
+            payload: Some(RuntimeTransformPayload::Pause(Pause {})),
+        }),
+        _ => RuntimeMessage::Sink(RuntimeSinkMessage {
+            payload: Some(RuntimeSinkPayload::Pause(Pause {})),
+        }),
+    }
+}
+
+fn resume(plugin_type: PluginType) -> RuntimeMessage {
+    match plugin_type {
+        PluginType::Source => RuntimeMessage::Source(RuntimeSourceMessage {
+            payload: Some(RuntimeSourcePayload::Resume(Resume {})),
+        }),
+        PluginType::Transform => RuntimeMessage::Transform(RuntimeTransformMessage {
+            payload: Some(RuntimeTransformPayload::Resume(Resume {})),
+        }),
+        _ => RuntimeMessage::Sink(RuntimeSinkMessage {
+            payload: Some(RuntimeSinkPayload::Resume(Resume {})),
+        }),
+    }
+}
+
+fn shutdown(plugin_type: PluginType) -> RuntimeMessage {
+    match plugin_type {
+        PluginType::Source => RuntimeMessage::Source(RuntimeSourceMessage {
+            payload: Some(RuntimeSourcePayload::Shutdown(source::Shutdown {})),
+        }),
+        PluginType::Transform => RuntimeMessage::Transform(RuntimeTransformMessage {
+            payload: Some(RuntimeTransformPayload::Shutdown(transform::Shutdown {})),
+        }),
+        _ => RuntimeMessage::Sink(RuntimeSinkMessage {
+            payload: Some(RuntimeSinkPayload::Shutdown(sink::Shutdown {})),
+        }),
+    }
+}
+
+fn ping(plugin_type: PluginType, nonce: u64) -> RuntimeMessage {
+    let ping = Ping { nonce };
+    match plugin_type {
+        PluginType::Source => RuntimeMessage::Source(RuntimeSourceMessage {
+            payload: Some(RuntimeSourcePayload::Ping(ping)),
+        }),
+        PluginType::Transform => RuntimeMessage::Transform(RuntimeTransformMessage {
+            payload: Some(RuntimeTransformPayload::Ping(ping)),
+        }),
+        _ => RuntimeMessage::Sink(RuntimeSinkMessage {
+            payload: Some(RuntimeSinkPayload::Ping(ping)),
+        }),
+    }
+}
+
+/// Compares tokens in constant time, so the time taken doesn't tell how much of a guess was
+/// right.
+/// Checks the token of a handshake, and settles the node the session runs as. Valid are a
+/// token issued to a process of the plugin, which runs as the node it was launched for, and
+/// the configured shared token, whose plugins run as the node they say.
+fn check_token(
+    config: &HubConfig,
+    handshake: &Handshake,
+    issued: &[(Option<String>, String)],
+) -> Result<Option<String>, String> {
+    let claimed = Some(handshake.node_id.clone()).filter(|node_id| !node_id.is_empty());
+    let issued = issued
+        .iter()
+        .find(|(_, token)| tokens_match(token, handshake.auth_token.as_str()));
+    if let Some((node_id, _)) = issued {
+        return match claimed {
+            Some(claimed) if node_id.as_ref() != Some(&claimed) => {
+                Err(format!("plugin was not launched as node [{claimed}]"))
+            }
+            _ => Ok(node_id.clone()),
+        };
+    }
+    if config.allow_unauthenticated {
+        return Ok(claimed);
+    }
+    if handshake.auth_token.is_empty() {
+        return Err("auth token is missing".to_string());
+    }
+    if config
+        .shared_token
+        .iter()
+        .any(|token| tokens_match(token, handshake.auth_token.as_str()))
+    {
+        Ok(claimed)
+    } else {
+        Err("auth token doesn't match".to_string())
+    }
+}
+
+fn tokens_match(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len()
+        && expected
+            .bytes()
+            .zip(actual.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Logs a line the plugin logged, at its level, which is returned.
+fn log_plugin_event(session: &Session, evt: &LogEvent) -> log::Level {
+    let level = match evt.log_level() {
+        LogLevel::Trace => log::Level::Trace,
+        LogLevel::Debug => log::Level::Debug,
+        LogLevel::Info => log::Level::Info,
+        LogLevel::Warn | LogLevel::Undefined => log::Level::Warn,
+        LogLevel::Error => log::Level::Error,
+    };
+    log::log!(
+        level,
+        "Plugin [{plugin_id}]: {msg}",
+        plugin_id = session.plugin_id,
+        msg = evt.message
+    );
+    level
+}
+
+fn log_plugin_error(session: &Session, evt: &ErrorEvent) {
+    log::error!(
+        "Plugin [{plugin_id}] reported an error: {msg}",
+        plugin_id = session.plugin_id,
+        msg = evt.message
+    );
+}
+
+fn log_rejected_session(evt: ErrorEvent) {
+    log::error!(
+        "Hub: plugin [{plugin_id}]: {msg}",
+        plugin_id = evt.plugin_id,
+        msg = evt.message
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flwrs_plugin::plugin::framing::PROTOCOL_VERSION_1_0;
+
+    fn handshake(auth_token: &str, node_id: &str) -> Handshake {
+        Handshake {
+            plugin_id: "plugin".to_string(),
+            plugin_version: "0.0.1".to_string(),
+            plugin_type: PluginType::Sink,
+            in_schema: None,
+            out_schema: None,
+            auth_token: auth_token.to_string(),
+            node_id: node_id.to_string(),
+            config_options: vec![],
+            protocol_version: PROTOCOL_VERSION_1_0,
+        }
+    }
+
+    fn shared(token: &str) -> HubConfig {
+        HubConfig {
+            shared_token: Some(token.to_string()),
+            ..HubConfig::defaults()
+        }
+    }
+
+    fn issued(node_id: &str, token: &str) -> Vec<(Option<String>, String)> {
+        vec![(Some(node_id.to_string()), token.to_string())]
+    }
+
+    #[test]
+    fn rejects_missing_token() {
+        let result = check_token(&shared("secret"), &handshake("", ""), &[]);
+        assert_eq!(result, Err("auth token is missing".to_string()));
+    }
+
+    #[test]
+    fn rejects_wrong_token() {
+        let result = check_token(&shared("secret"), &handshake("guess", ""), &[]);
+        assert_eq!(result, Err("auth token doesn't match".to_string()));
+        let result = check_token(&HubConfig::defaults(), &handshake("guess", ""), &[]);
+        assert_eq!(result, Err("auth token doesn't match".to_string()));
+    }
+
+    #[test]
+    fn shared_token_runs_as_claimed_node() {
+        let result = check_token(&shared("secret"), &handshake("secret", "node"), &[]);
+        assert_eq!(result, Ok(Some("node".to_string())));
+    }
+
+    #[test]
+    fn issued_token_runs_as_launched_node() {
+        let result = check_token(
+            &HubConfig::defaults(),
+            &handshake("issued", ""),
+            &issued("node", "issued"),
+        );
+        assert_eq!(result, Ok(Some("node".to_string())));
+    }
+
+    #[test]
+    fn rejects_issued_token_claiming_another_node() {
+        let result = check_token(
+            &HubConfig::defaults(),
+            &handshake("issued", "other"),
+            &issued("node", "issued"),
+        );
+        assert_eq!(
+            result,
+            Err("plugin was not launched as node [other]".to_string())
+        );
+    }
+
+    #[test]
+    fn allows_unauthenticated_when_configured() {
+        let config = HubConfig {
+            allow_unauthenticated: true,
+            ..HubConfig::defaults()
+        };
+        let result = check_token(&config, &handshake("", "node"), &[]);
+        assert_eq!(result, Ok(Some("node".to_string())));
+    }
+}
@@ -0,0 +1,693 @@
+use crate::modules::hub::service::HubError;
+use chrono::{DateTime, Local};
+use flwrs_plugin::plugin::framing::{ProtocolVersion, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use flwrs_plugin::schema::common::plugin_type::Enum as PluginType;
+use flwrs_plugin::schema::common::{Credit, Reconfigured};
+use flwrs_plugin::schema::schema::{ConfigOption, PluginPayload, SchemaDefinition};
+use flwrs_plugin::schema::sink::{
+    runtime_sink_message::Payload as RuntimeSinkPayload, sink_message::Payload as SinkPayload,
+    RuntimeSinkMessage, SinkMessage,
+};
+use flwrs_plugin::schema::source::{
+    runtime_source_message::Payload as RuntimeSourcePayload,
+    source_message::Payload as SourcePayload, RuntimeSourceMessage, SourceEvent, SourceMessage,
+};
+use flwrs_plugin::schema::transform::{
+    runtime_transform_message::Payload as RuntimeTransformPayload,
+    transform_message::Payload as TransformPayload, RuntimeTransformMessage, TransformEvent,
+    TransformMessage,
+};
+use prost::Message;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot, RwLock, Semaphore};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// Message sent by the runtime to a plugin, typed by the plugin protocol.
+pub(crate) enum RuntimeMessage {
+    Source(RuntimeSourceMessage),
+    Sink(RuntimeSinkMessage),
+    Transform(RuntimeTransformMessage),
+}
+
+impl RuntimeMessage {
+    /// Encodes the message for a plugin speaking the given protocol version. Returns `None`
+    /// for messages the version doesn't have, which the plugin would not understand.
+    pub(crate) fn encode(&self, version: ProtocolVersion) -> Option<Vec<u8>> {
+        if self.is_ping() && !version.has_heartbeats() {
+            return None;
+        }
+        if self.is_pause() && !version.has_pause() {
+            return None;
+        }
+        if self.is_credit() && !version.has_credits() {
+            return None;
+        }
+        Some(match self {
+            RuntimeMessage::Source(msg) => msg.encode_to_vec(),
+            RuntimeMessage::Sink(msg) => msg.encode_to_vec(),
+            RuntimeMessage::Transform(msg) => msg.encode_to_vec(),
+        })
+    }
+
+    fn is_ping(&self) -> bool {
+        matches!(
+            self,
+            RuntimeMessage::Source(RuntimeSourceMessage {
+                payload: Some(RuntimeSourcePayload::Ping(_))
+            }) | RuntimeMessage::Sink(RuntimeSinkMessage {
+                payload: Some(RuntimeSinkPayload::Ping(_))
+            }) | RuntimeMessage::Transform(RuntimeTransformMessage {
+                payload: Some(RuntimeTransformPayload::Ping(_))
+            })
+        )
+    }
+
+    fn is_credit(&self) -> bool {
+        matches!(
+            self,
+            RuntimeMessage::Source(RuntimeSourceMessage {
+                payload: Some(RuntimeSourcePayload::Credit(_))
+            }) | RuntimeMessage::Transform(RuntimeTransformMessage {
+                payload: Some(RuntimeTransformPayload::Credit(_))
+            })
+        )
+    }
+
+    fn is_pause(&self) -> bool {
+        matches!(
+            self,
+            RuntimeMessage::Source(RuntimeSourceMessage {
+                payload: Some(RuntimeSourcePayload::Pause(_) | RuntimeSourcePayload::Resume(_))
+            }) | RuntimeMessage::Sink(RuntimeSinkMessage {
+                payload: Some(RuntimeSinkPayload::Pause(_) | RuntimeSinkPayload::Resume(_))
+            }) | RuntimeMessage::Transform(RuntimeTransformMessage {
+                payload: Some(
+                    RuntimeTransformPayload::Pause(_) | RuntimeTransformPayload::Resume(_)
+                )
+            })
+        )
+    }
+}
+
+/// Message sent by a plugin to the runtime, typed by the plugin protocol.
+pub(crate) enum PluginMessage {
+    Source(SourcePayload),
+    Sink(SinkPayload),
+    Transform(TransformPayload),
+}
+
+impl PluginMessage {
+    pub(crate) fn decode(plugin_type: PluginType, bytes: &[u8]) -> Result<Option<Self>, HubError> {
+        let msg = match plugin_type {
+            PluginType::Source => SourceMessage::decode(bytes)?
+                .payload
+                .map(PluginMessage::Source),
+            PluginType::Sink => SinkMessage::decode(bytes)?.payload.map(PluginMessage::Sink),
+            PluginType::Transform => TransformMessage::decode(bytes)?
+                .payload
+                .map(PluginMessage::Transform),
+            PluginType::Undefined => {
+                return Err(HubError::Handshake("undefined plugin type".to_string()));
+            }
+        };
+        Ok(msg)
+    }
+}
+
+/// Event emitted by a plugin, as it arrived on the wire.
+pub(crate) enum PluginEvent {
+    Source(SourceEvent),
+    Transform(TransformEvent),
+}
+
+/// Lineage IDs of an emitted event.
+pub(crate) struct EventIds<'a> {
+    pub event_id: &'a mut String,
+    pub trace_id: &'a mut String,
+    /// Source events have no parents.
+    pub parent_ids: &'a [String],
+}
+
+impl PluginEvent {
+    pub(crate) fn into_payload(self) -> Option<PluginPayload> {
+        match self {
+            PluginEvent::Source(evt) => evt.payload,
+            PluginEvent::Transform(evt) => evt.payload,
+        }
+    }
+
+    pub(crate) fn ids_mut(&mut self) -> EventIds<'_> {
+        match self {
+            PluginEvent::Source(evt) => EventIds {
+                event_id: &mut evt.event_id,
+                trace_id: &mut evt.trace_id,
+                parent_ids: &[],
+            },
+            PluginEvent::Transform(evt) => EventIds {
+                event_id: &mut evt.event_id,
+                trace_id: &mut evt.trace_id,
+                parent_ids: evt.parent_ids.as_slice(),
+            },
+        }
+    }
+
+    pub(crate) fn event_id(&self) -> &str {
+        match self {
+            PluginEvent::Source(evt) => evt.event_id.as_str(),
+            PluginEvent::Transform(evt) => evt.event_id.as_str(),
+        }
+    }
+
+    pub(crate) fn trace_id(&self) -> &str {
+        match self {
+            PluginEvent::Source(evt) => evt.trace_id.as_str(),
+            PluginEvent::Transform(evt) => evt.trace_id.as_str(),
+        }
+    }
+}
+
+/// How a plugin settled an event delivered to it.
+pub(crate) enum Settlement {
+    Ack,
+    Nack(String),
+}
+
+/// What became of a config pushed to a running plugin.
+pub(crate) enum Reconfiguration {
+    /// The plugin runs with the new config.
+    Applied,
+    /// The plugin keeps the config it has.
+    Rejected(String),
+    /// The plugin can't take a new config while it runs.
+    Unsupported(String),
+    /// The plugin didn't answer, it may or may not run with the new config.
+    Failed(String),
+}
+
+/// Something that happened on the hub that other services may react to.
+pub(crate) enum HubEvent {
+    Connected(Arc<Session>),
+    Disconnected(Arc<Session>),
+    Event(Arc<Session>, PluginEvent),
+}
+
+/// Contents of the `Initialize` message a plugin opens its session with.
+pub(crate) struct Handshake {
+    pub plugin_id: String,
+    pub plugin_version: String,
+    pub plugin_type: PluginType,
+    pub in_schema: Option<SchemaDefinition>,
+    pub out_schema: Option<SchemaDefinition>,
+    pub auth_token: String,
+    /// Node the plugin says it runs as, empty if it doesn't say.
+    pub node_id: String,
+    /// Settings the plugin accepts.
+    pub config_options: Vec<ConfigOption>,
+    /// Protocol version agreed on for the session.
+    pub protocol_version: ProtocolVersion,
+}
+
+impl Handshake {
+    /// Decodes the first message of a session, sent in the given protocol version. Every
+    /// plugin protocol carries `Initialize` and its `plugin_type` under the same tags, so the
+    /// sink envelope is used to find out which protocol the plugin speaks before decoding the
+    /// message for real.
+    pub(crate) fn decode(version: ProtocolVersion, bytes: &[u8]) -> Result<Self, HubError> {
+        let plugin_type = match SinkMessage::decode(bytes)?.payload {
+            Some(SinkPayload::Initialize(init)) => init.plugin_type(),
+            _ => {
+                return Err(HubError::Handshake(
+                    "first message is not an initialize message".to_string(),
+                ));
+            }
+        };
+        let (handshake, versions) = match PluginMessage::decode(plugin_type, bytes)? {
+            Some(PluginMessage::Source(SourcePayload::Initialize(init))) => (
+                Self {
+                    plugin_id: init.plugin_id,
+                    plugin_version: init.plugin_version,
+                    plugin_type,
+                    in_schema: None,
+                    out_schema: init.schema,
+                    auth_token: init.auth_token,
+                    node_id: init.node_id,
+                    config_options: init.config_options,
+                    protocol_version: version,
+                },
+                (init.min_protocol_version, init.max_protocol_version),
+            ),
+            Some(PluginMessage::Sink(SinkPayload::Initialize(init))) => (
+                Self {
+                    plugin_id: init.plugin_id,
+                    plugin_version: init.plugin_version,
+                    plugin_type,
+                    in_schema: init.schema,
+                    out_schema: None,
+                    auth_token: init.auth_token,
+                    node_id: init.node_id,
+                    config_options: init.config_options,
+                    protocol_version: version,
+                },
+                (init.min_protocol_version, init.max_protocol_version),
+            ),
+            Some(PluginMessage::Transform(TransformPayload::Initialize(init))) => (
+                Self {
+                    plugin_id: init.plugin_id,
+                    plugin_version: init.plugin_version,
+                    plugin_type,
+                    in_schema: init.in_schema,
+                    out_schema: init.out_schema,
+                    auth_token: init.auth_token,
+                    node_id: init.node_id,
+                    config_options: init.config_options,
+                    protocol_version: version,
+                },
+                (init.min_protocol_version, init.max_protocol_version),
+            ),
+            _ => {
+                return Err(HubError::Handshake(
+                    "first message is not an initialize message".to_string(),
+                ));
+            }
+        };
+        if handshake.plugin_id.is_empty() {
+            return Err(HubError::Handshake("plugin ID is missing".to_string()));
+        }
+        // plugins that predate version negotiation speak only the version they sent
+        let (min, max) = match versions {
+            (min, max) if min.is_empty() && max.is_empty() => (version, version),
+            (min, max) => match (ProtocolVersion::parse(&min), ProtocolVersion::parse(&max)) {
+                (Some(min), Some(max)) => (min, max),
+                _ => {
+                    return Err(HubError::Handshake(format!(
+                        "invalid protocol versions [{min}]..[{max}]"
+                    )));
+                }
+            },
+        };
+        let protocol_version = ProtocolVersion::negotiate(min, max).ok_or_else(|| {
+            HubError::Handshake(format!(
+                "no common protocol version, plugin speaks [{min}]..[{max}], runtime speaks [{MIN_PROTOCOL_VERSION}]..[{PROTOCOL_VERSION}]"
+            ))
+        })?;
+        Ok(Self {
+            protocol_version,
+            ..handshake
+        })
+    }
+}
+
+/// A plugin connected to the hub that completed its handshake.
+pub(crate) struct Session {
+    pub id: String,
+    pub plugin_id: String,
+    /// Scene node the plugin runs as. Plugins that don't run as a particular node stand for
+    /// every node of their plugin.
+    pub node_id: Option<String>,
+    pub plugin_version: String,
+    pub plugin_type: PluginType,
+    pub protocol_version: ProtocolVersion,
+    pub in_schema: Option<SchemaDefinition>,
+    pub out_schema: Option<SchemaDefinition>,
+    /// Settings the plugin accepts.
+    pub config_options: Vec<ConfigOption>,
+    pub peer_addr: String,
+    pub connect_time: DateTime<Local>,
+    /// When the plugin last sent a message of any kind. Monotonic, so a clock change neither
+    /// hides a hung plugin nor makes a healthy one look silent.
+    last_seen: Mutex<Instant>,
+    /// Cleared once the plugin stopped answering heartbeats.
+    responsive: AtomicBool,
+    outbound: mpsc::Sender<RuntimeMessage>,
+    closed: CancellationToken,
+    /// Credit the plugin granted for events delivered to it.
+    delivery_credits: Semaphore,
+    /// Events the plugin emitted that the runtime is done with, not yet granted back.
+    returned_credits: AtomicU32,
+    /// Set while the plugin is paused, which keeps returned credit from being granted back.
+    paused: AtomicBool,
+    /// Deliveries waiting for the plugin to ack or nack them, by event ID.
+    unsettled: Mutex<HashMap<String, oneshot::Sender<Settlement>>>,
+    /// Configs pushed to the plugin that it did not accept or reject yet, by request ID.
+    reconfiguring: Mutex<HashMap<String, oneshot::Sender<Reconfigured>>>,
+}
+
+impl Session {
+    pub(crate) fn new(
+        id: String,
+        handshake: Handshake,
+        node_id: Option<String>,
+        peer_addr: String,
+        outbound: mpsc::Sender<RuntimeMessage>,
+    ) -> Self {
+        Self {
+            id,
+            plugin_id: handshake.plugin_id,
+            node_id,
+            plugin_version: handshake.plugin_version,
+            plugin_type: handshake.plugin_type,
+            protocol_version: handshake.protocol_version,
+            in_schema: handshake.in_schema,
+            out_schema: handshake.out_schema,
+            config_options: handshake.config_options,
+            peer_addr,
+            connect_time: Local::now(),
+            last_seen: Mutex::new(Instant::now()),
+            responsive: AtomicBool::new(true),
+            outbound,
+            closed: CancellationToken::new(),
+            delivery_credits: Semaphore::new(0),
+            returned_credits: AtomicU32::new(0),
+            paused: AtomicBool::new(false),
+            unsettled: Mutex::new(HashMap::new()),
+            reconfiguring: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// ID of the supervised process the plugin runs in, if the supervisor launched it.
+    pub(crate) fn process_id(&self) -> &str {
+        self.node_id.as_deref().unwrap_or(self.plugin_id.as_str())
+    }
+
+    /// Whether the plugin runs as the node.
+    pub(crate) fn runs_as(&self, node_id: &str, plugin_id: &str) -> bool {
+        match &self.node_id {
+            Some(id) => id == node_id,
+            None => self.plugin_id == plugin_id,
+        }
+    }
+
+    /// Queues a message to be written to the plugin.
+    pub(crate) async fn send(&self, msg: RuntimeMessage) -> Result<(), HubError> {
+        self.outbound
+            .send(msg)
+            .await
+            .map_err(|_| HubError::SessionClosed(self.id.clone()))
+    }
+
+    /// Queues a message unless the outbound queue is full. Returns whether it was queued.
+    pub(crate) fn try_send(&self, msg: RuntimeMessage) -> Result<bool, HubError> {
+        match self.outbound.try_send(msg) {
+            Ok(()) => Ok(true),
+            Err(TrySendError::Full(_)) => Ok(false),
+            Err(TrySendError::Closed(_)) => Err(HubError::SessionClosed(self.id.clone())),
+        }
+    }
+
+    /// Records that the plugin just sent a message.
+    pub(crate) fn touch(&self) {
+        *self.last_seen.lock().expect("last seen lock poisoned") = Instant::now();
+    }
+
+    /// How long the plugin has sent nothing.
+    pub(crate) fn silence(&self) -> Duration {
+        self.last_seen
+            .lock()
+            .expect("last seen lock poisoned")
+            .elapsed()
+    }
+
+    /// When the plugin last sent a message, on the wall clock.
+    pub(crate) fn last_seen(&self) -> DateTime<Local> {
+        Local::now() - chrono::Duration::from_std(self.silence()).unwrap_or_default()
+    }
+
+    pub(crate) fn is_responsive(&self) -> bool {
+        self.responsive.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn mark_unresponsive(&self) {
+        self.responsive.store(false, Ordering::Release);
+    }
+
+    /// Records credit granted by the plugin for deliveries.
+    pub(crate) fn add_delivery_credits(&self, credit: Credit) {
+        self.delivery_credits.add_permits(credit.amount as usize);
+    }
+
+    /// Waits until the plugin accepts one more event and takes that credit. Returns `false`
+    /// if the session closed first. Plugins speaking a protocol version without credit take
+    /// events as they come.
+    pub(crate) async fn acquire_delivery_credit(&self) -> bool {
+        if !self.protocol_version.has_credits() {
+            return !self.closed.is_cancelled();
+        }
+        tokio::select! {
+            permit = self.delivery_credits.acquire() => match permit {
+                Ok(permit) => {
+                    permit.forget();
+                    true
+                }
+                Err(_) => false,
+            },
+            _ = self.closed() => false,
+        }
+    }
+
+    /// Lets the plugin emit `amount` more events.
+    pub(crate) async fn grant_credits(&self, amount: u32) -> Result<(), HubError> {
+        let credit = Credit { amount };
+        let msg = match self.plugin_type {
+            PluginType::Transform => RuntimeMessage::Transform(RuntimeTransformMessage {
+                payload: Some(RuntimeTransformPayload::Credit(credit)),
+            }),
+            _ => RuntimeMessage::Source(RuntimeSourceMessage {
+                payload: Some(RuntimeSourcePayload::Credit(credit)),
+            }),
+        };
+        self.send(msg).await
+    }
+
+    /// Records that the runtime is done with one event the plugin emitted. Once `batch`
+    /// events are done, their credit is granted back to the plugin.
+    pub(crate) fn return_credit(self: &Arc<Self>, batch: u32) {
+        let returned = self.returned_credits.fetch_add(1, Ordering::AcqRel) + 1;
+        if returned < batch || self.is_paused() {
+            return;
+        }
+        self.grant_returned_credits();
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    /// Pauses or resumes the plugin on the runtime side. A paused plugin is granted no
+    /// credit back, so it runs out of credit and stops emitting even if it doesn't know
+    /// pausing; the credit returned meanwhile is granted once it is resumed.
+    pub(crate) fn set_paused(self: &Arc<Self>, paused: bool) {
+        self.paused.store(paused, Ordering::Release);
+        if !paused {
+            self.grant_returned_credits();
+        }
+    }
+
+    fn grant_returned_credits(self: &Arc<Self>) {
+        let amount = self.returned_credits.swap(0, Ordering::AcqRel);
+        if amount == 0 {
+            // another caller took this batch
+            return;
+        }
+        let session = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = session.grant_credits(amount).await {
+                log::trace!(
+                    "Hub: session [{id}]: failed to grant credit: {e}",
+                    id = session.id
+                );
+            }
+        });
+    }
+
+    /// Registers an event about to be delivered. The receiver resolves once the plugin acks
+    /// or nacks it, and errors if the session drops the delivery.
+    pub(crate) fn expect_settlement(&self, event_id: &str) -> oneshot::Receiver<Settlement> {
+        let (settled, settled_rx) = oneshot::channel();
+        self.unsettled
+            .lock()
+            .expect("unsettled lock poisoned")
+            .insert(event_id.to_string(), settled);
+        settled_rx
+    }
+
+    /// Passes the plugin's ack or nack on to whoever delivered the event.
+    pub(crate) fn settle(&self, event_id: &str, settlement: Settlement) {
+        let settled = self
+            .unsettled
+            .lock()
+            .expect("unsettled lock poisoned")
+            .remove(event_id);
+        match settled {
+            Some(settled) => {
+                let _ = settled.send(settlement);
+            }
+            None => log::trace!(
+                "Hub: session [{id}]: event [{event_id}] is not awaiting settlement",
+                id = self.id
+            ),
+        }
+    }
+
+    /// Gives up on a delivery, e.g. after it timed out. A late ack is then ignored.
+    pub(crate) fn forget(&self, event_id: &str) {
+        self.unsettled
+            .lock()
+            .expect("unsettled lock poisoned")
+            .remove(event_id);
+    }
+
+    /// Registers a config about to be pushed to the plugin. The receiver resolves once the
+    /// plugin accepts or rejects it.
+    pub(crate) fn expect_reconfigured(&self, request_id: &str) -> oneshot::Receiver<Reconfigured> {
+        let (reconfigured, reconfigured_rx) = oneshot::channel();
+        self.reconfiguring
+            .lock()
+            .expect("reconfiguring lock poisoned")
+            .insert(request_id.to_string(), reconfigured);
+        reconfigured_rx
+    }
+
+    /// Passes the plugin's answer to a pushed config on to whoever pushed it.
+    pub(crate) fn reconfigured(&self, answer: Reconfigured) {
+        let reconfigured = self
+            .reconfiguring
+            .lock()
+            .expect("reconfiguring lock poisoned")
+            .remove(answer.request_id.as_str());
+        match reconfigured {
+            Some(reconfigured) => {
+                let _ = reconfigured.send(answer);
+            }
+            None => log::trace!(
+                "Hub: session [{id}]: config [{request_id}] is not awaiting an answer",
+                id = self.id,
+                request_id = answer.request_id
+            ),
+        }
+    }
+
+    /// Gives up on a pushed config, e.g. after the plugin took too long to answer.
+    pub(crate) fn forget_reconfigured(&self, request_id: &str) {
+        self.reconfiguring
+            .lock()
+            .expect("reconfiguring lock poisoned")
+            .remove(request_id);
+    }
+
+    pub(crate) fn close(&self) {
+        self.closed.cancel();
+    }
+
+    pub(crate) async fn closed(&self) {
+        self.closed.cancelled().await
+    }
+}
+
+/// Live plugin sessions, indexed by session ID.
+#[derive(Default)]
+pub(crate) struct SessionSet {
+    sessions: RwLock<HashMap<String, Arc<Session>>>,
+}
+
+impl SessionSet {
+    pub(crate) async fn insert(&self, session: Arc<Session>) {
+        self.sessions
+            .write()
+            .await
+            .insert(session.id.clone(), session);
+    }
+
+    pub(crate) async fn remove(&self, id: &str) -> Option<Arc<Session>> {
+        self.sessions.write().await.remove(id)
+    }
+
+    pub(crate) async fn get(&self, id: &str) -> Option<Arc<Session>> {
+        self.sessions.read().await.get(id).cloned()
+    }
+
+    /// Session of the plugin running as the node. A plugin that runs as no particular node is
+    /// only picked if none runs as this one.
+    pub(crate) async fn find_by_node(
+        &self,
+        node_id: &str,
+        plugin_id: &str,
+    ) -> Option<Arc<Session>> {
+        let sessions = self.sessions.read().await;
+        sessions
+            .values()
+            .find(|session| session.node_id.as_deref() == Some(node_id))
+            .or_else(|| {
+                sessions
+                    .values()
+                    .find(|session| session.node_id.is_none() && session.plugin_id == plugin_id)
+            })
+            .cloned()
+    }
+
+    /// Session of the plugin running in the supervised process.
+    pub(crate) async fn find_by_process_id(&self, process_id: &str) -> Option<Arc<Session>> {
+        self.sessions
+            .read()
+            .await
+            .values()
+            .find(|session| session.process_id() == process_id)
+            .cloned()
+    }
+
+    /// Any session of the plugin, whatever node it runs as.
+    pub(crate) async fn find_by_plugin_id(&self, plugin_id: &str) -> Option<Arc<Session>> {
+        self.sessions
+            .read()
+            .await
+            .values()
+            .find(|session| session.plugin_id == plugin_id)
+            .cloned()
+    }
+
+    pub(crate) async fn list(&self) -> Vec<Arc<Session>> {
+        self.sessions.read().await.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        let (outbound, _) = mpsc::channel(1);
+        let handshake = Handshake {
+            plugin_id: "plugin".to_string(),
+            plugin_version: "0.0.1".to_string(),
+            plugin_type: PluginType::Sink,
+            in_schema: None,
+            out_schema: None,
+            auth_token: String::new(),
+            node_id: String::new(),
+            config_options: vec![],
+            protocol_version: PROTOCOL_VERSION,
+        };
+        Session::new(
+            "session".to_string(),
+            handshake,
+            None,
+            "peer".to_string(),
+            outbound,
+        )
+    }
+
+    #[tokio::test]
+    async fn silence_counts_from_the_last_message() {
+        let session = session();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(session.silence() >= Duration::from_millis(50));
+        assert!(session.last_seen() <= Local::now() - chrono::Duration::milliseconds(50));
+
+        session.touch();
+        assert!(session.silence() < Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,53 @@
+use crate::modules::hub::service::HubTlsConfig;
+use rustls::crypto::ring;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use std::io;
+use std::sync::Arc;
+use tokio_rustls::TlsAcceptor;
+
+/// Builds the acceptor that secures plugin connections. Plugins must present a client
+/// certificate signed by one of the configured CAs.
+pub(crate) fn acceptor(config: &HubTlsConfig) -> io::Result<TlsAcceptor> {
+    let provider = Arc::new(ring::default_provider());
+    let verifier = WebPkiClientVerifier::builder_with_provider(
+        Arc::new(roots(&config.ca_path)?),
+        provider.clone(),
+    )
+    .build()
+    .map_err(io::Error::other)?;
+    let server_config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(
+            certs(&config.cert_path)?,
+            PrivateKeyDer::from_pem_file(&config.key_path)
+                .map_err(|e| pem_error(&config.key_path, e))?,
+        )
+        .map_err(io::Error::other)?;
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+fn roots(path: &str) -> io::Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in certs(path)? {
+        roots.add(cert).map_err(io::Error::other)?;
+    }
+    Ok(roots)
+}
+
+fn certs(path: &str) -> io::Result<Vec<CertificateDer<'static>>> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect())
+        .map_err(|e| pem_error(path, e))
+}
+
+fn pem_error(path: &str, e: rustls::pki_types::pem::Error) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("failed to read [{path}]: {e}"),
+    )
+}
@@ -0,0 +1,18 @@
+use crate::db::queue_db;
+use crate::modules::queue::service::{QueueConfig, Service};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+pub(crate) mod api;
+pub(crate) mod service;
+
+static SERVICE: OnceCell<Arc<Service>> = OnceCell::const_new();
+
+pub(crate) async fn service() -> &'static Service {
+    SERVICE
+        .get_or_init(|| async {
+            let db = queue_db().await;
+            Arc::new(Service::new(db, QueueConfig::read()))
+        })
+        .await
+}
@@ -0,0 +1,399 @@
+use crate::modules::director::service::DirectorError;
+use crate::modules::queue::service::{Selection, ServiceError};
+use crate::modules::{director, queue};
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use flwrs_plugin::schema::schema::field_value::Value;
+use flwrs_plugin::schema::schema::{FieldValue, PluginPayload};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct DeadLetter {
+    pub id: String,
+    pub scene_id: String,
+    pub edge_id: String,
+    pub target_plugin_id: String,
+    /// Event payload as a JSON object of its fields. Bytes are hex encoded, date-times
+    /// RFC 3339 strings.
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+    pub attempts: u32,
+    /// Why the last delivery attempt failed.
+    pub error: String,
+    /// Event the plugin emitted, which this one is a copy of.
+    pub parent_id: String,
+    pub trace_id: String,
+    /// When the event was first routed.
+    pub create_time: i64,
+    pub dead_time: i64,
+}
+
+impl From<queue::service::DeadLetter> for DeadLetter {
+    fn from(value: queue::service::DeadLetter) -> Self {
+        Self {
+            payload: payload_json(value.payload.as_slice()),
+            id: value.id,
+            scene_id: value.scene_id,
+            edge_id: value.edge_id,
+            target_plugin_id: value.target_plugin_id,
+            attempts: value.attempts,
+            error: value.error,
+            parent_id: value.parent_id,
+            trace_id: value.trace_id,
+            create_time: value.create_time.timestamp_millis(),
+            dead_time: value.dead_time.timestamp_millis(),
+        }
+    }
+}
+
+fn payload_json(bytes: &[u8]) -> serde_json::Value {
+    match PluginPayload::decode(bytes) {
+        Ok(payload) => fields_json(&payload),
+        Err(e) => serde_json::Value::String(format!("undecodable payload: {e}")),
+    }
+}
+
+/// The payload as a JSON object of its fields.
+pub(crate) fn fields_json(payload: &PluginPayload) -> serde_json::Value {
+    serde_json::Value::Object(
+        payload
+            .fields
+            .iter()
+            .map(|field| (field.key.clone(), field_json(field.value.as_ref())))
+            .collect(),
+    )
+}
+
+fn field_json(value: Option<&FieldValue>) -> serde_json::Value {
+    let Some(value) = value.and_then(|value| value.value.as_ref()) else {
+        return serde_json::Value::Null;
+    };
+    match value {
+        Value::String(s) => serde_json::Value::from(s.as_str()),
+        Value::I32(i) => serde_json::Value::from(*i),
+        Value::U32(u) => serde_json::Value::from(*u),
+        Value::F32(f) => serde_json::Value::from(*f),
+        Value::Bool(b) => serde_json::Value::from(*b),
+        Value::DateTime(ts) => chrono::DateTime::from_timestamp(ts.seconds, ts.nanos as u32)
+            .map(|time| serde_json::Value::from(time.to_rfc3339()))
+            .unwrap_or(serde_json::Value::Null),
+        Value::Bytes(bytes) => serde_json::Value::from(
+            bytes
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>(),
+        ),
+        Value::Array(array) => serde_json::Value::Array(
+            array
+                .value
+                .iter()
+                .map(|item| field_json(Some(item)))
+                .collect(),
+        ),
+        Value::Map(map) => serde_json::Value::Object(
+            map.value
+                .iter()
+                .map(|(key, item)| (key.clone(), field_json(Some(item))))
+                .collect(),
+        ),
+    }
+}
+
+/// Dead letters to delete or replay: the listed ones, all of a scene, or the listed ones of
+/// a scene. At least one of the two is required.
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct DeadLetterSelection {
+    #[serde(default)]
+    pub ids: Vec<String>,
+    pub scene_id: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct DeleteDeadLettersResponse {
+    deleted: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ReplayDeadLettersResponse {
+    replayed: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/by-id/{id}",
+    operation_id = "get-dead-letter",
+    description = "Get a dead-lettered event by its event ID",
+    summary = "Get dead letter by ID",
+    responses(
+        (status = 200, description = "Dead letter", body = DeadLetter),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "Event ID of the dead letter to fetch")
+    )
+)]
+async fn get_dead_letter(Path(id): Path<String>) -> Result<Json<DeadLetter>, StatusCode> {
+    log::trace!("Dead Letters API: getting dead letter [{id}]");
+    match queue::service().await.get_dead_letter(id.as_str()).await {
+        Ok(dead_letter) => Ok(Json(DeadLetter::from(dead_letter))),
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Dead Letters API: Failed to get dead letter [{id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            _ => {
+                log::error!("Dead Letters API: Failed to get dead letter [{id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/by-id/{id}",
+    operation_id = "delete-dead-letter",
+    description = "Delete a dead-lettered event by its event ID",
+    summary = "Delete dead letter by ID",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "Event ID of the dead letter to delete")
+    )
+)]
+async fn delete_dead_letter(Path(id): Path<String>) -> Result<StatusCode, StatusCode> {
+    log::trace!("Dead Letters API: deleting dead letter [{id}]");
+    let selection = Selection::new(vec![id.clone()], None).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match queue::service().await.delete_dead_letters(&selection).await {
+        Ok(0) => {
+            log::trace!("Dead Letters API: Failed to delete dead letter [{id}]: not found");
+            Err(StatusCode::NOT_FOUND)
+        }
+        Ok(_) => Ok(StatusCode::OK),
+        Err(e) => {
+            log::error!("Dead Letters API: Failed to delete dead letter [{id}]: {e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/replay",
+    operation_id = "replay-dead-letter",
+    description = "Put a dead-lettered event back on its scene edge. If the scene is not running, \
+        the event is delivered once it runs.",
+    summary = "Replay dead letter by ID",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "Event ID of the dead letter to replay")
+    )
+)]
+async fn replay_dead_letter(Path(id): Path<String>) -> Result<StatusCode, StatusCode> {
+    log::trace!("Dead Letters API: replaying dead letter [{id}]");
+    let selection = Selection::new(vec![id.clone()], None).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match director::service().replay_dead_letters(&selection).await {
+        Ok(0) => {
+            log::trace!("Dead Letters API: Failed to replay dead letter [{id}]: not found");
+            Err(StatusCode::NOT_FOUND)
+        }
+        Ok(_) => Ok(StatusCode::OK),
+        Err(e) => {
+            log::error!("Dead Letters API: Failed to replay dead letter [{id}]: {e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/delete",
+    operation_id = "delete-dead-letters",
+    description = "Delete dead-lettered events by event ID, by scene, or both",
+    summary = "Delete dead letters in bulk",
+    request_body(
+        content = DeadLetterSelection,
+        description = "Dead letters to delete",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Number of deleted dead letters", body = DeleteDeadLettersResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+)]
+async fn delete_dead_letters(
+    Json(selection): Json<DeadLetterSelection>,
+) -> Result<Json<DeleteDeadLettersResponse>, StatusCode> {
+    log::trace!("Dead Letters API: deleting dead letters");
+    let selection = match Selection::new(selection.ids, selection.scene_id) {
+        Ok(selection) => selection,
+        Err(e) => {
+            log::trace!("Dead Letters API: Failed to delete dead letters: {e}");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    };
+    match queue::service().await.delete_dead_letters(&selection).await {
+        Ok(deleted) => Ok(Json(DeleteDeadLettersResponse { deleted })),
+        Err(e) => {
+            log::error!("Dead Letters API: Failed to delete dead letters: {e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/replay",
+    operation_id = "replay-dead-letters",
+    description = "Put dead-lettered events back on their scene edges, selected by event ID, by \
+        scene, or both. Events of scenes that are not running are delivered once they run.",
+    summary = "Replay dead letters in bulk",
+    request_body(
+        content = DeadLetterSelection,
+        description = "Dead letters to replay",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Number of replayed dead letters", body = ReplayDeadLettersResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+)]
+async fn replay_dead_letters(
+    Json(selection): Json<DeadLetterSelection>,
+) -> Result<Json<ReplayDeadLettersResponse>, StatusCode> {
+    log::trace!("Dead Letters API: replaying dead letters");
+    let selection = match Selection::new(selection.ids, selection.scene_id) {
+        Ok(selection) => selection,
+        Err(e) => {
+            log::trace!("Dead Letters API: Failed to replay dead letters: {e}");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    };
+    match director::service().replay_dead_letters(&selection).await {
+        Ok(replayed) => Ok(Json(ReplayDeadLettersResponse { replayed })),
+        Err(DirectorError::Queue(ServiceError::Invalid(e))) => {
+            log::trace!("Dead Letters API: Failed to replay dead letters: {e}");
+            Err(StatusCode::BAD_REQUEST)
+        }
+        Err(e) => {
+            log::error!("Dead Letters API: Failed to replay dead letters: {e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+const DEFAULT_LIMIT: u32 = 50;
+
+#[derive(Deserialize, IntoParams, Clone)]
+pub(crate) struct ListFilters {
+    /// Only dead letters of this scene.
+    pub scene_id: Option<String>,
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+impl From<ListFilters> for queue::service::ListFilters {
+    fn from(value: ListFilters) -> Self {
+        queue::service::ListFilters::new(
+            value.scene_id,
+            i64::from(value.offset.unwrap_or(0)),
+            i64::from(value.limit.unwrap_or(DEFAULT_LIMIT)),
+        )
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ListDeadLettersResponse {
+    dead_letters: Vec<DeadLetter>,
+    has_more: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "",
+    operation_id = "list-dead-letters",
+    description = "List dead-lettered events, newest first (paginated)",
+    summary = "List dead letters (paginated)",
+    responses(
+        (status = 200, description = "Dead letter page", body = ListDeadLettersResponse),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ListFilters
+    )
+)]
+async fn list_dead_letters(
+    Query(filters): Query<ListFilters>,
+) -> Result<Json<ListDeadLettersResponse>, StatusCode> {
+    log::trace!(
+        "Dead Letters API: listing dead letters [{offset}:{limit}]",
+        offset = filters.offset.unwrap_or(0),
+        limit = filters.limit.unwrap_or(DEFAULT_LIMIT)
+    );
+    match queue::service()
+        .await
+        .list_dead_letters(filters.into())
+        .await
+    {
+        Ok((dead_letters, has_more)) => Ok(Json(ListDeadLettersResponse {
+            dead_letters: dead_letters.into_iter().map(From::from).collect(),
+            has_more,
+        })),
+        Err(e) => {
+            log::error!("Dead Letters API: Failed to list dead letters: {e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Dead Letters",
+        description = "Events given up on after all delivery attempts",
+    ),
+    paths(
+        list_dead_letters,
+        delete_dead_letters,
+        replay_dead_letters,
+        get_dead_letter,
+        delete_dead_letter,
+        replay_dead_letter,
+    ),
+    components(schemas(
+        DeadLetter,
+        DeadLetterSelection,
+        DeleteDeadLettersResponse,
+        ReplayDeadLettersResponse,
+        ListDeadLettersResponse,
+    ))
+)]
+pub(crate) struct Api;
+
+impl Api {
+    pub(crate) fn build_router() -> Router {
+        Router::new()
+            .route("/dead-letters", get(list_dead_letters))
+            .route("/dead-letters/delete", post(delete_dead_letters))
+            .route("/dead-letters/replay", post(replay_dead_letters))
+            .route(
+                "/dead-letters/by-id/{id}",
+                get(get_dead_letter).delete(delete_dead_letter),
+            )
+            .route("/dead-letters/by-id/{id}/replay", post(replay_dead_letter))
+    }
+}
@@ -0,0 +1,305 @@
+mod query_sqlite;
+
+use chrono::{DateTime, Local};
+use flwrs_core::config;
+use flwrs_core::config::main_config;
+use flwrs_core::db::{Database, DbError};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Deserialize)]
+pub(crate) struct QueueConfig {
+    /// Seconds an event is kept before it is purged, delivered or not.
+    #[serde(default = "default_retention_seconds")]
+    retention_seconds: u64,
+    /// Events a scene may have queued. Further events are dropped until some are acked.
+    #[serde(default = "default_max_events")]
+    max_events: u64,
+    #[serde(default = "default_purge_interval_seconds")]
+    purge_interval_seconds: u64,
+    /// Overrides of the limits above, by scene ID.
+    #[serde(default)]
+    scenes: HashMap<String, SceneQueueConfig>,
+}
+
+#[derive(Deserialize)]
+struct SceneQueueConfig {
+    retention_seconds: Option<u64>,
+    max_events: Option<u64>,
+}
+
+fn default_retention_seconds() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_max_events() -> u64 {
+    100_000
+}
+
+fn default_purge_interval_seconds() -> u64 {
+    60
+}
+
+impl QueueConfig {
+    pub(crate) fn read() -> Self {
+        let main_config = main_config();
+        config::read_struct(main_config, &["queue".to_string()]).unwrap_or(Self {
+            retention_seconds: default_retention_seconds(),
+            max_events: default_max_events(),
+            purge_interval_seconds: default_purge_interval_seconds(),
+            scenes: HashMap::new(),
+        })
+    }
+}
+
+/// Retention and size limits of the queue of one scene.
+#[derive(Clone, Copy)]
+pub(crate) struct QueueLimits {
+    pub retention: Duration,
+    pub max_events: u64,
+    pub purge_interval: Duration,
+}
+
+/// An event waiting to be acked by the plugin at the end of an edge.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub(crate) struct QueuedEvent {
+    /// Event ID the plugin acks.
+    pub id: String,
+    pub scene_id: String,
+    pub edge_id: String,
+    pub target_plugin_id: String,
+    /// `PluginPayload`, protobuf encoded.
+    pub payload: Vec<u8>,
+    pub attempts: u32,
+    /// Event the plugin emitted, which this one is a copy of.
+    pub parent_id: String,
+    pub trace_id: String,
+    pub create_time: DateTime<Local>,
+}
+
+/// An event given up on after all its delivery attempts, kept for inspection and replay.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub(crate) struct DeadLetter {
+    pub id: String,
+    pub scene_id: String,
+    pub edge_id: String,
+    pub target_plugin_id: String,
+    /// `PluginPayload`, protobuf encoded.
+    pub payload: Vec<u8>,
+    pub attempts: u32,
+    /// Why the last delivery attempt failed.
+    pub error: String,
+    pub parent_id: String,
+    pub trace_id: String,
+    /// When the event was first routed.
+    pub create_time: DateTime<Local>,
+    pub dead_time: DateTime<Local>,
+}
+
+pub(crate) struct ListFilters {
+    pub(self) scene_id: Option<String>,
+    pub(self) offset: i64,
+    pub(self) limit: i64,
+}
+
+impl ListFilters {
+    pub(crate) fn new(scene_id: Option<String>, offset: i64, limit: i64) -> Self {
+        Self {
+            scene_id,
+            offset,
+            limit,
+        }
+    }
+}
+
+/// Dead letters a bulk operation applies to: the listed ones, all of a scene, or the listed
+/// ones of a scene.
+pub(crate) struct Selection {
+    pub(self) ids: Vec<String>,
+    pub(self) scene_id: Option<String>,
+}
+
+impl Selection {
+    pub(crate) fn new(ids: Vec<String>, scene_id: Option<String>) -> Result<Self, ServiceError> {
+        if ids.is_empty() && scene_id.is_none() {
+            return Err(ServiceError::Invalid(
+                "select dead letters by ID or scene".to_string(),
+            ));
+        }
+        Ok(Self { ids, scene_id })
+    }
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum ServiceError {
+    #[error("no event found")]
+    NotFound,
+    #[error("conflict")]
+    Conflict,
+    #[error("invalid input: {0}")]
+    Invalid(String),
+    #[error("failed to execute query: {0}")]
+    Query(sqlx::Error),
+    #[error("failed to get connection: {0}")]
+    Connection(#[from] DbError),
+}
+
+/// Durable queue of routed events. Events are written before they are handed to an edge and
+/// removed once acked, so a restarted runtime can deliver what it had in flight.
+pub(crate) struct Service {
+    db: &'static Database,
+    config: QueueConfig,
+}
+
+impl Service {
+    pub(crate) fn new(db: &'static Database, config: QueueConfig) -> Self {
+        Self { db, config }
+    }
+
+    pub(crate) fn limits(&self, scene_id: &str) -> QueueLimits {
+        let scene = self.config.scenes.get(scene_id);
+        QueueLimits {
+            retention: Duration::from_secs(
+                scene
+                    .and_then(|scene| scene.retention_seconds)
+                    .unwrap_or(self.config.retention_seconds),
+            ),
+            max_events: scene
+                .and_then(|scene| scene.max_events)
+                .unwrap_or(self.config.max_events),
+            purge_interval: Duration::from_secs(self.config.purge_interval_seconds.max(1)),
+        }
+    }
+
+    /// Stores the copies of one event, one per edge, all or none.
+    pub(crate) async fn append(&self, events: &[QueuedEvent]) -> Result<(), ServiceError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(query_sqlite::append_events(&mut conn, events).await?)
+            }
+        }
+    }
+
+    pub(crate) async fn remove(&self, id: &str) -> Result<(), ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                match query_sqlite::delete_event(&mut conn, id).await? {
+                    0 => Err(ServiceError::NotFound),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn record_attempt(&self, id: &str, attempts: u32) -> Result<(), ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                match query_sqlite::update_attempts(&mut conn, id, attempts).await? {
+                    0 => Err(ServiceError::NotFound),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Events of the scene that were never acked, oldest first.
+    pub(crate) async fn pending(&self, scene_id: &str) -> Result<Vec<QueuedEvent>, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(query_sqlite::list_scene_events(&mut conn, scene_id).await?)
+            }
+        }
+    }
+
+    /// Moves an event out of the queue into the dead letters. The event is passed in full,
+    /// so it is kept even if its queued copy is gone.
+    pub(crate) async fn dead_letter(&self, dead_letter: DeadLetter) -> Result<(), ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(query_sqlite::move_to_dead_letters(&mut conn, dead_letter).await?)
+            }
+        }
+    }
+
+    pub(crate) async fn get_dead_letter(&self, id: &str) -> Result<DeadLetter, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(query_sqlite::get_dead_letter(&mut conn, id).await?)
+            }
+        }
+    }
+
+    /// Newest dead letters first, optionally of one scene only.
+    pub(crate) async fn list_dead_letters(
+        &self,
+        filters: ListFilters,
+    ) -> Result<(Vec<DeadLetter>, bool), ServiceError> {
+        let limit = filters.limit;
+        let input = ListFilters {
+            limit: limit + 1,
+            ..filters
+        };
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                let dead_letters = query_sqlite::list_dead_letters(&mut conn, input).await?;
+                let has_more = dead_letters.len() > limit as usize;
+                Ok((
+                    dead_letters.into_iter().take(limit as usize).collect(),
+                    has_more,
+                ))
+            }
+        }
+    }
+
+    /// Deletes the selected dead letters. Returns how many.
+    pub(crate) async fn delete_dead_letters(
+        &self,
+        selection: &Selection,
+    ) -> Result<u64, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(query_sqlite::delete_dead_letters(&mut conn, selection).await?)
+            }
+        }
+    }
+
+    /// Moves the selected dead letters back into the queue with fresh attempts, and returns
+    /// them as queued events for the router of their scene to pick up.
+    pub(crate) async fn requeue_dead_letters(
+        &self,
+        selection: &Selection,
+    ) -> Result<Vec<QueuedEvent>, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(query_sqlite::requeue_dead_letters(&mut conn, selection, Local::now()).await?)
+            }
+        }
+    }
+
+    /// Drops the events of the scene that are older than its retention. Returns how many.
+    pub(crate) async fn purge_expired(&self, scene_id: &str) -> Result<u64, ServiceError> {
+        let retention = self.limits(scene_id).retention;
+        let before =
+            Local::now() - chrono::Duration::from_std(retention).unwrap_or(chrono::Duration::MAX);
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(query_sqlite::delete_scene_events_before(&mut conn, scene_id, before).await?)
+            }
+        }
+    }
+}
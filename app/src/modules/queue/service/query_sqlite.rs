@@ -0,0 +1,373 @@
+use crate::modules::queue::service::{
+    DeadLetter, ListFilters, QueuedEvent, Selection, ServiceError,
+};
+use chrono::{DateTime, Local};
+use sqlx::error::ErrorKind;
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, Executor, FromRow, QueryBuilder, Sqlite};
+
+impl From<sqlx::Error> for ServiceError {
+    fn from(error: sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::RowNotFound => ServiceError::NotFound,
+            _ => match error.as_database_error() {
+                None => ServiceError::Query(error),
+                Some(e) => match e.kind() {
+                    ErrorKind::UniqueViolation => ServiceError::Conflict,
+                    _ => ServiceError::Query(error),
+                },
+            },
+        }
+    }
+}
+
+/// Rows per multi-row statement, well below the bind parameter limit of SQLite.
+const BATCH_SIZE: usize = 500;
+
+pub(super) async fn append_events(
+    conn: &mut PoolConnection<Sqlite>,
+    events: &[QueuedEvent],
+) -> Result<(), sqlx::Error> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "INSERT INTO events (id, scene_id, edge_id, target_plugin_id, payload, attempts, parent_id, trace_id, create_time) ",
+    );
+    query.push_values(events, |mut row, event| {
+        row.push_bind(event.id.as_str())
+            .push_bind(event.scene_id.as_str())
+            .push_bind(event.edge_id.as_str())
+            .push_bind(event.target_plugin_id.as_str())
+            .push_bind(event.payload.as_slice())
+            .push_bind(event.attempts)
+            .push_bind(event.parent_id.as_str())
+            .push_bind(event.trace_id.as_str())
+            .push_bind(event.create_time);
+    });
+    conn.execute(query.build()).await?;
+    Ok(())
+}
+
+pub(super) async fn delete_event(
+    conn: &mut PoolConnection<Sqlite>,
+    id: &str,
+) -> Result<u64, sqlx::Error> {
+    let result = conn
+        .execute(sqlx::query("DELETE FROM events WHERE id = $1").bind(id))
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub(super) async fn update_attempts(
+    conn: &mut PoolConnection<Sqlite>,
+    id: &str,
+    attempts: u32,
+) -> Result<u64, sqlx::Error> {
+    let result = conn
+        .execute(
+            sqlx::query("UPDATE events SET attempts = $1 WHERE id = $2")
+                .bind(attempts)
+                .bind(id),
+        )
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub(super) async fn list_scene_events(
+    conn: &mut PoolConnection<Sqlite>,
+    scene_id: &str,
+) -> Result<Vec<QueuedEvent>, sqlx::Error> {
+    let rows = conn
+        .fetch_all(
+            sqlx::query_as::<Sqlite, QueuedEvent>(
+                "SELECT * FROM events WHERE scene_id = $1 ORDER BY create_time, id",
+            )
+            .bind(scene_id),
+        )
+        .await?;
+    let mut events = Vec::with_capacity(rows.len());
+    for row in rows.iter() {
+        events.push(QueuedEvent::from_row(row)?);
+    }
+    Ok(events)
+}
+
+pub(super) async fn delete_scene_events_before(
+    conn: &mut PoolConnection<Sqlite>,
+    scene_id: &str,
+    before: DateTime<Local>,
+) -> Result<u64, sqlx::Error> {
+    let result = conn
+        .execute(
+            sqlx::query("DELETE FROM events WHERE scene_id = $1 AND create_time < $2")
+                .bind(scene_id)
+                .bind(before),
+        )
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub(super) async fn move_to_dead_letters(
+    conn: &mut PoolConnection<Sqlite>,
+    dead_letter: DeadLetter,
+) -> Result<(), sqlx::Error> {
+    let mut tx = conn.begin().await?;
+    tx.execute(
+        sqlx::query(
+            "INSERT OR REPLACE INTO dead_letters (id, scene_id, edge_id, target_plugin_id, payload, \
+                attempts, error, parent_id, trace_id, create_time, dead_time) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+        )
+        .bind(dead_letter.id.as_str())
+        .bind(dead_letter.scene_id)
+        .bind(dead_letter.edge_id)
+        .bind(dead_letter.target_plugin_id)
+        .bind(dead_letter.payload)
+        .bind(dead_letter.attempts)
+        .bind(dead_letter.error)
+        .bind(dead_letter.parent_id)
+        .bind(dead_letter.trace_id)
+        .bind(dead_letter.create_time)
+        .bind(dead_letter.dead_time),
+    )
+    .await?;
+    tx.execute(sqlx::query("DELETE FROM events WHERE id = $1").bind(dead_letter.id.as_str()))
+        .await?;
+    tx.commit().await
+}
+
+pub(super) async fn get_dead_letter(
+    conn: &mut PoolConnection<Sqlite>,
+    id: &str,
+) -> Result<DeadLetter, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, DeadLetter>("SELECT * FROM dead_letters WHERE id = $1")
+                .bind(id),
+        )
+        .await?;
+    let dead_letter = DeadLetter::from_row(&row)?;
+    Ok(dead_letter)
+}
+
+pub(super) async fn list_dead_letters(
+    conn: &mut PoolConnection<Sqlite>,
+    filters: ListFilters,
+) -> Result<Vec<DeadLetter>, sqlx::Error> {
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM dead_letters");
+    if let Some(scene_id) = filters.scene_id {
+        query.push(" WHERE scene_id = ").push_bind(scene_id);
+    }
+    query
+        .push(" ORDER BY dead_time DESC, id DESC LIMIT ")
+        .push_bind(filters.limit)
+        .push(" OFFSET ")
+        .push_bind(filters.offset);
+    let rows = conn.fetch_all(query.build()).await?;
+    let mut dead_letters = vec![];
+    for row in rows {
+        dead_letters.push(DeadLetter::from_row(&row)?);
+    }
+
+    Ok(dead_letters)
+}
+
+pub(super) async fn delete_dead_letters(
+    conn: &mut PoolConnection<Sqlite>,
+    selection: &Selection,
+) -> Result<u64, sqlx::Error> {
+    let mut query = QueryBuilder::<Sqlite>::new("DELETE FROM dead_letters");
+    push_selection(&mut query, selection);
+    let result = conn.execute(query.build()).await?;
+    Ok(result.rows_affected())
+}
+
+pub(super) async fn requeue_dead_letters(
+    conn: &mut PoolConnection<Sqlite>,
+    selection: &Selection,
+    time: DateTime<Local>,
+) -> Result<Vec<QueuedEvent>, sqlx::Error> {
+    let mut tx = conn.begin().await?;
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM dead_letters");
+    push_selection(&mut query, selection);
+    query.push(" ORDER BY create_time, id");
+    let rows = tx.fetch_all(query.build()).await?;
+    let mut events = vec![];
+    for row in rows {
+        let dead_letter = DeadLetter::from_row(&row)?;
+        events.push(QueuedEvent {
+            id: dead_letter.id,
+            scene_id: dead_letter.scene_id,
+            edge_id: dead_letter.edge_id,
+            target_plugin_id: dead_letter.target_plugin_id,
+            payload: dead_letter.payload,
+            attempts: 0,
+            parent_id: dead_letter.parent_id,
+            trace_id: dead_letter.trace_id,
+            create_time: time,
+        });
+    }
+    for batch in events.chunks(BATCH_SIZE) {
+        let mut insert = QueryBuilder::<Sqlite>::new(
+            "INSERT OR REPLACE INTO events (id, scene_id, edge_id, target_plugin_id, payload, attempts, parent_id, trace_id, create_time) ",
+        );
+        insert.push_values(batch, |mut row, event| {
+            row.push_bind(event.id.as_str())
+                .push_bind(event.scene_id.as_str())
+                .push_bind(event.edge_id.as_str())
+                .push_bind(event.target_plugin_id.as_str())
+                .push_bind(event.payload.as_slice())
+                .push_bind(event.attempts)
+                .push_bind(event.parent_id.as_str())
+                .push_bind(event.trace_id.as_str())
+                .push_bind(event.create_time);
+        });
+        tx.execute(insert.build()).await?;
+
+        let mut delete = QueryBuilder::<Sqlite>::new("DELETE FROM dead_letters WHERE id IN ");
+        delete.push_tuples(batch, |mut row, event| {
+            row.push_bind(event.id.as_str());
+        });
+        tx.execute(delete.build()).await?;
+    }
+    tx.commit().await?;
+    Ok(events)
+}
+
+fn push_selection<'a>(query: &mut QueryBuilder<'a, Sqlite>, selection: &'a Selection) {
+    query.push(" WHERE 1 = 1");
+    if let Some(scene_id) = selection.scene_id.as_ref() {
+        query.push(" AND scene_id = ").push_bind(scene_id.as_str());
+    }
+    if !selection.ids.is_empty() {
+        query.push(" AND id IN ");
+        query.push_tuples(selection.ids.iter(), |mut row, id| {
+            row.push_bind(id.as_str());
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn connection() -> PoolConnection<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        for migration in [
+            include_str!("../../../../resources/migrations/sqlite/queue/1.0.0-1.up.sql"),
+            include_str!("../../../../resources/migrations/sqlite/queue/1.0.0-2.up.sql"),
+            include_str!("../../../../resources/migrations/sqlite/queue/1.0.0-3.up.sql"),
+        ] {
+            conn.execute(sqlx::raw_sql(migration)).await.unwrap();
+        }
+        conn
+    }
+
+    fn event(id: &str, create_time: DateTime<Local>) -> QueuedEvent {
+        QueuedEvent {
+            id: id.to_string(),
+            scene_id: "scene".to_string(),
+            edge_id: "edge".to_string(),
+            target_plugin_id: "plugin".to_string(),
+            payload: vec![1, 2, 3],
+            attempts: 0,
+            parent_id: "parent".to_string(),
+            trace_id: "trace".to_string(),
+            create_time,
+        }
+    }
+
+    fn dead_letter(event: QueuedEvent, attempts: u32) -> DeadLetter {
+        DeadLetter {
+            id: event.id,
+            scene_id: event.scene_id,
+            edge_id: event.edge_id,
+            target_plugin_id: event.target_plugin_id,
+            payload: event.payload,
+            attempts,
+            error: "rejected: boom".to_string(),
+            parent_id: event.parent_id,
+            trace_id: event.trace_id,
+            create_time: event.create_time,
+            dead_time: Local::now(),
+        }
+    }
+
+    fn ids(events: &[QueuedEvent]) -> Vec<&str> {
+        events.iter().map(|event| event.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn pending_events_replay_in_routing_order() {
+        let mut conn = connection().await;
+        let now = Local::now();
+        append_events(&mut conn, &[event("e3", now + TimeDelta::seconds(2))])
+            .await
+            .unwrap();
+        append_events(&mut conn, &[event("e1", now), event("e2", now)])
+            .await
+            .unwrap();
+        let events = list_scene_events(&mut conn, "scene").await.unwrap();
+        assert_eq!(ids(&events), ["e1", "e2", "e3"]);
+    }
+
+    #[tokio::test]
+    async fn attempts_survive_a_restart() {
+        let mut conn = connection().await;
+        append_events(&mut conn, &[event("e1", Local::now())])
+            .await
+            .unwrap();
+        assert_eq!(update_attempts(&mut conn, "e1", 2).await.unwrap(), 1);
+        let events = list_scene_events(&mut conn, "scene").await.unwrap();
+        assert_eq!(events[0].attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn dead_lettering_takes_the_event_off_the_queue() {
+        let mut conn = connection().await;
+        let queued = event("e1", Local::now());
+        append_events(&mut conn, std::slice::from_ref(&queued))
+            .await
+            .unwrap();
+        move_to_dead_letters(&mut conn, dead_letter(queued, 3))
+            .await
+            .unwrap();
+        assert!(list_scene_events(&mut conn, "scene")
+            .await
+            .unwrap()
+            .is_empty());
+        let dead_letter = get_dead_letter(&mut conn, "e1").await.unwrap();
+        assert_eq!(dead_letter.attempts, 3);
+        assert_eq!(dead_letter.error, "rejected: boom");
+        assert_eq!(dead_letter.payload, vec![1, 2, 3]);
+        assert_eq!(dead_letter.trace_id, "trace");
+    }
+
+    #[tokio::test]
+    async fn requeued_dead_letters_get_fresh_attempts() {
+        let mut conn = connection().await;
+        let now = Local::now();
+        for (id, time) in [("e2", now + TimeDelta::seconds(1)), ("e1", now)] {
+            move_to_dead_letters(&mut conn, dead_letter(event(id, time), 3))
+                .await
+                .unwrap();
+        }
+        let selection = Selection::new(vec![], Some("scene".to_string())).unwrap();
+        let requeued = requeue_dead_letters(&mut conn, &selection, Local::now())
+            .await
+            .unwrap();
+        assert_eq!(ids(&requeued), ["e1", "e2"]);
+        assert!(matches!(
+            get_dead_letter(&mut conn, "e1").await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+        let events = list_scene_events(&mut conn, "scene").await.unwrap();
+        assert_eq!(ids(&events), ["e1", "e2"]);
+        assert!(events.iter().all(|event| event.attempts == 0));
+    }
+}
@@ -0,0 +1,504 @@
+use crate::modules::recording;
+use crate::modules::recording::service::{
+    RecordingState, ReplayRequest, ReplayState, ReplayStatus, ServiceError, Timing,
+};
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) enum State {
+    #[serde(rename = "recording")]
+    Recording,
+    #[serde(rename = "stopped")]
+    Stopped,
+}
+
+impl From<RecordingState> for State {
+    fn from(value: RecordingState) -> Self {
+        match value {
+            RecordingState::Recording => State::Recording,
+            RecordingState::Stopped => State::Stopped,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Recording {
+    pub id: String,
+    pub scene_id: String,
+    pub name: Option<String>,
+    pub state: State,
+    /// Source events recorded so far.
+    pub event_count: i64,
+    pub start_time: i64,
+    pub stop_time: Option<i64>,
+}
+
+impl From<recording::service::Recording> for Recording {
+    fn from(value: recording::service::Recording) -> Self {
+        Self {
+            id: value.id,
+            scene_id: value.scene_id,
+            name: value.name,
+            state: value.state.into(),
+            event_count: value.event_count,
+            start_time: value.start_time.timestamp_millis(),
+            stop_time: value.stop_time.map(|time| time.timestamp_millis()),
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct StartRecordingRequest {
+    /// Scene whose source events to record.
+    pub scene_id: String,
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Default)]
+pub(crate) enum ReplayTiming {
+    /// Keep the time that passed between the recorded events.
+    #[default]
+    #[serde(rename = "original")]
+    Original,
+    /// Replay the events as fast as the scene takes them.
+    #[serde(rename = "fast")]
+    Fast,
+}
+
+impl From<ReplayTiming> for Timing {
+    fn from(value: ReplayTiming) -> Self {
+        match value {
+            ReplayTiming::Original => Timing::Original,
+            ReplayTiming::Fast => Timing::Fast,
+        }
+    }
+}
+
+impl From<Timing> for ReplayTiming {
+    fn from(value: Timing) -> Self {
+        match value {
+            Timing::Original => ReplayTiming::Original,
+            Timing::Fast => ReplayTiming::Fast,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct StartReplayRequest {
+    /// Scene to replay into, which must be running. Defaults to the recorded scene.
+    pub scene_id: Option<String>,
+    #[serde(default)]
+    pub timing: ReplayTiming,
+    /// Nodes of the target scene by recorded node ID, for scenes whose source nodes have
+    /// other IDs than the recorded ones. Unmapped nodes keep their ID.
+    #[serde(default)]
+    pub node_map: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) enum Progress {
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+}
+
+impl From<ReplayState> for Progress {
+    fn from(value: ReplayState) -> Self {
+        match value {
+            ReplayState::Running => Progress::Running,
+            ReplayState::Completed => Progress::Completed,
+            ReplayState::Failed => Progress::Failed,
+            ReplayState::Cancelled => Progress::Cancelled,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Replay {
+    pub id: String,
+    pub recording_id: String,
+    pub scene_id: String,
+    pub timing: ReplayTiming,
+    pub state: Progress,
+    /// Events in the recording.
+    pub total: u64,
+    /// Events routed into the scene so far.
+    pub replayed: u64,
+    /// Events dropped, because the scene queue was full or the payload undecodable.
+    pub dropped: u64,
+    /// Why the replay failed.
+    pub error: Option<String>,
+    pub start_time: i64,
+    pub finish_time: Option<i64>,
+}
+
+impl From<ReplayStatus> for Replay {
+    fn from(value: ReplayStatus) -> Self {
+        Self {
+            id: value.id,
+            recording_id: value.recording_id,
+            scene_id: value.scene_id,
+            timing: value.timing.into(),
+            state: value.state.into(),
+            total: value.total,
+            replayed: value.replayed,
+            dropped: value.dropped,
+            error: value.error,
+            start_time: value.start_time.timestamp_millis(),
+            finish_time: value.finish_time.map(|time| time.timestamp_millis()),
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "",
+    operation_id = "start-recording",
+    description = "Start recording the events the source plugins of a scene emit. A scene that \
+        is not running is recorded once it runs. A scene is recorded by one recording at a time.",
+    summary = "Start recording",
+    request_body(
+        content = StartRecordingRequest,
+        description = "Scene to record",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 201, description = "Recording started", body = Recording),
+        (status = 404, description = "Scene not found"),
+        (status = 409, description = "Scene is already recorded"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+)]
+async fn start_recording(
+    Json(request): Json<StartRecordingRequest>,
+) -> Result<(StatusCode, Json<Recording>), StatusCode> {
+    log::trace!(
+        "Recordings API: recording scene [{scene_id}]",
+        scene_id = request.scene_id
+    );
+    match recording::service()
+        .await
+        .start(request.scene_id.as_str(), request.name)
+        .await
+    {
+        Ok(recording) => Ok((StatusCode::CREATED, Json(recording.into()))),
+        Err(e) => Err(error_status("start recording", e)),
+    }
+}
+
+const DEFAULT_LIMIT: u32 = 50;
+
+#[derive(Deserialize, IntoParams, Clone)]
+pub(crate) struct ListFilters {
+    /// Only recordings of this scene.
+    pub scene_id: Option<String>,
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+impl From<ListFilters> for recording::service::ListFilters {
+    fn from(value: ListFilters) -> Self {
+        recording::service::ListFilters::new(
+            value.scene_id,
+            i64::from(value.offset.unwrap_or(0)),
+            i64::from(value.limit.unwrap_or(DEFAULT_LIMIT)),
+        )
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ListRecordingsResponse {
+    recordings: Vec<Recording>,
+    has_more: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "",
+    operation_id = "list-recordings",
+    description = "List recordings, newest first (paginated)",
+    summary = "List recordings (paginated)",
+    responses(
+        (status = 200, description = "Recording page", body = ListRecordingsResponse),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ListFilters
+    )
+)]
+async fn list_recordings(
+    Query(filters): Query<ListFilters>,
+) -> Result<Json<ListRecordingsResponse>, StatusCode> {
+    log::trace!(
+        "Recordings API: listing recordings [{offset}:{limit}]",
+        offset = filters.offset.unwrap_or(0),
+        limit = filters.limit.unwrap_or(DEFAULT_LIMIT)
+    );
+    match recording::service().await.list(filters.into()).await {
+        Ok((recordings, has_more)) => Ok(Json(ListRecordingsResponse {
+            recordings: recordings.into_iter().map(From::from).collect(),
+            has_more,
+        })),
+        Err(e) => Err(error_status("list recordings", e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/by-id/{id}",
+    operation_id = "get-recording",
+    description = "Get a recording by its ID",
+    summary = "Get recording by ID",
+    responses(
+        (status = 200, description = "Recording", body = Recording),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "Recording ID")
+    )
+)]
+async fn get_recording(Path(id): Path<String>) -> Result<Json<Recording>, StatusCode> {
+    log::trace!("Recordings API: getting recording [{id}]");
+    match recording::service().await.get(id.as_str()).await {
+        Ok(recording) => Ok(Json(recording.into())),
+        Err(e) => Err(error_status("get recording", e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/by-id/{id}",
+    operation_id = "delete-recording",
+    description = "Delete a stopped recording and its events",
+    summary = "Delete recording by ID",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Still recording"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "Recording ID")
+    )
+)]
+async fn delete_recording(Path(id): Path<String>) -> Result<StatusCode, StatusCode> {
+    log::trace!("Recordings API: deleting recording [{id}]");
+    match recording::service().await.delete(id.as_str()).await {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(e) => Err(error_status("delete recording", e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/stop",
+    operation_id = "stop-recording",
+    description = "Stop a recording. Stopping a stopped recording changes nothing.",
+    summary = "Stop recording",
+    responses(
+        (status = 200, description = "Recording stopped", body = Recording),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "Recording ID")
+    )
+)]
+async fn stop_recording(Path(id): Path<String>) -> Result<Json<Recording>, StatusCode> {
+    log::trace!("Recordings API: stopping recording [{id}]");
+    match recording::service().await.stop(id.as_str()).await {
+        Ok(recording) => Ok(Json(recording.into())),
+        Err(e) => Err(error_status("stop recording", e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/replay",
+    operation_id = "replay-recording",
+    description = "Replay the recorded events into a running scene, in the order they were \
+        recorded, keeping their original timing or as fast as the scene takes them. Replayed \
+        events are new events with IDs of their own. The replay runs in the background.",
+    summary = "Replay recording",
+    request_body(
+        content = StartReplayRequest,
+        description = "Where and how fast to replay",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 202, description = "Replay started", body = Replay),
+        (status = 400, description = "Recorded nodes are not nodes of the scene"),
+        (status = 404, description = "Recording or scene not found"),
+        (status = 409, description = "Scene is not running"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "Recording ID")
+    )
+)]
+async fn replay_recording(
+    Path(id): Path<String>,
+    Json(request): Json<StartReplayRequest>,
+) -> Result<(StatusCode, Json<Replay>), StatusCode> {
+    log::trace!("Recordings API: replaying recording [{id}]");
+    let request = ReplayRequest {
+        scene_id: request.scene_id,
+        timing: request.timing.into(),
+        node_map: request.node_map,
+    };
+    match recording::service()
+        .await
+        .replay(id.as_str(), request)
+        .await
+    {
+        Ok(replay) => Ok((StatusCode::ACCEPTED, Json(replay.into()))),
+        Err(e) => Err(error_status("replay recording", e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/replays",
+    operation_id = "list-replays",
+    description = "List running replays and the last finished ones, newest first",
+    summary = "List replays",
+    responses(
+        (status = 200, description = "Replays", body = [Replay]),
+    ),
+)]
+async fn list_replays() -> Json<Vec<Replay>> {
+    log::trace!("Recordings API: listing replays");
+    Json(
+        recording::service()
+            .await
+            .list_replays()
+            .into_iter()
+            .map(From::from)
+            .collect(),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/replays/by-id/{id}",
+    operation_id = "get-replay",
+    description = "Get the progress of a replay by its ID",
+    summary = "Get replay by ID",
+    responses(
+        (status = 200, description = "Replay", body = Replay),
+        (status = 404, description = "Not found"),
+    ),
+    params(
+        ("id" = String, Path, description = "Replay ID")
+    )
+)]
+async fn get_replay(Path(id): Path<String>) -> Result<Json<Replay>, StatusCode> {
+    log::trace!("Recordings API: getting replay [{id}]");
+    match recording::service().await.get_replay(id.as_str()) {
+        Ok(replay) => Ok(Json(replay.into())),
+        Err(e) => Err(error_status("get replay", e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/replays/by-id/{id}/cancel",
+    operation_id = "cancel-replay",
+    description = "Stop a running replay. Events already replayed stay in the scene.",
+    summary = "Cancel replay",
+    responses(
+        (status = 200, description = "Replay", body = Replay),
+        (status = 404, description = "Not found"),
+    ),
+    params(
+        ("id" = String, Path, description = "Replay ID")
+    )
+)]
+async fn cancel_replay(Path(id): Path<String>) -> Result<Json<Replay>, StatusCode> {
+    log::trace!("Recordings API: cancelling replay [{id}]");
+    match recording::service().await.cancel_replay(id.as_str()) {
+        Ok(replay) => Ok(Json(replay.into())),
+        Err(e) => Err(error_status("cancel replay", e)),
+    }
+}
+
+fn error_status(action: &str, e: ServiceError) -> StatusCode {
+    match e {
+        ServiceError::NotFound | ServiceError::ReplayNotFound | ServiceError::SceneNotFound(_) => {
+            log::trace!("Recordings API: Failed to {action}: {e}");
+            StatusCode::NOT_FOUND
+        }
+        ServiceError::AlreadyRecording(_)
+        | ServiceError::StillRecording(_)
+        | ServiceError::NotRunning(_) => {
+            log::trace!("Recordings API: Failed to {action}: {e}");
+            StatusCode::CONFLICT
+        }
+        ServiceError::Invalid(_) => {
+            log::trace!("Recordings API: Failed to {action}: {e}");
+            StatusCode::BAD_REQUEST
+        }
+        _ => {
+            log::error!("Recordings API: Failed to {action}: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Recordings",
+        description = "Recorded source events of scenes, and their replay into running scenes",
+    ),
+    paths(
+        start_recording,
+        list_recordings,
+        get_recording,
+        delete_recording,
+        stop_recording,
+        replay_recording,
+        list_replays,
+        get_replay,
+        cancel_replay,
+    ),
+    components(schemas(
+        State,
+        Recording,
+        StartRecordingRequest,
+        ListRecordingsResponse,
+        ReplayTiming,
+        StartReplayRequest,
+        Progress,
+        Replay,
+    ))
+)]
+pub(crate) struct Api;
+
+impl Api {
+    pub(crate) fn build_router() -> Router {
+        Router::new()
+            .route("/recordings", get(list_recordings).post(start_recording))
+            .route(
+                "/recordings/by-id/{id}",
+                get(get_recording).delete(delete_recording),
+            )
+            .route("/recordings/by-id/{id}/stop", post(stop_recording))
+            .route("/recordings/by-id/{id}/replay", post(replay_recording))
+            .route("/recordings/replays", get(list_replays))
+            .route("/recordings/replays/by-id/{id}", get(get_replay))
+            .route("/recordings/replays/by-id/{id}/cancel", post(cancel_replay))
+    }
+}
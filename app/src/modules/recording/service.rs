@@ -0,0 +1,671 @@
+mod query_sqlite;
+
+use crate::modules::{director, scene};
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use flwrs_core::db::{Database, DbError};
+use flwrs_core::registry;
+use flwrs_core::registry::RegistryError;
+use flwrs_plugin::schema::schema::PluginPayload;
+use prost::Message;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use ulid::Ulid;
+
+/// Recorded events loaded at once while replaying.
+const REPLAY_PAGE_SIZE: i64 = 500;
+
+/// Finished replays kept for their status to be looked up.
+const REPLAY_HISTORY: usize = 100;
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub(crate) enum RecordingState {
+    Recording,
+    Stopped,
+}
+
+/// Source events of a scene, recorded for replay.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub(crate) struct Recording {
+    pub id: String,
+    pub scene_id: String,
+    pub name: Option<String>,
+    pub state: RecordingState,
+    pub event_count: i64,
+    pub start_time: DateTime<Local>,
+    pub stop_time: Option<DateTime<Local>>,
+}
+
+/// An event a source plugin emitted into the scene while it was recorded.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub(crate) struct RecordedEvent {
+    pub recording_id: String,
+    /// Order the events were emitted in.
+    pub seq: i64,
+    /// Source node the event was emitted by.
+    pub node_id: String,
+    pub plugin_id: String,
+    /// `PluginPayload`, protobuf encoded.
+    pub payload: Vec<u8>,
+    /// Time since the recording started.
+    pub offset_micros: i64,
+}
+
+pub(crate) struct ListFilters {
+    pub(self) scene_id: Option<String>,
+    pub(self) offset: i64,
+    pub(self) limit: i64,
+}
+
+impl ListFilters {
+    pub(crate) fn new(scene_id: Option<String>, offset: i64, limit: i64) -> Self {
+        Self {
+            scene_id,
+            offset,
+            limit,
+        }
+    }
+}
+
+/// How fast recorded events are replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Timing {
+    /// Events keep the time that passed between them while they were recorded.
+    Original,
+    /// Events follow each other as fast as the scene takes them.
+    Fast,
+}
+
+pub(crate) struct ReplayRequest {
+    /// Scene to replay into. Defaults to the recorded scene.
+    pub scene_id: Option<String>,
+    pub timing: Timing,
+    /// Nodes of the target scene by recorded node ID, for scenes whose source nodes have
+    /// other IDs than the recorded ones. Unmapped nodes keep their ID.
+    pub node_map: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReplayState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Progress of a replay.
+#[derive(Debug, Clone)]
+pub(crate) struct ReplayStatus {
+    pub id: String,
+    pub recording_id: String,
+    pub scene_id: String,
+    pub timing: Timing,
+    pub state: ReplayState,
+    /// Events in the recording.
+    pub total: u64,
+    /// Events routed into the scene so far.
+    pub replayed: u64,
+    /// Events dropped, because the scene queue was full or the payload undecodable.
+    pub dropped: u64,
+    /// Why the replay failed.
+    pub error: Option<String>,
+    pub start_time: DateTime<Local>,
+    pub finish_time: Option<DateTime<Local>>,
+}
+
+struct Replay {
+    status: Mutex<ReplayStatus>,
+    cancel: CancellationToken,
+}
+
+impl Replay {
+    fn status(&self) -> ReplayStatus {
+        self.status.lock().expect("replay lock poisoned").clone()
+    }
+
+    fn update(&self, update: impl FnOnce(&mut ReplayStatus)) {
+        update(&mut self.status.lock().expect("replay lock poisoned"));
+    }
+
+    fn finish(&self, state: ReplayState, error: Option<String>) {
+        self.update(|status| {
+            status.state = state;
+            status.error = error;
+            status.finish_time = Some(Local::now());
+        });
+    }
+}
+
+/// A recording in progress.
+struct Active {
+    recording_id: String,
+    /// Monotonic, so offsets don't jump with the wall clock.
+    started: Instant,
+    seq: i64,
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum ServiceError {
+    #[error("no recording found")]
+    NotFound,
+    #[error("no replay found")]
+    ReplayNotFound,
+    #[error("scene [{0}] not found")]
+    SceneNotFound(String),
+    #[error("scene [{0}] is already recorded")]
+    AlreadyRecording(String),
+    #[error("recording [{0}] is still recording")]
+    StillRecording(String),
+    #[error("scene [{0}] is not running")]
+    NotRunning(String),
+    #[error("invalid input: {0}")]
+    Invalid(String),
+    #[error("scene error: {0}")]
+    Scene(scene::service::ServiceError),
+    #[error("failed to execute query: {0}")]
+    Query(sqlx::Error),
+    #[error("failed to get connection: {0}")]
+    Connection(#[from] DbError),
+}
+
+/// Records the events source plugins emit into a scene, and replays them into a running
+/// scene later, e.g. to check a changed transform against the same input.
+pub(crate) struct Service {
+    db: &'static Database,
+    /// Recordings in progress, by scene ID.
+    active: Mutex<HashMap<String, Active>>,
+    replays: Mutex<HashMap<String, Arc<Replay>>>,
+    tasks: TaskTracker,
+    stop: CancellationToken,
+}
+
+impl Service {
+    pub(crate) fn new(db: &'static Database) -> Self {
+        Self {
+            db,
+            active: Mutex::new(HashMap::new()),
+            replays: Mutex::new(HashMap::new()),
+            tasks: TaskTracker::new(),
+            stop: CancellationToken::new(),
+        }
+    }
+
+    /// Starts recording the source events of a scene. The scene does not need to run: events
+    /// are recorded once it does.
+    pub(crate) async fn start(
+        &self,
+        scene_id: &str,
+        name: Option<String>,
+    ) -> Result<Recording, ServiceError> {
+        match scene::service().await.get_scene(scene_id).await {
+            Ok(_) => {}
+            Err(scene::service::ServiceError::NotFound) => {
+                return Err(ServiceError::SceneNotFound(scene_id.to_string()));
+            }
+            Err(e) => return Err(ServiceError::Scene(e)),
+        }
+        let recording = Recording {
+            id: Ulid::new().to_string(),
+            scene_id: scene_id.to_string(),
+            name,
+            state: RecordingState::Recording,
+            event_count: 0,
+            start_time: Local::now(),
+            stop_time: None,
+        };
+        {
+            let mut active = self.active.lock().expect("recordings lock poisoned");
+            if active.contains_key(scene_id) {
+                return Err(ServiceError::AlreadyRecording(scene_id.to_string()));
+            }
+            active.insert(
+                scene_id.to_string(),
+                Active {
+                    recording_id: recording.id.clone(),
+                    started: Instant::now(),
+                    seq: 0,
+                },
+            );
+        }
+        let result = match self.db {
+            Database::SQLite(db) => match db.get_connection().await {
+                Ok(mut conn) => query_sqlite::insert_recording(&mut conn, &recording)
+                    .await
+                    .map_err(ServiceError::from),
+                Err(e) => Err(ServiceError::from(e)),
+            },
+        };
+        if let Err(e) = result {
+            self.active
+                .lock()
+                .expect("recordings lock poisoned")
+                .remove(scene_id);
+            return Err(e);
+        }
+        log::info!(
+            "Recorder: recording scene [{scene_id}] as [{id}]",
+            id = recording.id
+        );
+        Ok(recording)
+    }
+
+    /// Stops a recording. Stopping a stopped recording changes nothing.
+    pub(crate) async fn stop(&self, id: &str) -> Result<Recording, ServiceError> {
+        self.active
+            .lock()
+            .expect("recordings lock poisoned")
+            .retain(|_, active| active.recording_id != id);
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                query_sqlite::stop_recording(&mut conn, id, Local::now()).await?;
+                let recording = query_sqlite::get_recording(&mut conn, id).await?;
+                log::info!("Recorder: recording [{id}] stopped");
+                Ok(recording)
+            }
+        }
+    }
+
+    pub(crate) async fn get(&self, id: &str) -> Result<Recording, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(query_sqlite::get_recording(&mut conn, id).await?)
+            }
+        }
+    }
+
+    /// Newest recordings first, optionally of one scene only.
+    pub(crate) async fn list(
+        &self,
+        filters: ListFilters,
+    ) -> Result<(Vec<Recording>, bool), ServiceError> {
+        let limit = filters.limit;
+        let input = ListFilters {
+            limit: limit + 1,
+            ..filters
+        };
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                let recordings = query_sqlite::list_recordings(&mut conn, input).await?;
+                let has_more = recordings.len() > limit as usize;
+                Ok((
+                    recordings.into_iter().take(limit as usize).collect(),
+                    has_more,
+                ))
+            }
+        }
+    }
+
+    /// Deletes a stopped recording and its events.
+    pub(crate) async fn delete(&self, id: &str) -> Result<(), ServiceError> {
+        if self.is_recording(id) {
+            return Err(ServiceError::StillRecording(id.to_string()));
+        }
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                match query_sqlite::delete_recording(&mut conn, id).await? {
+                    0 => Err(ServiceError::NotFound),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    fn is_recording(&self, id: &str) -> bool {
+        self.active
+            .lock()
+            .expect("recordings lock poisoned")
+            .values()
+            .any(|active| active.recording_id == id)
+    }
+
+    /// Adds an event a source plugin emitted into the scene to the recording of the scene, if
+    /// it is recorded. A failing write is logged, the event is routed anyway.
+    pub(crate) async fn record(
+        &self,
+        scene_id: &str,
+        node_id: &str,
+        plugin_id: &str,
+        payload: &PluginPayload,
+    ) {
+        let event = {
+            let mut active = self.active.lock().expect("recordings lock poisoned");
+            let Some(active) = active.get_mut(scene_id) else {
+                return;
+            };
+            active.seq += 1;
+            RecordedEvent {
+                recording_id: active.recording_id.clone(),
+                seq: active.seq,
+                node_id: node_id.to_string(),
+                plugin_id: plugin_id.to_string(),
+                payload: payload.encode_to_vec(),
+                offset_micros: i64::try_from(active.started.elapsed().as_micros())
+                    .unwrap_or(i64::MAX),
+            }
+        };
+        let result = match self.db {
+            Database::SQLite(db) => match db.get_connection().await {
+                Ok(mut conn) => query_sqlite::insert_event(&mut conn, &event)
+                    .await
+                    .map_err(ServiceError::from),
+                Err(e) => Err(ServiceError::from(e)),
+            },
+        };
+        if let Err(e) = result {
+            log::error!(
+                "Recorder: failed to record event of scene [{scene_id}] in [{id}]: {e}",
+                id = event.recording_id
+            );
+        }
+    }
+
+    /// Starts replaying a recording into a running scene. Every recorded node must be a node
+    /// of the scene, possibly under another ID given by the node map. Replayed events are
+    /// new events: they get event and trace IDs of their own.
+    pub(crate) async fn replay(
+        &self,
+        recording_id: &str,
+        request: ReplayRequest,
+    ) -> Result<ReplayStatus, ServiceError> {
+        let recording = self.get(recording_id).await?;
+        let scene_id = request.scene_id.unwrap_or(recording.scene_id);
+        let nodes = match scene::service().await.list_nodes(scene_id.as_str()).await {
+            Ok(nodes) => nodes,
+            Err(scene::service::ServiceError::NotFound) => {
+                return Err(ServiceError::SceneNotFound(scene_id));
+            }
+            Err(e) => return Err(ServiceError::Scene(e)),
+        };
+        if !director::service().is_running(scene_id.as_str()).await {
+            return Err(ServiceError::NotRunning(scene_id));
+        }
+        let recorded_nodes = match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                query_sqlite::list_recorded_nodes(&mut conn, recording_id).await?
+            }
+        };
+        let mut targets: HashMap<String, String> = HashMap::new();
+        let mut unmapped = BTreeSet::new();
+        for node_id in recorded_nodes {
+            let target = request.node_map.get(&node_id).unwrap_or(&node_id);
+            match nodes.iter().find(|node| node.id == *target) {
+                Some(node) => {
+                    targets.insert(node_id, node.id.clone());
+                }
+                None => {
+                    unmapped.insert(node_id);
+                }
+            }
+        }
+        if !unmapped.is_empty() {
+            return Err(ServiceError::Invalid(format!(
+                "recorded nodes [{nodes}] are not nodes of scene [{scene_id}], map them to nodes of the scene",
+                nodes = unmapped.into_iter().collect::<Vec<_>>().join(", ")
+            )));
+        }
+        let replay = Arc::new(Replay {
+            status: Mutex::new(ReplayStatus {
+                id: Ulid::new().to_string(),
+                recording_id: recording_id.to_string(),
+                scene_id,
+                timing: request.timing,
+                state: ReplayState::Running,
+                total: recording.event_count as u64,
+                replayed: 0,
+                dropped: 0,
+                error: None,
+                start_time: Local::now(),
+                finish_time: None,
+            }),
+            cancel: self.stop.child_token(),
+        });
+        let status = replay.status();
+        {
+            let mut replays = self.replays.lock().expect("replays lock poisoned");
+            prune_replays(&mut replays);
+            replays.insert(status.id.clone(), replay.clone());
+        }
+        log::info!(
+            "Recorder: replaying recording [{recording_id}] into scene [{scene_id}] as [{id}]",
+            scene_id = status.scene_id,
+            id = status.id
+        );
+        self.tasks.spawn(async move {
+            super::service().await.run_replay(replay, targets).await;
+        });
+        Ok(status)
+    }
+
+    pub(crate) fn get_replay(&self, id: &str) -> Result<ReplayStatus, ServiceError> {
+        self.replays
+            .lock()
+            .expect("replays lock poisoned")
+            .get(id)
+            .map(|replay| replay.status())
+            .ok_or(ServiceError::ReplayNotFound)
+    }
+
+    /// Replays still running and the last ones finished, newest first.
+    pub(crate) fn list_replays(&self) -> Vec<ReplayStatus> {
+        let mut replays: Vec<ReplayStatus> = self
+            .replays
+            .lock()
+            .expect("replays lock poisoned")
+            .values()
+            .map(|replay| replay.status())
+            .collect();
+        replays.sort_by(|a, b| b.id.cmp(&a.id));
+        replays
+    }
+
+    /// Stops a running replay. Events already replayed stay in the scene.
+    pub(crate) fn cancel_replay(&self, id: &str) -> Result<ReplayStatus, ServiceError> {
+        let replay = self
+            .replays
+            .lock()
+            .expect("replays lock poisoned")
+            .get(id)
+            .cloned()
+            .ok_or(ServiceError::ReplayNotFound)?;
+        replay.cancel.cancel();
+        Ok(replay.status())
+    }
+
+    /// Routes the recorded events into the scene, in the order they were recorded, as the
+    /// nodes their recorded nodes map to.
+    async fn run_replay(&self, replay: Arc<Replay>, targets: HashMap<String, String>) {
+        let ReplayStatus {
+            id,
+            recording_id,
+            scene_id,
+            timing,
+            ..
+        } = replay.status();
+        let started = Instant::now();
+        let mut first_offset = None;
+        let mut after_seq = 0;
+        let (state, error) = 'replay: loop {
+            let page = match self.page(recording_id.as_str(), after_seq).await {
+                Ok(page) if page.is_empty() => break (ReplayState::Completed, None),
+                Ok(page) => page,
+                Err(e) => break (ReplayState::Failed, Some(e.to_string())),
+            };
+            for event in page {
+                after_seq = event.seq;
+                if timing == Timing::Original {
+                    let first = *first_offset.get_or_insert(event.offset_micros);
+                    let due = started + replay_delay(first, event.offset_micros);
+                    tokio::select! {
+                        _ = replay.cancel.cancelled() => break 'replay (ReplayState::Cancelled, None),
+                        _ = tokio::time::sleep_until(due) => {}
+                    }
+                }
+                let payload = match PluginPayload::decode(event.payload.as_slice()) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        log::warn!(
+                            "Recorder: replay [{id}]: dropping event [{seq}]: {e}",
+                            seq = event.seq
+                        );
+                        replay.update(|status| status.dropped += 1);
+                        continue;
+                    }
+                };
+                let Some(node_id) = targets.get(event.node_id.as_str()) else {
+                    log::warn!(
+                        "Recorder: replay [{id}]: dropping event [{seq}]: node [{node_id}] is not mapped",
+                        seq = event.seq,
+                        node_id = event.node_id
+                    );
+                    replay.update(|status| status.dropped += 1);
+                    continue;
+                };
+                let injected = tokio::select! {
+                    _ = replay.cancel.cancelled() => break 'replay (ReplayState::Cancelled, None),
+                    injected = director::service().inject(scene_id.as_str(), node_id.as_str(), payload) => injected,
+                };
+                match injected {
+                    Ok(true) => replay.update(|status| status.replayed += 1),
+                    Ok(false) => replay.update(|status| status.dropped += 1),
+                    Err(e) => break 'replay (ReplayState::Failed, Some(e.to_string())),
+                }
+            }
+        };
+        match error.as_ref() {
+            Some(e) => log::error!("Recorder: replay [{id}] failed: {e}"),
+            None => log::info!("Recorder: replay [{id}] finished [{state:?}]"),
+        }
+        replay.finish(state, error);
+    }
+
+    async fn page(
+        &self,
+        recording_id: &str,
+        after_seq: i64,
+    ) -> Result<Vec<RecordedEvent>, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(
+                    query_sqlite::list_events(&mut conn, recording_id, after_seq, REPLAY_PAGE_SIZE)
+                        .await?,
+                )
+            }
+        }
+    }
+
+    /// Marks the recordings that were in progress when the app stopped as stopped. Their
+    /// scenes are not recorded any longer.
+    async fn stop_abandoned(&self) -> Result<(), ServiceError> {
+        let active: Vec<String> = self
+            .active
+            .lock()
+            .expect("recordings lock poisoned")
+            .values()
+            .map(|active| active.recording_id.clone())
+            .collect();
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                let stopped =
+                    query_sqlite::stop_other_recordings(&mut conn, &active, Local::now()).await?;
+                if stopped > 0 {
+                    log::warn!(
+                        "Recorder: stopped [{stopped}] recordings left in progress at the last shutdown"
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// When a recorded event is due in a replay at the original timing, relative to the first
+/// event replayed. Events recorded out of order are due right away.
+fn replay_delay(first_offset_micros: i64, offset_micros: i64) -> Duration {
+    Duration::from_micros(offset_micros.saturating_sub(first_offset_micros).max(0) as u64)
+}
+
+/// Drops the oldest finished replays beyond the history kept.
+fn prune_replays(replays: &mut HashMap<String, Arc<Replay>>) {
+    let mut finished: Vec<String> = replays
+        .iter()
+        .filter(|(_, replay)| replay.status().state != ReplayState::Running)
+        .map(|(id, _)| id.clone())
+        .collect();
+    if finished.len() < REPLAY_HISTORY {
+        return;
+    }
+    finished.sort();
+    for id in finished.iter().take(finished.len() + 1 - REPLAY_HISTORY) {
+        replays.remove(id);
+    }
+}
+
+#[async_trait]
+impl registry::Service for Service {
+    fn id(&self) -> String {
+        "recording-service".to_string()
+    }
+
+    async fn start(&self, shutdown_token: CancellationToken) -> Result<(), RegistryError> {
+        log::debug!("Starting recorder.");
+        if let Err(e) = self.stop_abandoned().await {
+            log::error!("Recorder: failed to stop abandoned recordings: {e}");
+        }
+
+        shutdown_token.cancelled().await;
+        log::debug!("Recorder shutting down.");
+        self.stop.cancel();
+        self.tasks.close();
+        self.tasks.wait().await;
+        let recording_ids: Vec<String> = self
+            .active
+            .lock()
+            .expect("recordings lock poisoned")
+            .values()
+            .map(|active| active.recording_id.clone())
+            .collect();
+        for id in recording_ids {
+            if let Err(e) = self.stop(id.as_str()).await {
+                log::error!("Recorder: failed to stop recording [{id}]: {e}");
+            }
+        }
+        log::debug!("Recorder exited");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_keeps_the_spacing_of_events() {
+        let offsets = [1_500, 1_500, 2_000, 12_000];
+        let delays: Vec<Duration> = offsets
+            .iter()
+            .map(|offset| replay_delay(offsets[0], *offset))
+            .collect();
+        assert_eq!(
+            delays,
+            [0, 0, 500, 10_500].map(Duration::from_micros).to_vec()
+        );
+    }
+
+    #[test]
+    fn replay_never_waits_for_events_before_the_first() {
+        assert_eq!(replay_delay(2_000, 1_000), Duration::ZERO);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::modules::recording::service::{ListFilters, RecordedEvent, Recording, ServiceError};
+use chrono::{DateTime, Local};
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, Executor, FromRow, QueryBuilder, Sqlite};
+
+impl From<sqlx::Error> for ServiceError {
+    fn from(error: sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::RowNotFound => ServiceError::NotFound,
+            _ => ServiceError::Query(error),
+        }
+    }
+}
+
+const SELECT_RECORDINGS: &str = "SELECT r.*, \
+    (SELECT COUNT(*) FROM recorded_events e WHERE e.recording_id = r.id) AS event_count \
+    FROM recordings r";
+
+pub(super) async fn insert_recording(
+    conn: &mut PoolConnection<Sqlite>,
+    recording: &Recording,
+) -> Result<(), sqlx::Error> {
+    conn.execute(
+        sqlx::query(
+            "INSERT INTO recordings (id, scene_id, \"name\", state, start_time) \
+                VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(recording.id.as_str())
+        .bind(recording.scene_id.as_str())
+        .bind(recording.name.as_deref())
+        .bind(recording.state)
+        .bind(recording.start_time),
+    )
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn stop_recording(
+    conn: &mut PoolConnection<Sqlite>,
+    id: &str,
+    stop_time: DateTime<Local>,
+) -> Result<u64, sqlx::Error> {
+    let result = conn
+        .execute(
+            sqlx::query(
+                "UPDATE recordings SET state = 'stopped', stop_time = $1 \
+                    WHERE id = $2 AND state = 'recording'",
+            )
+            .bind(stop_time)
+            .bind(id),
+        )
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Stops the recordings in progress except the listed ones.
+pub(super) async fn stop_other_recordings(
+    conn: &mut PoolConnection<Sqlite>,
+    keep: &[String],
+    stop_time: DateTime<Local>,
+) -> Result<u64, sqlx::Error> {
+    let mut query =
+        QueryBuilder::<Sqlite>::new("UPDATE recordings SET state = 'stopped', stop_time = ");
+    query
+        .push_bind(stop_time)
+        .push(" WHERE state = 'recording'");
+    if !keep.is_empty() {
+        query.push(" AND id NOT IN ");
+        query.push_tuples(keep, |mut row, id| {
+            row.push_bind(id.as_str());
+        });
+    }
+    let result = conn.execute(query.build()).await?;
+    Ok(result.rows_affected())
+}
+
+pub(super) async fn get_recording(
+    conn: &mut PoolConnection<Sqlite>,
+    id: &str,
+) -> Result<Recording, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, Recording>(
+                format!("{SELECT_RECORDINGS} WHERE r.id = $1").as_str(),
+            )
+            .bind(id),
+        )
+        .await?;
+    Recording::from_row(&row)
+}
+
+pub(super) async fn list_recordings(
+    conn: &mut PoolConnection<Sqlite>,
+    filters: ListFilters,
+) -> Result<Vec<Recording>, sqlx::Error> {
+    let mut query = QueryBuilder::<Sqlite>::new(SELECT_RECORDINGS);
+    if let Some(scene_id) = filters.scene_id {
+        query.push(" WHERE r.scene_id = ").push_bind(scene_id);
+    }
+    query
+        .push(" ORDER BY r.start_time DESC, r.id DESC LIMIT ")
+        .push_bind(filters.limit)
+        .push(" OFFSET ")
+        .push_bind(filters.offset);
+    let rows = conn.fetch_all(query.build()).await?;
+    let mut recordings = vec![];
+    for row in rows {
+        recordings.push(Recording::from_row(&row)?);
+    }
+
+    Ok(recordings)
+}
+
+pub(super) async fn delete_recording(
+    conn: &mut PoolConnection<Sqlite>,
+    id: &str,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = conn.begin().await?;
+    tx.execute(sqlx::query("DELETE FROM recorded_events WHERE recording_id = $1").bind(id))
+        .await?;
+    let result = tx
+        .execute(sqlx::query("DELETE FROM recordings WHERE id = $1").bind(id))
+        .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected())
+}
+
+pub(super) async fn insert_event(
+    conn: &mut PoolConnection<Sqlite>,
+    event: &RecordedEvent,
+) -> Result<(), sqlx::Error> {
+    conn.execute(
+        sqlx::query(
+            "INSERT INTO recorded_events (recording_id, seq, node_id, plugin_id, payload, offset_micros) \
+                VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(event.recording_id.as_str())
+        .bind(event.seq)
+        .bind(event.node_id.as_str())
+        .bind(event.plugin_id.as_str())
+        .bind(event.payload.as_slice())
+        .bind(event.offset_micros),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Events of the recording after the given one, in recorded order.
+pub(super) async fn list_events(
+    conn: &mut PoolConnection<Sqlite>,
+    recording_id: &str,
+    after_seq: i64,
+    limit: i64,
+) -> Result<Vec<RecordedEvent>, sqlx::Error> {
+    let rows = conn
+        .fetch_all(
+            sqlx::query_as::<Sqlite, RecordedEvent>(
+                "SELECT * FROM recorded_events WHERE recording_id = $1 AND seq > $2 \
+                    ORDER BY seq LIMIT $3",
+            )
+            .bind(recording_id)
+            .bind(after_seq)
+            .bind(limit),
+        )
+        .await?;
+    let mut events = Vec::with_capacity(rows.len());
+    for row in rows.iter() {
+        events.push(RecordedEvent::from_row(row)?);
+    }
+    Ok(events)
+}
+
+/// Source nodes the events of the recording were emitted by.
+pub(super) async fn list_recorded_nodes(
+    conn: &mut PoolConnection<Sqlite>,
+    recording_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<Sqlite, String>(
+        "SELECT DISTINCT node_id FROM recorded_events WHERE recording_id = $1",
+    )
+    .bind(recording_id)
+    .fetch_all(&mut **conn)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn connection() -> PoolConnection<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        conn.execute(sqlx::raw_sql(include_str!(
+            "../../../../resources/migrations/sqlite/recordings/1.0.0-1.up.sql"
+        )))
+        .await
+        .unwrap();
+        conn
+    }
+
+    fn event(recording_id: &str, seq: i64) -> RecordedEvent {
+        RecordedEvent {
+            recording_id: recording_id.to_string(),
+            seq,
+            node_id: "node".to_string(),
+            plugin_id: "plugin".to_string(),
+            payload: vec![],
+            offset_micros: seq * 1_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn events_page_in_recorded_order() {
+        let mut conn = connection().await;
+        for seq in [3, 1, 5, 2, 4] {
+            insert_event(&mut conn, &event("recording", seq))
+                .await
+                .unwrap();
+        }
+        insert_event(&mut conn, &event("other", 6)).await.unwrap();
+
+        let mut replayed = vec![];
+        let mut after_seq = 0;
+        loop {
+            let page = list_events(&mut conn, "recording", after_seq, 2)
+                .await
+                .unwrap();
+            let Some(last) = page.last() else {
+                break;
+            };
+            after_seq = last.seq;
+            replayed.extend(page.iter().map(|event| event.seq));
+        }
+        assert_eq!(replayed, [1, 2, 3, 4, 5]);
+    }
+}
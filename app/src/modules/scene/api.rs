@@ -1,17 +1,26 @@
-use crate::modules::scene;
-use crate::modules::scene::service::ServiceError;
+use crate::modules::director::service::{DirectorError, PauseState};
+use crate::modules::hub::compat::{Mismatch, SchemaDiff};
+use crate::modules::hub::session::Reconfiguration;
+use crate::modules::scene::service::{NodeType, SceneState, ServiceError};
+use crate::modules::{director, scene};
 use axum::extract::{Path, Query};
 use axum::http::StatusCode;
-use axum::routing::get;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
 use axum::{Json, Router};
 use chrono::Local;
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json as DbJson;
 use utoipa::{IntoParams, OpenApi, ToSchema};
 
 #[derive(Serialize, Deserialize, ToSchema)]
 pub(crate) struct Scene {
     pub id: String,
     pub name: String,
+    pub state: State,
+    /// Why the scene entered its current state, e.g. the error it failed with.
+    pub state_reason: Option<String>,
+    pub state_time: Option<i64>,
     pub create_time: i64,
     pub update_time: i64,
 }
@@ -21,6 +30,9 @@ impl From<scene::service::Scene> for Scene {
         Self {
             id: value.id,
             name: value.name,
+            state: State::from(value.state),
+            state_reason: value.state_reason,
+            state_time: value.state_time.map(|time| time.timestamp_millis()),
             create_time: value.create_time.timestamp_millis(),
             update_time: value.update_time.timestamp_millis(),
         }
@@ -37,6 +49,9 @@ impl Into<scene::service::Scene> for SceneRequest {
         scene::service::Scene {
             id: "".to_string(),
             name: self.name,
+            state: SceneState::Draft,
+            state_reason: None,
+            state_time: None,
             create_time: Local::now(),
             update_time: Local::now(),
         }
@@ -209,6 +224,146 @@ pub(crate) async fn delete_scene(Path(id): Path<String>) -> Result<StatusCode, S
     }
 }
 
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum State {
+    Draft,
+    Starting,
+    Running,
+    Paused,
+    Stopping,
+    Stopped,
+    Failed,
+}
+
+impl From<SceneState> for State {
+    fn from(value: SceneState) -> Self {
+        match value {
+            SceneState::Draft => State::Draft,
+            SceneState::Starting => State::Starting,
+            SceneState::Running => State::Running,
+            SceneState::Paused => State::Paused,
+            SceneState::Stopping => State::Stopping,
+            SceneState::Stopped => State::Stopped,
+            SceneState::Failed => State::Failed,
+        }
+    }
+}
+
+fn lifecycle_status(action: &str, id: &str, e: DirectorError) -> StatusCode {
+    match e {
+        DirectorError::Scene(ServiceError::NotFound) => {
+            log::trace!("Scenes API: Failed to {action} scene [{id}]: not found");
+            StatusCode::NOT_FOUND
+        }
+        DirectorError::Scene(ServiceError::InvalidTransition { .. })
+        | DirectorError::Scene(ServiceError::Conflict) => {
+            log::trace!("Scenes API: Failed to {action} scene [{id}]: {e}");
+            StatusCode::CONFLICT
+        }
+        _ => {
+            log::error!("Scenes API: Failed to {action} scene [{id}]: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/start",
+    operation_id = "start-scene",
+    description = "Start a scene, or resume it if it is paused. A scene that fails to start is left in the failed state with the reason recorded",
+    summary = "Start scene",
+    responses(
+        (status = 200, description = "Running scene", body = Scene),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Scene cannot be started from its current state"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene to start")
+    )
+)]
+async fn start_scene(Path(id): Path<String>) -> Result<Json<Scene>, StatusCode> {
+    log::trace!("Scenes API: starting scene [{id}]");
+    match director::service().start(id.as_str()).await {
+        Ok(scene) => Ok(Json(Scene::from(scene))),
+        Err(e) => Err(lifecycle_status("start", id.as_str(), e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/stop",
+    operation_id = "stop-scene",
+    description = "Stop a running or paused scene and its plugins",
+    summary = "Stop scene",
+    responses(
+        (status = 200, description = "Stopped scene", body = Scene),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Scene cannot be stopped from its current state"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene to stop")
+    )
+)]
+async fn stop_scene(Path(id): Path<String>) -> Result<Json<Scene>, StatusCode> {
+    log::trace!("Scenes API: stopping scene [{id}]");
+    match director::service().stop(id.as_str()).await {
+        Ok(scene) => Ok(Json(Scene::from(scene))),
+        Err(e) => Err(lifecycle_status("stop", id.as_str(), e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/pause",
+    operation_id = "pause-scene",
+    description = "Pause a running scene. Its plugins keep running, but sources stop emitting and events wait on their edges until the scene is resumed",
+    summary = "Pause scene",
+    responses(
+        (status = 200, description = "Paused scene", body = Scene),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Scene is not running"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene to pause")
+    )
+)]
+async fn pause_scene(Path(id): Path<String>) -> Result<Json<Scene>, StatusCode> {
+    log::trace!("Scenes API: pausing scene [{id}]");
+    match director::service().pause(id.as_str()).await {
+        Ok(scene) => Ok(Json(Scene::from(scene))),
+        Err(e) => Err(lifecycle_status("pause", id.as_str(), e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/resume",
+    operation_id = "resume-scene",
+    description = "Resume a paused scene. Nodes paused on their own stay paused",
+    summary = "Resume scene",
+    responses(
+        (status = 200, description = "Running scene", body = Scene),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Scene is not paused"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene to resume")
+    )
+)]
+async fn resume_scene(Path(id): Path<String>) -> Result<Json<Scene>, StatusCode> {
+    log::trace!("Scenes API: resuming scene [{id}]");
+    match director::service().resume(id.as_str()).await {
+        Ok(scene) => Ok(Json(Scene::from(scene))),
+        Err(e) => Err(lifecycle_status("resume", id.as_str(), e)),
+    }
+}
+
 const DEFAULT_LIMIT: u32 = 50;
 
 #[derive(Deserialize, IntoParams, Clone)]
@@ -279,11 +434,876 @@ async fn list_scenes(
     }
 }
 
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy)]
+pub(crate) enum Type {
+    #[serde(rename = "source")]
+    Source,
+    #[serde(rename = "transform")]
+    Transform,
+    #[serde(rename = "sink")]
+    Sink,
+}
+
+impl From<NodeType> for Type {
+    fn from(value: NodeType) -> Self {
+        match value {
+            NodeType::Source => Type::Source,
+            NodeType::Transform => Type::Transform,
+            NodeType::Sink => Type::Sink,
+        }
+    }
+}
+
+impl From<Type> for NodeType {
+    fn from(value: Type) -> Self {
+        match value {
+            Type::Source => NodeType::Source,
+            Type::Transform => NodeType::Transform,
+            Type::Sink => NodeType::Sink,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct Node {
+    pub id: String,
+    pub scene_id: String,
+    pub node_type: Type,
+    pub plugin_id: String,
+    pub plugin_version: String,
+    #[schema(value_type = Object)]
+    pub config: serde_json::Value,
+    pub create_time: i64,
+    pub update_time: i64,
+    /// How the running plugin of the node took an edited config, if it was pushed to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconfiguration: Option<NodeReconfiguration>,
+}
+
+impl From<scene::service::Node> for Node {
+    fn from(value: scene::service::Node) -> Self {
+        Self {
+            id: value.id,
+            scene_id: value.scene_id,
+            node_type: Type::from(value.node_type),
+            plugin_id: value.plugin_id,
+            plugin_version: value.plugin_version,
+            config: value.config.0,
+            create_time: value.create_time.timestamp_millis(),
+            update_time: value.update_time.timestamp_millis(),
+            reconfiguration: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) enum ReconfigurationStatus {
+    #[serde(rename = "applied")]
+    Applied,
+    #[serde(rename = "rejected")]
+    Rejected,
+    #[serde(rename = "invalid")]
+    Invalid,
+    #[serde(rename = "unsupported")]
+    Unsupported,
+    #[serde(rename = "failed")]
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct NodeReconfiguration {
+    pub status: ReconfigurationStatus,
+    pub message: Option<String>,
+}
+
+impl NodeReconfiguration {
+    fn new(status: ReconfigurationStatus, message: String) -> Self {
+        Self {
+            status,
+            message: Some(message),
+        }
+    }
+}
+
+impl From<Reconfiguration> for NodeReconfiguration {
+    fn from(value: Reconfiguration) -> Self {
+        match value {
+            Reconfiguration::Applied => Self {
+                status: ReconfigurationStatus::Applied,
+                message: None,
+            },
+            Reconfiguration::Rejected(message) => {
+                Self::new(ReconfigurationStatus::Rejected, message)
+            }
+            Reconfiguration::Unsupported(message) => {
+                Self::new(ReconfigurationStatus::Unsupported, message)
+            }
+            Reconfiguration::Failed(message) => Self::new(ReconfigurationStatus::Failed, message),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct NodePause {
+    pub node_id: String,
+    /// Whether the node gets no events, because it or its scene is paused.
+    pub paused: bool,
+    /// Whether the plugin of the node was told. Plugins that are not connected or predate
+    /// pausing are paused by the runtime alone: they get no events, and sources stop once
+    /// they run out of credit.
+    pub notified: bool,
+}
+
+impl NodePause {
+    fn new(node_id: String, state: PauseState) -> Self {
+        Self {
+            node_id,
+            paused: state.paused,
+            notified: state.notified,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct NodeRequest {
+    pub node_type: Type,
+    /// Name of the catalog plugin, or a `name@version` reference.
+    pub plugin_id: String,
+    /// Version of the catalog plugin. Defaults to the latest registered version.
+    #[serde(default)]
+    pub plugin_version: String,
+    #[schema(value_type = Option<Object>)]
+    pub config: Option<serde_json::Value>,
+}
+
+impl NodeRequest {
+    fn into_node(self, scene_id: String, id: String) -> scene::service::Node {
+        scene::service::Node {
+            id,
+            scene_id,
+            node_type: self.node_type.into(),
+            plugin_id: self.plugin_id,
+            plugin_version: self.plugin_version,
+            config: DbJson(
+                self.config
+                    .unwrap_or_else(|| serde_json::Value::Object(Default::default())),
+            ),
+            create_time: Local::now(),
+            update_time: Local::now(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ListNodesResponse {
+    nodes: Vec<Node>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct Edge {
+    pub id: String,
+    pub scene_id: String,
+    pub from_node_id: String,
+    pub to_node_id: String,
+    pub create_time: i64,
+}
+
+impl From<scene::service::Edge> for Edge {
+    fn from(value: scene::service::Edge) -> Self {
+        Self {
+            id: value.id,
+            scene_id: value.scene_id,
+            from_node_id: value.from_node_id,
+            to_node_id: value.to_node_id,
+            create_time: value.create_time.timestamp_millis(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct EdgeRequest {
+    pub from_node_id: String,
+    pub to_node_id: String,
+    /// Create the edge even if the plugin schemas do not match, logging a warning instead.
+    #[serde(default)]
+    pub allow_incompatible: bool,
+}
+
+impl EdgeRequest {
+    fn into_edge(self, scene_id: String) -> scene::service::Edge {
+        scene::service::Edge {
+            id: "".to_string(),
+            scene_id,
+            from_node_id: self.from_node_id,
+            to_node_id: self.to_node_id,
+            create_time: Local::now(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ListEdgesResponse {
+    edges: Vec<Edge>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) enum MismatchKind {
+    #[serde(rename = "missing_field")]
+    MissingField,
+    #[serde(rename = "type_mismatch")]
+    TypeMismatch,
+    #[serde(rename = "missing_nested_type")]
+    MissingNestedType,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct SchemaMismatch {
+    pub path: String,
+    pub kind: MismatchKind,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+    pub message: String,
+}
+
+impl From<&Mismatch> for SchemaMismatch {
+    fn from(value: &Mismatch) -> Self {
+        let (kind, expected, actual) = match value {
+            Mismatch::MissingField { expected, .. } => (
+                MismatchKind::MissingField,
+                Some(expected.as_str_name().to_string()),
+                None,
+            ),
+            Mismatch::WrongType {
+                expected, actual, ..
+            } => (
+                MismatchKind::TypeMismatch,
+                Some(expected.as_str_name().to_string()),
+                Some(actual.as_str_name().to_string()),
+            ),
+            Mismatch::MissingNestedType { .. } => (MismatchKind::MissingNestedType, None, None),
+        };
+        Self {
+            path: value.path().to_string(),
+            kind,
+            expected,
+            actual,
+            message: value.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct EdgeCompatibility {
+    /// Whether both plugins announced their schemas, so they could be compared at all.
+    pub checked: bool,
+    pub compatible: bool,
+    pub mismatches: Vec<SchemaMismatch>,
+}
+
+impl From<Option<SchemaDiff>> for EdgeCompatibility {
+    fn from(value: Option<SchemaDiff>) -> Self {
+        match value {
+            None => Self {
+                checked: false,
+                compatible: true,
+                mismatches: vec![],
+            },
+            Some(diff) => Self {
+                checked: true,
+                compatible: diff.is_compatible(),
+                mismatches: diff.mismatches.iter().map(From::from).collect(),
+            },
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/by-id/{id}/nodes",
+    operation_id = "list-scene-nodes",
+    description = "List nodes of a scene graph",
+    summary = "List scene nodes",
+    responses(
+        (status = 200, description = "Scene nodes", body = ListNodesResponse),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene")
+    )
+)]
+async fn list_nodes(Path(id): Path<String>) -> Result<Json<ListNodesResponse>, StatusCode> {
+    log::trace!("Scenes API: listing nodes of scene [{id}]");
+    match scene::service().await.list_nodes(id.as_str()).await {
+        Ok(nodes) => Ok(Json(ListNodesResponse {
+            nodes: nodes.into_iter().map(From::from).collect(),
+        })),
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Scenes API: Failed to list nodes of scene [{id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            _ => {
+                log::error!("Scenes API: Failed to list nodes of scene [{id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/nodes",
+    operation_id = "create-scene-node",
+    description = "Add a node to a scene graph",
+    summary = "Create scene node",
+    request_body(
+        content = NodeRequest,
+        description = "Node creation request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Created node", body = Node),
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene")
+    )
+)]
+async fn create_node(
+    Path(id): Path<String>,
+    Json(node): Json<NodeRequest>,
+) -> Result<Json<Node>, StatusCode> {
+    log::trace!("Scenes API: creating node in scene [{id}]");
+    let input = node.into_node(id.clone(), "".to_string());
+    match scene::service().await.create_node(input).await {
+        Ok(node) => {
+            log::trace!("Scenes API: created node [{node_id}]", node_id = node.id);
+            Ok(Json(Node::from(node)))
+        }
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Scenes API: Failed to create node in scene [{id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            ServiceError::Invalid(_) => {
+                log::trace!("Scenes API: Failed to create node in scene [{id}]: {e}");
+                Err(StatusCode::BAD_REQUEST)
+            }
+            _ => {
+                log::error!("Scenes API: Failed to create node in scene [{id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/by-id/{id}/nodes/by-id/{node_id}",
+    operation_id = "get-scene-node",
+    description = "Get a node of a scene graph by ID",
+    summary = "Get scene node by ID",
+    responses(
+        (status = 200, description = "Scene node", body = Node),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene"),
+        ("node_id" = String, Path, description = "ID of the node to fetch")
+    )
+)]
+async fn get_node(Path((id, node_id)): Path<(String, String)>) -> Result<Json<Node>, StatusCode> {
+    log::trace!("Scenes API: getting node [{node_id}] of scene [{id}]");
+    match scene::service()
+        .await
+        .get_node(id.as_str(), node_id.as_str())
+        .await
+    {
+        Ok(node) => Ok(Json(Node::from(node))),
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Scenes API: Failed to get node [{node_id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            _ => {
+                log::error!("Scenes API: Failed to get node [{node_id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/by-id/{id}/nodes/by-id/{node_id}",
+    operation_id = "update-scene-node",
+    description = "Update a node of a scene graph by ID. If the scene is running, an edited \
+        config is checked against the options the plugin of the node declares and pushed to the \
+        running plugin, which may reject it; the node is only updated if it does not.",
+    summary = "Update scene node by ID",
+    request_body(
+        content = NodeRequest,
+        description = "Node update request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Updated node", body = Node),
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Running plugin rejected the config", body = NodeReconfiguration),
+        (status = 422, description = "Config does not fit the plugin options", body = NodeReconfiguration),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene"),
+        ("node_id" = String, Path, description = "ID of the node to update")
+    )
+)]
+async fn update_node(
+    Path((id, node_id)): Path<(String, String)>,
+    Json(node): Json<NodeRequest>,
+) -> Result<Json<Node>, Response> {
+    log::trace!("Scenes API: updating node [{node_id}] of scene [{id}]");
+    let input = node.into_node(id.clone(), node_id.clone());
+    match director::service().update_node(input).await {
+        Ok((node, reconfiguration)) => Ok(Json(Node {
+            reconfiguration: reconfiguration.map(NodeReconfiguration::from),
+            ..Node::from(node)
+        })),
+        Err(e) => match e {
+            DirectorError::Scene(ServiceError::NotFound) => {
+                log::trace!("Scenes API: Failed to update node [{node_id}]: not found");
+                Err(StatusCode::NOT_FOUND.into_response())
+            }
+            DirectorError::Scene(ServiceError::Invalid(_)) => {
+                log::trace!("Scenes API: Failed to update node [{node_id}]: {e}");
+                Err(StatusCode::BAD_REQUEST.into_response())
+            }
+            DirectorError::InvalidConfig(message) => {
+                log::trace!("Scenes API: Failed to update node [{node_id}]: {message}");
+                Err((
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(NodeReconfiguration::new(
+                        ReconfigurationStatus::Invalid,
+                        message,
+                    )),
+                )
+                    .into_response())
+            }
+            DirectorError::ConfigRejected(_, message) => {
+                log::trace!("Scenes API: Failed to update node [{node_id}]: {message}");
+                Err((
+                    StatusCode::CONFLICT,
+                    Json(NodeReconfiguration::new(
+                        ReconfigurationStatus::Rejected,
+                        message,
+                    )),
+                )
+                    .into_response())
+            }
+            _ => {
+                log::error!("Scenes API: Failed to update node [{node_id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+            }
+        },
+    }
+}
+
+fn node_pause_status(action: &str, node_id: &str, e: DirectorError) -> StatusCode {
+    match e {
+        DirectorError::Scene(ServiceError::NotFound) => {
+            log::trace!("Scenes API: Failed to {action} node [{node_id}]: not found");
+            StatusCode::NOT_FOUND
+        }
+        DirectorError::NotRunning(_) => {
+            log::trace!("Scenes API: Failed to {action} node [{node_id}]: {e}");
+            StatusCode::CONFLICT
+        }
+        _ => {
+            log::error!("Scenes API: Failed to {action} node [{node_id}]: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/nodes/by-id/{node_id}/pause",
+    operation_id = "pause-scene-node",
+    description = "Pause a node of a running scene, e.g. for maintenance on the API a sink writes to. \
+        A paused source stops emitting; a paused sink or transform gets the chance to flush and is \
+        delivered no events, which wait on its edges. The rest of the scene keeps running until it \
+        backs up. Pauses last until the node is resumed or the scene stops",
+    summary = "Pause scene node",
+    responses(
+        (status = 200, description = "Pause state of the node", body = NodePause),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Scene is not running"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene"),
+        ("node_id" = String, Path, description = "ID of the node to pause")
+    )
+)]
+async fn pause_node(
+    Path((id, node_id)): Path<(String, String)>,
+) -> Result<Json<NodePause>, StatusCode> {
+    log::trace!("Scenes API: pausing node [{node_id}] of scene [{id}]");
+    match director::service()
+        .pause_node(id.as_str(), node_id.as_str(), true)
+        .await
+    {
+        Ok(state) => Ok(Json(NodePause::new(node_id, state))),
+        Err(e) => Err(node_pause_status("pause", node_id.as_str(), e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/nodes/by-id/{node_id}/resume",
+    operation_id = "resume-scene-node",
+    description = "Resume a paused node of a running scene. The node stays paused while its scene is",
+    summary = "Resume scene node",
+    responses(
+        (status = 200, description = "Pause state of the node", body = NodePause),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Scene is not running"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene"),
+        ("node_id" = String, Path, description = "ID of the node to resume")
+    )
+)]
+async fn resume_node(
+    Path((id, node_id)): Path<(String, String)>,
+) -> Result<Json<NodePause>, StatusCode> {
+    log::trace!("Scenes API: resuming node [{node_id}] of scene [{id}]");
+    match director::service()
+        .pause_node(id.as_str(), node_id.as_str(), false)
+        .await
+    {
+        Ok(state) => Ok(Json(NodePause::new(node_id, state))),
+        Err(e) => Err(node_pause_status("resume", node_id.as_str(), e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/by-id/{id}/nodes/by-id/{node_id}",
+    operation_id = "delete-scene-node",
+    description = "Delete a node of a scene graph by ID, along with its edges",
+    summary = "Delete scene node by ID",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene"),
+        ("node_id" = String, Path, description = "ID of the node to delete")
+    )
+)]
+async fn delete_node(
+    Path((id, node_id)): Path<(String, String)>,
+) -> Result<StatusCode, StatusCode> {
+    log::trace!("Scenes API: deleting node [{node_id}] of scene [{id}]");
+    match scene::service()
+        .await
+        .delete_node(id.as_str(), node_id.as_str())
+        .await
+    {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Scenes API: Failed to delete node [{node_id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            _ => {
+                log::error!("Scenes API: Failed to delete node [{node_id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/by-id/{id}/edges",
+    operation_id = "list-scene-edges",
+    description = "List edges of a scene graph",
+    summary = "List scene edges",
+    responses(
+        (status = 200, description = "Scene edges", body = ListEdgesResponse),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene")
+    )
+)]
+async fn list_edges(Path(id): Path<String>) -> Result<Json<ListEdgesResponse>, StatusCode> {
+    log::trace!("Scenes API: listing edges of scene [{id}]");
+    match scene::service().await.list_edges(id.as_str()).await {
+        Ok(edges) => Ok(Json(ListEdgesResponse {
+            edges: edges.into_iter().map(From::from).collect(),
+        })),
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Scenes API: Failed to list edges of scene [{id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            _ => {
+                log::error!("Scenes API: Failed to list edges of scene [{id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/edges",
+    operation_id = "create-scene-edge",
+    description = "Connect two nodes of a scene graph",
+    summary = "Create scene edge",
+    request_body(
+        content = EdgeRequest,
+        description = "Edge creation request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Created edge", body = Edge),
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Conflict"),
+        (status = 422, description = "Plugin schemas are incompatible", body = EdgeCompatibility),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene")
+    )
+)]
+async fn create_edge(
+    Path(id): Path<String>,
+    Json(edge): Json<EdgeRequest>,
+) -> Result<Json<Edge>, Response> {
+    log::trace!("Scenes API: creating edge in scene [{id}]");
+    let allow_incompatible = edge.allow_incompatible;
+    match scene::service()
+        .await
+        .create_edge(edge.into_edge(id.clone()), allow_incompatible)
+        .await
+    {
+        Ok(edge) => {
+            log::trace!("Scenes API: created edge [{edge_id}]", edge_id = edge.id);
+            Ok(Json(Edge::from(edge)))
+        }
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Scenes API: Failed to create edge in scene [{id}]: not found");
+                Err(StatusCode::NOT_FOUND.into_response())
+            }
+            ServiceError::Conflict => {
+                log::trace!("Scenes API: Failed to create edge in scene [{id}]: conflict");
+                Err(StatusCode::CONFLICT.into_response())
+            }
+            ServiceError::Invalid(_) => {
+                log::trace!("Scenes API: Failed to create edge in scene [{id}]: {e}");
+                Err(StatusCode::BAD_REQUEST.into_response())
+            }
+            ServiceError::Incompatible(diff) => {
+                log::trace!("Scenes API: Failed to create edge in scene [{id}]: {diff}");
+                Err((
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(EdgeCompatibility::from(Some(diff))),
+                )
+                    .into_response())
+            }
+            _ => {
+                log::error!("Scenes API: Failed to create edge in scene [{id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/by-id/{id}/edges/check",
+    operation_id = "check-scene-edge",
+    description = "Check whether two nodes of a scene graph can be connected, comparing the \
+        schemas their plugins announced, without creating the edge",
+    summary = "Check scene edge",
+    request_body(
+        content = EdgeRequest,
+        description = "Edge to check",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Compatibility report", body = EdgeCompatibility),
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene")
+    )
+)]
+async fn check_edge(
+    Path(id): Path<String>,
+    Json(edge): Json<EdgeRequest>,
+) -> Result<Json<EdgeCompatibility>, StatusCode> {
+    log::trace!("Scenes API: checking edge in scene [{id}]");
+    match scene::service()
+        .await
+        .check_edge(&edge.into_edge(id.clone()))
+        .await
+    {
+        Ok(diff) => Ok(Json(EdgeCompatibility::from(diff))),
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Scenes API: Failed to check edge in scene [{id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            ServiceError::Invalid(_) => {
+                log::trace!("Scenes API: Failed to check edge in scene [{id}]: {e}");
+                Err(StatusCode::BAD_REQUEST)
+            }
+            _ => {
+                log::error!("Scenes API: Failed to check edge in scene [{id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/by-id/{id}/edges/by-id/{edge_id}",
+    operation_id = "get-scene-edge",
+    description = "Get an edge of a scene graph by ID",
+    summary = "Get scene edge by ID",
+    responses(
+        (status = 200, description = "Scene edge", body = Edge),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene"),
+        ("edge_id" = String, Path, description = "ID of the edge to fetch")
+    )
+)]
+async fn get_edge(Path((id, edge_id)): Path<(String, String)>) -> Result<Json<Edge>, StatusCode> {
+    log::trace!("Scenes API: getting edge [{edge_id}] of scene [{id}]");
+    match scene::service()
+        .await
+        .get_edge(id.as_str(), edge_id.as_str())
+        .await
+    {
+        Ok(edge) => Ok(Json(Edge::from(edge))),
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Scenes API: Failed to get edge [{edge_id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            _ => {
+                log::error!("Scenes API: Failed to get edge [{edge_id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/by-id/{id}/edges/by-id/{edge_id}",
+    operation_id = "delete-scene-edge",
+    description = "Delete an edge of a scene graph by ID",
+    summary = "Delete scene edge by ID",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal Server Error"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the scene"),
+        ("edge_id" = String, Path, description = "ID of the edge to delete")
+    )
+)]
+async fn delete_edge(
+    Path((id, edge_id)): Path<(String, String)>,
+) -> Result<StatusCode, StatusCode> {
+    log::trace!("Scenes API: deleting edge [{edge_id}] of scene [{id}]");
+    match scene::service()
+        .await
+        .delete_edge(id.as_str(), edge_id.as_str())
+        .await
+    {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(e) => match e {
+            ServiceError::NotFound => {
+                log::trace!("Scenes API: Failed to delete edge [{edge_id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            _ => {
+                log::error!("Scenes API: Failed to delete edge [{edge_id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
 #[derive(OpenApi)]
 #[openapi(
     info(title = "Scenes", description = "Scenes API",),
-    paths(list_scenes, create_scene, get_scene, update_scene, delete_scene,),
-    components(schemas(Scene, SceneRequest, ListScenesResponse,))
+    paths(
+        list_scenes,
+        create_scene,
+        get_scene,
+        update_scene,
+        delete_scene,
+        start_scene,
+        stop_scene,
+        pause_scene,
+        resume_scene,
+        list_nodes,
+        create_node,
+        get_node,
+        update_node,
+        pause_node,
+        resume_node,
+        delete_node,
+        list_edges,
+        create_edge,
+        check_edge,
+        get_edge,
+        delete_edge,
+    ),
+    components(schemas(
+        Scene,
+        State,
+        SceneRequest,
+        ListScenesResponse,
+        Type,
+        Node,
+        NodeRequest,
+        ReconfigurationStatus,
+        NodeReconfiguration,
+        NodePause,
+        ListNodesResponse,
+        Edge,
+        EdgeRequest,
+        ListEdgesResponse,
+        MismatchKind,
+        SchemaMismatch,
+        EdgeCompatibility,
+    ))
 )]
 pub(crate) struct Api;
 
@@ -295,5 +1315,34 @@ impl Api {
                 "/scenes/by-id/{id}",
                 get(get_scene).put(update_scene).delete(delete_scene),
             )
+            .route("/scenes/by-id/{id}/start", post(start_scene))
+            .route("/scenes/by-id/{id}/stop", post(stop_scene))
+            .route("/scenes/by-id/{id}/pause", post(pause_scene))
+            .route("/scenes/by-id/{id}/resume", post(resume_scene))
+            .route(
+                "/scenes/by-id/{id}/nodes",
+                get(list_nodes).post(create_node),
+            )
+            .route(
+                "/scenes/by-id/{id}/nodes/by-id/{node_id}",
+                get(get_node).put(update_node).delete(delete_node),
+            )
+            .route(
+                "/scenes/by-id/{id}/nodes/by-id/{node_id}/pause",
+                post(pause_node),
+            )
+            .route(
+                "/scenes/by-id/{id}/nodes/by-id/{node_id}/resume",
+                post(resume_node),
+            )
+            .route(
+                "/scenes/by-id/{id}/edges",
+                get(list_edges).post(create_edge),
+            )
+            .route("/scenes/by-id/{id}/edges/check", post(check_edge))
+            .route(
+                "/scenes/by-id/{id}/edges/by-id/{edge_id}",
+                get(get_edge).delete(delete_edge),
+            )
     }
 }
@@ -1,7 +1,13 @@
 mod query_sqlite;
 
-use flwrs_core::db::{Database, DbError};
+use crate::modules::hub::compat;
+use crate::modules::hub::compat::SchemaDiff;
+use crate::modules::{activity, catalog, hub};
 use chrono::Local;
+use flwrs_core::db::{Database, DbError};
+use flwrs_plugin::schema::schema::SchemaDefinition;
+use sqlx::types::Json;
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
 use ulid::Ulid;
 
@@ -9,16 +15,108 @@ use ulid::Ulid;
 pub(crate) struct Scene {
     pub id: String,
     pub name: String,
+    pub state: SceneState,
+    /// Why the scene entered its current state, e.g. the error a failed scene stopped with.
+    pub state_reason: Option<String>,
+    pub state_time: Option<chrono::DateTime<Local>>,
+    pub create_time: chrono::DateTime<Local>,
+    pub update_time: chrono::DateTime<Local>,
+}
+
+/// Runtime state of a scene. New scenes are drafts; the director moves them through
+/// `starting → running ⇄ paused → stopping → stopped`, or to `failed` when anything goes
+/// wrong on the way.
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub(crate) enum SceneState {
+    Draft,
+    Starting,
+    Running,
+    Paused,
+    Stopping,
+    Stopped,
+    Failed,
+}
+
+impl SceneState {
+    pub(crate) fn can_transition(self, to: SceneState) -> bool {
+        use SceneState::*;
+        matches!(
+            (self, to),
+            (Draft | Stopped | Failed, Starting)
+                | (Starting, Running | Stopping | Failed)
+                | (Running, Paused | Stopping | Failed)
+                | (Paused, Running | Stopping | Failed)
+                | (Stopping, Stopped | Failed)
+        )
+    }
+
+    /// Whether the scene should be running, i.e. brought back up when the app restarts.
+    pub(crate) fn is_active(self) -> bool {
+        matches!(
+            self,
+            SceneState::Starting | SceneState::Running | SceneState::Paused
+        )
+    }
+}
+
+impl std::fmt::Display for SceneState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SceneState::Draft => "draft",
+            SceneState::Starting => "starting",
+            SceneState::Running => "running",
+            SceneState::Paused => "paused",
+            SceneState::Stopping => "stopping",
+            SceneState::Stopped => "stopped",
+            SceneState::Failed => "failed",
+        })
+    }
+}
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub(crate) enum NodeType {
+    Source,
+    Transform,
+    Sink,
+}
+
+/// A plugin instance placed on the scene graph.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub(crate) struct Node {
+    pub id: String,
+    pub scene_id: String,
+    pub node_type: NodeType,
+    pub plugin_id: String,
+    pub plugin_version: String,
+    pub config: Json<serde_json::Value>,
     pub create_time: chrono::DateTime<Local>,
     pub update_time: chrono::DateTime<Local>,
 }
 
+/// A directed connection: events emitted by `from_node_id` are delivered to `to_node_id`.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub(crate) struct Edge {
+    pub id: String,
+    pub scene_id: String,
+    pub from_node_id: String,
+    pub to_node_id: String,
+    pub create_time: chrono::DateTime<Local>,
+}
+
 #[derive(Error, Debug)]
 pub(crate) enum ServiceError {
     #[error("no scene found")]
     NotFound,
     #[error("conflict")]
     Conflict,
+    #[error("invalid input: {0}")]
+    Invalid(String),
+    #[error("incompatible schemas: {0}")]
+    Incompatible(SchemaDiff),
+    #[error("scene cannot go from [{from}] to [{to}]")]
+    InvalidTransition { from: SceneState, to: SceneState },
     #[error("failed to execute query: {0}")]
     Query(sqlx::Error),
     #[error("failed to get connection: {0}")]
@@ -65,6 +163,9 @@ impl Service {
                 let input = Scene {
                     id: Ulid::new().to_string(),
                     name: scene.name,
+                    state: SceneState::Draft,
+                    state_reason: None,
+                    state_time: None,
                     create_time: Local::now(),
                     update_time: Local::now(),
                 };
@@ -93,6 +194,9 @@ impl Service {
                 let input = Scene {
                     id: scene.id,
                     name: scene.name,
+                    state: scene.state,
+                    state_reason: scene.state_reason,
+                    state_time: scene.state_time,
                     create_time: scene.create_time,
                     update_time: Local::now(),
                 };
@@ -137,4 +241,391 @@ impl Service {
             }
         }
     }
+
+    /// Moves the scene to a new state. Fails with `InvalidTransition` if the scene is not
+    /// in a state it can leave for `to`, and with `Conflict` if it changed concurrently.
+    pub(crate) async fn transition(
+        &self,
+        id: &str,
+        to: SceneState,
+        reason: Option<String>,
+    ) -> Result<Scene, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                let scene = query_sqlite::get_scene(&mut conn, id).await?;
+                if !scene.state.can_transition(to) {
+                    return Err(ServiceError::InvalidTransition {
+                        from: scene.state,
+                        to,
+                    });
+                }
+                log::debug!(
+                    "Scene Service: scene [{id}] [{from}] -> [{to}]",
+                    from = scene.state
+                );
+                match query_sqlite::update_state(
+                    &mut conn,
+                    id,
+                    scene.state,
+                    to,
+                    reason,
+                    Local::now(),
+                )
+                .await
+                {
+                    Ok(scene) => {
+                        activity::service().scene_state(&scene);
+                        Ok(scene)
+                    }
+                    Err(sqlx::Error::RowNotFound) => Err(ServiceError::Conflict),
+                    Err(e) => Err(ServiceError::from(e)),
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn list_nodes(&self, scene_id: &str) -> Result<Vec<Node>, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                query_sqlite::get_scene(&mut conn, scene_id).await?;
+                Ok(query_sqlite::list_nodes(&mut conn, scene_id).await?)
+            }
+        }
+    }
+
+    pub(crate) async fn get_node(&self, scene_id: &str, id: &str) -> Result<Node, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(query_sqlite::get_node(&mut conn, scene_id, id).await?)
+            }
+        }
+    }
+
+    /// Adds a node to a scene. The node refers to a catalog plugin either through
+    /// `plugin_id` and `plugin_version`, or with a `name@version` reference in `plugin_id`.
+    pub(crate) async fn create_node(&self, node: Node) -> Result<Node, ServiceError> {
+        log::debug!(
+            "Scene Service: creating node in scene [{id}]",
+            id = node.scene_id
+        );
+        validate_node(&node)?;
+        let node = resolve_plugin(node).await?;
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                query_sqlite::get_scene(&mut conn, node.scene_id.as_str()).await?;
+                let input = Node {
+                    id: Ulid::new().to_string(),
+                    create_time: Local::now(),
+                    update_time: Local::now(),
+                    ..node
+                };
+                Ok(query_sqlite::create_node(&mut conn, input).await?)
+            }
+        }
+    }
+
+    /// Updates a node. Changing the node type is rejected if the node has edges that
+    /// the new type could not have.
+    pub(crate) async fn update_node(&self, node: Node) -> Result<Node, ServiceError> {
+        validate_node(&node)?;
+        let node = resolve_plugin(node).await?;
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                let current =
+                    query_sqlite::get_node(&mut conn, node.scene_id.as_str(), node.id.as_str())
+                        .await?;
+                if current.node_type != node.node_type {
+                    let edges = query_sqlite::list_edges(&mut conn, node.scene_id.as_str()).await?;
+                    let has_inbound = edges.iter().any(|edge| edge.to_node_id == node.id);
+                    let has_outbound = edges.iter().any(|edge| edge.from_node_id == node.id);
+                    if (node.node_type == NodeType::Source && has_inbound)
+                        || (node.node_type == NodeType::Sink && has_outbound)
+                    {
+                        return Err(ServiceError::Invalid(
+                            "node edges are incompatible with the new node type".to_string(),
+                        ));
+                    }
+                }
+                let input = Node {
+                    create_time: current.create_time,
+                    update_time: Local::now(),
+                    ..node
+                };
+                Ok(query_sqlite::update_node(&mut conn, input).await?)
+            }
+        }
+    }
+
+    pub(crate) async fn delete_node(&self, scene_id: &str, id: &str) -> Result<(), ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                match query_sqlite::delete_node(&mut conn, scene_id, id).await? {
+                    0 => Err(ServiceError::NotFound),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn list_edges(&self, scene_id: &str) -> Result<Vec<Edge>, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                query_sqlite::get_scene(&mut conn, scene_id).await?;
+                Ok(query_sqlite::list_edges(&mut conn, scene_id).await?)
+            }
+        }
+    }
+
+    pub(crate) async fn get_edge(&self, scene_id: &str, id: &str) -> Result<Edge, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                Ok(query_sqlite::get_edge(&mut conn, scene_id, id).await?)
+            }
+        }
+    }
+
+    /// Connects two nodes of the same scene. Sources cannot receive events, sinks cannot
+    /// emit them, and an edge may not close a cycle. An edge whose plugins announced
+    /// incompatible schemas is rejected, unless `allow_incompatible` is set.
+    pub(crate) async fn create_edge(
+        &self,
+        edge: Edge,
+        allow_incompatible: bool,
+    ) -> Result<Edge, ServiceError> {
+        log::debug!(
+            "Scene Service: creating edge in scene [{id}]",
+            id = edge.scene_id
+        );
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                // The cycle check only holds as long as no other edge is added in the meantime
+                query_sqlite::begin_immediate(&mut conn).await?;
+                let created = match insert_edge(&mut conn, edge, allow_incompatible).await {
+                    Ok(edge) => query_sqlite::commit(&mut conn)
+                        .await
+                        .map(|_| edge)
+                        .map_err(ServiceError::from),
+                    Err(e) => Err(e),
+                };
+                if created.is_err()
+                    && let Err(e) = query_sqlite::rollback(&mut conn).await
+                {
+                    log::error!("Scene Service: failed to roll back edge creation: {e}");
+                }
+                created
+            }
+        }
+    }
+
+    /// Checks a prospective edge without creating it. Returns `None` when the schemas
+    /// cannot be compared, because either plugin has not announced its schema yet.
+    pub(crate) async fn check_edge(&self, edge: &Edge) -> Result<Option<SchemaDiff>, ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                let (from, to) = validate_edge(&mut conn, edge).await?;
+                Ok(check_schemas(&from, &to).await)
+            }
+        }
+    }
+
+    pub(crate) async fn delete_edge(&self, scene_id: &str, id: &str) -> Result<(), ServiceError> {
+        match self.db {
+            Database::SQLite(db) => {
+                let mut conn = db.get_connection().await?;
+                match query_sqlite::delete_edge(&mut conn, scene_id, id).await? {
+                    0 => Err(ServiceError::NotFound),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+fn validate_node(node: &Node) -> Result<(), ServiceError> {
+    if node.plugin_id.is_empty() {
+        return Err(ServiceError::Invalid("plugin ID is missing".to_string()));
+    }
+    if !node.config.0.is_object() {
+        return Err(ServiceError::Invalid(
+            "node config must be an object".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Validates and inserts an edge. Runs inside the write transaction of `create_edge`.
+async fn insert_edge(
+    conn: &mut sqlx::pool::PoolConnection<sqlx::Sqlite>,
+    edge: Edge,
+    allow_incompatible: bool,
+) -> Result<Edge, ServiceError> {
+    let (from, to) = validate_edge(conn, &edge).await?;
+    if let Some(diff) = check_schemas(&from, &to).await
+        && !diff.is_compatible()
+    {
+        if !allow_incompatible {
+            return Err(ServiceError::Incompatible(diff));
+        }
+        log::warn!(
+            "Scene Service: connecting incompatible nodes [{from}] and [{to}]: {diff}",
+            from = from.id,
+            to = to.id
+        );
+    }
+    let input = Edge {
+        id: Ulid::new().to_string(),
+        create_time: Local::now(),
+        ..edge
+    };
+    Ok(query_sqlite::create_edge(conn, input).await?)
+}
+
+/// Checks that the edge fits the scene graph and returns the nodes it connects.
+async fn validate_edge(
+    conn: &mut sqlx::pool::PoolConnection<sqlx::Sqlite>,
+    edge: &Edge,
+) -> Result<(Node, Node), ServiceError> {
+    let scene_id = edge.scene_id.as_str();
+    query_sqlite::get_scene(conn, scene_id).await?;
+    let from = find_node(conn, scene_id, edge.from_node_id.as_str()).await?;
+    let to = find_node(conn, scene_id, edge.to_node_id.as_str()).await?;
+    if from.id == to.id {
+        return Err(ServiceError::Invalid(
+            "a node cannot be connected to itself".to_string(),
+        ));
+    }
+    if from.node_type == NodeType::Sink {
+        return Err(ServiceError::Invalid(format!(
+            "sink node [{id}] cannot emit events",
+            id = from.id
+        )));
+    }
+    if to.node_type == NodeType::Source {
+        return Err(ServiceError::Invalid(format!(
+            "source node [{id}] cannot receive events",
+            id = to.id
+        )));
+    }
+    let edges = query_sqlite::list_edges(conn, scene_id).await?;
+    if is_reachable(&edges, to.id.as_str(), from.id.as_str()) {
+        return Err(ServiceError::Invalid(
+            "edge would introduce a cycle".to_string(),
+        ));
+    }
+    Ok((from, to))
+}
+
+/// Resolves the catalog plugin a node refers to, pinning the node to the resolved version.
+async fn resolve_plugin(node: Node) -> Result<Node, ServiceError> {
+    let (name, version) = match catalog::service::parse_reference(node.plugin_id.as_str()) {
+        (name, "") => (name.to_string(), node.plugin_version.clone()),
+        (name, version) => (name.to_string(), version.to_string()),
+    };
+    let plugin = match catalog::service().await.find_plugin(&name, &version).await {
+        Ok(plugin) => plugin,
+        Err(catalog::service::ServiceError::NotFound) => {
+            return Err(ServiceError::Invalid(format!(
+                "plugin [{name}@{version}] is not in the catalog"
+            )));
+        }
+        Err(e) => return Err(ServiceError::Unknown(e.to_string())),
+    };
+    if plugin.plugin_type != node.node_type {
+        return Err(ServiceError::Invalid(format!(
+            "plugin [{reference}] is a {actual:?} plugin, node is a {expected:?}",
+            reference = plugin.reference(),
+            actual = plugin.plugin_type,
+            expected = node.node_type
+        )));
+    }
+    Ok(Node {
+        plugin_id: plugin.name,
+        plugin_version: plugin.version,
+        ..node
+    })
+}
+
+/// Compares the out-schema of the upstream plugin with the in-schema of the downstream one.
+async fn check_schemas(from: &Node, to: &Node) -> Option<SchemaDiff> {
+    let (_, out_schema) = announced_schemas(from).await?;
+    let (in_schema, _) = announced_schemas(to).await?;
+    Some(compat::check(out_schema.as_ref()?, in_schema.as_ref()?))
+}
+
+/// Finds the in- and out-schema of the plugin a node runs. A live session of the version the
+/// node asks for is preferred, otherwise the schemas the plugin last announced to the catalog
+/// are used.
+async fn announced_schemas(
+    node: &Node,
+) -> Option<(Option<SchemaDefinition>, Option<SchemaDefinition>)> {
+    if let Some(session) = hub::service()
+        .sessions()
+        .find_by_plugin_id(node.plugin_id.as_str())
+        .await
+    {
+        if node.plugin_version.is_empty() || session.plugin_version == node.plugin_version {
+            return Some((session.in_schema.clone(), session.out_schema.clone()));
+        }
+        log::trace!(
+            "Scene Service: plugin [{plugin_id}] runs version [{actual}], node [{id}] expects [{expected}]",
+            plugin_id = node.plugin_id,
+            actual = session.plugin_version,
+            id = node.id,
+            expected = node.plugin_version
+        );
+    }
+    let plugin = catalog::service()
+        .await
+        .find_plugin(node.plugin_id.as_str(), node.plugin_version.as_str())
+        .await
+        .ok()?;
+    plugin.schema_time?;
+    Some((plugin.in_schema(), plugin.out_schema()))
+}
+
+/// Looks up a node an edge refers to. A missing node is a bad request rather than a
+/// missing resource, since the edge itself is what is being addressed.
+async fn find_node(
+    conn: &mut sqlx::pool::PoolConnection<sqlx::Sqlite>,
+    scene_id: &str,
+    id: &str,
+) -> Result<Node, ServiceError> {
+    match query_sqlite::get_node(conn, scene_id, id).await {
+        Ok(node) => Ok(node),
+        Err(sqlx::Error::RowNotFound) => Err(ServiceError::Invalid(format!(
+            "node [{id}] does not belong to scene [{scene_id}]"
+        ))),
+        Err(e) => Err(ServiceError::from(e)),
+    }
+}
+
+fn is_reachable(edges: &[Edge], from: &str, to: &str) -> bool {
+    let mut adjacency: HashMap<&str, Vec<&str>> = HashMap::new();
+    for edge in edges {
+        adjacency
+            .entry(edge.from_node_id.as_str())
+            .or_default()
+            .push(edge.to_node_id.as_str());
+    }
+    let mut visited = HashSet::new();
+    let mut stack = vec![from];
+    while let Some(node) = stack.pop() {
+        if node == to {
+            return true;
+        }
+        if visited.insert(node) {
+            stack.extend(adjacency.get(node).into_iter().flatten());
+        }
+    }
+    false
 }
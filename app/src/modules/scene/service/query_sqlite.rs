@@ -1,4 +1,5 @@
-use crate::modules::scene::service::{ListFilters, Scene, ServiceError};
+use crate::modules::scene::service::{Edge, ListFilters, Node, Scene, SceneState, ServiceError};
+use chrono::{DateTime, Local};
 use sqlx::error::ErrorKind;
 use sqlx::pool::PoolConnection;
 use sqlx::{Executor, FromRow, Sqlite};
@@ -66,6 +67,32 @@ pub(super) async fn update_scene(
     Ok(scene)
 }
 
+/// Updates the state only if the scene is still in state `from`.
+pub(super) async fn update_state(
+    conn: &mut PoolConnection<Sqlite>,
+    id: &str,
+    from: SceneState,
+    to: SceneState,
+    reason: Option<String>,
+    state_time: DateTime<Local>,
+) -> Result<Scene, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, Scene>(
+                "UPDATE scenes SET state = $1, state_reason = $2, state_time = $3 \
+                    WHERE id = $4 AND state = $5 RETURNING *",
+            )
+            .bind(to)
+            .bind(reason)
+            .bind(state_time)
+            .bind(id)
+            .bind(from),
+        )
+        .await?;
+    let scene = Scene::from_row(&row)?;
+    Ok(scene)
+}
+
 pub(super) async fn delete_scene(
     conn: &mut PoolConnection<Sqlite>,
     id: &str,
@@ -99,3 +126,191 @@ pub(super) async fn list_scenes(
 
     Ok(scenes)
 }
+
+pub(super) async fn list_nodes(
+    conn: &mut PoolConnection<Sqlite>,
+    scene_id: &str,
+) -> Result<Vec<Node>, sqlx::Error> {
+    let rows = conn
+        .fetch_all(
+            sqlx::query_as::<Sqlite, Node>(
+                "SELECT * FROM scene_nodes WHERE scene_id = $1 ORDER BY create_time, id",
+            )
+            .bind(scene_id),
+        )
+        .await?;
+    let mut nodes = vec![];
+    for row in rows {
+        nodes.push(Node::from_row(&row)?);
+    }
+
+    Ok(nodes)
+}
+
+pub(super) async fn get_node(
+    conn: &mut PoolConnection<Sqlite>,
+    scene_id: &str,
+    id: &str,
+) -> Result<Node, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, Node>(
+                "SELECT * FROM scene_nodes WHERE scene_id = $1 AND id = $2",
+            )
+            .bind(scene_id)
+            .bind(id),
+        )
+        .await?;
+    let node = Node::from_row(&row)?;
+    Ok(node)
+}
+
+pub(super) async fn create_node(
+    conn: &mut PoolConnection<Sqlite>,
+    node: Node,
+) -> Result<Node, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, Node>(
+                "INSERT INTO scene_nodes (id, scene_id, node_type, plugin_id, plugin_version, config) \
+                    VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+            )
+            .bind(node.id)
+            .bind(node.scene_id)
+            .bind(node.node_type)
+            .bind(node.plugin_id)
+            .bind(node.plugin_version)
+            .bind(node.config),
+        )
+        .await?;
+    let node = Node::from_row(&row)?;
+    Ok(node)
+}
+
+pub(super) async fn update_node(
+    conn: &mut PoolConnection<Sqlite>,
+    node: Node,
+) -> Result<Node, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, Node>(
+                "UPDATE scene_nodes SET node_type = $1, plugin_id = $2, plugin_version = $3, \
+                    config = $4, update_time = $5 WHERE scene_id = $6 AND id = $7 RETURNING *",
+            )
+            .bind(node.node_type)
+            .bind(node.plugin_id)
+            .bind(node.plugin_version)
+            .bind(node.config)
+            .bind(node.update_time)
+            .bind(node.scene_id)
+            .bind(node.id),
+        )
+        .await?;
+    let node = Node::from_row(&row)?;
+    Ok(node)
+}
+
+pub(super) async fn delete_node(
+    conn: &mut PoolConnection<Sqlite>,
+    scene_id: &str,
+    id: &str,
+) -> Result<u64, sqlx::Error> {
+    let result = conn
+        .execute(
+            sqlx::query("DELETE FROM scene_nodes WHERE scene_id = $1 AND id = $2")
+                .bind(scene_id)
+                .bind(id),
+        )
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub(super) async fn list_edges(
+    conn: &mut PoolConnection<Sqlite>,
+    scene_id: &str,
+) -> Result<Vec<Edge>, sqlx::Error> {
+    let rows = conn
+        .fetch_all(
+            sqlx::query_as::<Sqlite, Edge>(
+                "SELECT * FROM scene_edges WHERE scene_id = $1 ORDER BY create_time, id",
+            )
+            .bind(scene_id),
+        )
+        .await?;
+    let mut edges = vec![];
+    for row in rows {
+        edges.push(Edge::from_row(&row)?);
+    }
+
+    Ok(edges)
+}
+
+pub(super) async fn get_edge(
+    conn: &mut PoolConnection<Sqlite>,
+    scene_id: &str,
+    id: &str,
+) -> Result<Edge, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, Edge>(
+                "SELECT * FROM scene_edges WHERE scene_id = $1 AND id = $2",
+            )
+            .bind(scene_id)
+            .bind(id),
+        )
+        .await?;
+    let edge = Edge::from_row(&row)?;
+    Ok(edge)
+}
+
+/// Starts a write transaction right away, so no other writer can change the scene between
+/// the checks made inside it and its writes.
+pub(super) async fn begin_immediate(conn: &mut PoolConnection<Sqlite>) -> Result<(), sqlx::Error> {
+    conn.execute("BEGIN IMMEDIATE").await?;
+    Ok(())
+}
+
+pub(super) async fn commit(conn: &mut PoolConnection<Sqlite>) -> Result<(), sqlx::Error> {
+    conn.execute("COMMIT").await?;
+    Ok(())
+}
+
+pub(super) async fn rollback(conn: &mut PoolConnection<Sqlite>) -> Result<(), sqlx::Error> {
+    conn.execute("ROLLBACK").await?;
+    Ok(())
+}
+
+pub(super) async fn create_edge(
+    conn: &mut PoolConnection<Sqlite>,
+    edge: Edge,
+) -> Result<Edge, sqlx::Error> {
+    let row = conn
+        .fetch_one(
+            sqlx::query_as::<Sqlite, Edge>(
+                "INSERT INTO scene_edges (id, scene_id, from_node_id, to_node_id) \
+                    VALUES ($1, $2, $3, $4) RETURNING *",
+            )
+            .bind(edge.id)
+            .bind(edge.scene_id)
+            .bind(edge.from_node_id)
+            .bind(edge.to_node_id),
+        )
+        .await?;
+    let edge = Edge::from_row(&row)?;
+    Ok(edge)
+}
+
+pub(super) async fn delete_edge(
+    conn: &mut PoolConnection<Sqlite>,
+    scene_id: &str,
+    id: &str,
+) -> Result<u64, sqlx::Error> {
+    let result = conn
+        .execute(
+            sqlx::query("DELETE FROM scene_edges WHERE scene_id = $1 AND id = $2")
+                .bind(scene_id)
+                .bind(id),
+        )
+        .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,14 @@
+use crate::modules::supervisor::service::{Service, SupervisorConfig};
+use lazy_static::lazy_static;
+use std::sync::Arc;
+
+pub(crate) mod api;
+pub(crate) mod service;
+
+lazy_static! {
+    static ref SERVICE: Arc<Service> = Arc::new(Service::new(SupervisorConfig::read()));
+}
+
+pub(crate) fn service() -> &'static Service {
+    SERVICE.as_ref()
+}
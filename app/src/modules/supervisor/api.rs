@@ -0,0 +1,175 @@
+use crate::modules::supervisor;
+use crate::modules::supervisor::service::{ManagedProcess, ProcessStatus, SupervisorError};
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) enum Status {
+    #[serde(rename = "starting")]
+    Starting,
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "backoff")]
+    Backoff,
+    #[serde(rename = "exited")]
+    Exited,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "stopped")]
+    Stopped,
+}
+
+impl From<ProcessStatus> for Status {
+    fn from(value: ProcessStatus) -> Self {
+        match value {
+            ProcessStatus::Starting => Status::Starting,
+            ProcessStatus::Running => Status::Running,
+            ProcessStatus::Backoff => Status::Backoff,
+            ProcessStatus::Exited => Status::Exited,
+            ProcessStatus::Failed => Status::Failed,
+            ProcessStatus::Stopped => Status::Stopped,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct PluginProcess {
+    /// The node the process runs its plugin as, or the plugin ID for plugins not launched for
+    /// a node.
+    pub id: String,
+    pub plugin_id: String,
+    pub node_id: Option<String>,
+    pub executable: String,
+    pub args: Vec<String>,
+    pub status: Status,
+    pub pid: Option<u32>,
+    pub restarts: u32,
+    pub last_exit_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub start_time: Option<i64>,
+    pub update_time: i64,
+}
+
+impl From<&ManagedProcess> for PluginProcess {
+    fn from(value: &ManagedProcess) -> Self {
+        let state = value.state();
+        Self {
+            id: value.spec.key().to_string(),
+            plugin_id: value.spec.id.clone(),
+            node_id: value.spec.node_id.clone(),
+            executable: value.spec.executable.clone(),
+            args: value.spec.args.clone(),
+            status: Status::from(state.status),
+            pid: state.pid,
+            restarts: state.restarts,
+            last_exit_code: state.last_exit_code,
+            last_error: state.last_error,
+            start_time: state.start_time.map(|time| time.timestamp_millis()),
+            update_time: state.update_time.timestamp_millis(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ListProcessesResponse {
+    processes: Vec<PluginProcess>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/processes",
+    operation_id = "list-plugin-processes",
+    description = "List plugin processes managed by the supervisor",
+    summary = "List plugin processes",
+    responses(
+        (status = 200, description = "Plugin processes", body = ListProcessesResponse),
+    ),
+)]
+async fn list_processes() -> Json<ListProcessesResponse> {
+    log::trace!("Supervisor API: listing processes");
+    let processes = supervisor::service().list().await;
+    Json(ListProcessesResponse {
+        processes: processes
+            .iter()
+            .map(|process| PluginProcess::from(process.as_ref()))
+            .collect(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/processes/by-id/{id}",
+    operation_id = "get-plugin-process",
+    description = "Get a plugin process by ID: the node it runs as, or the plugin ID for \
+        plugins not launched for a node",
+    summary = "Get plugin process by ID",
+    responses(
+        (status = 200, description = "Plugin process", body = PluginProcess),
+        (status = 404, description = "Not found"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the process")
+    )
+)]
+async fn get_process(Path(id): Path<String>) -> Result<Json<PluginProcess>, StatusCode> {
+    log::trace!("Supervisor API: getting process [{id}]");
+    match supervisor::service().get(id.as_str()).await {
+        Some(process) => Ok(Json(PluginProcess::from(process.as_ref()))),
+        None => {
+            log::trace!("Supervisor API: Failed to get process [{id}]: not found");
+            Err(StatusCode::NOT_FOUND)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/processes/by-id/{id}/stop",
+    operation_id = "stop-plugin-process",
+    description = "Stop a plugin process without restarting it",
+    summary = "Stop plugin process",
+    responses(
+        (status = 200, description = "Stop requested"),
+        (status = 404, description = "Not found"),
+    ),
+    params(
+        ("id" = String, Path, description = "ID of the process")
+    )
+)]
+async fn stop_process(Path(id): Path<String>) -> Result<StatusCode, StatusCode> {
+    log::trace!("Supervisor API: stopping process [{id}]");
+    match supervisor::service().stop(id.as_str()).await {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(e) => match e {
+            SupervisorError::NotFound(_) => {
+                log::trace!("Supervisor API: Failed to stop process [{id}]: not found");
+                Err(StatusCode::NOT_FOUND)
+            }
+            _ => {
+                log::error!("Supervisor API: Failed to stop process [{id}]: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Supervisor", description = "Plugin process supervisor API",),
+    paths(list_processes, get_process, stop_process,),
+    components(schemas(PluginProcess, Status, ListProcessesResponse,))
+)]
+pub(crate) struct Api;
+
+impl Api {
+    pub(crate) fn build_router() -> Router {
+        Router::new()
+            .route("/supervisor/processes", get(list_processes))
+            .route("/supervisor/processes/by-id/{id}", get(get_process))
+            .route("/supervisor/processes/by-id/{id}/stop", post(stop_process))
+    }
+}
@@ -0,0 +1,734 @@
+use crate::modules::scene::service::Node;
+use crate::modules::{catalog, hub};
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use flwrs_core::config;
+use flwrs_core::config::main_config;
+use flwrs_core::config::Source;
+use flwrs_core::metrics;
+use flwrs_core::registry;
+use flwrs_core::registry::{Health, HealthStatus, RegistryError};
+use flwrs_plugin::plugin::core::{
+    AUTH_TOKEN_ENV, NODE_ID_ENV, TLS_CA_ENV, TLS_CERT_ENV, TLS_KEY_ENV, TLS_SERVER_NAME_ENV,
+    UNIX_SCHEME,
+};
+use lazy_static::lazy_static;
+use prometheus::{IntCounterVec, Opts, Registry};
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{Notify, RwLock};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+lazy_static! {
+    static ref RESTARTS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "plugin_restarts_total",
+            "Times the supervisor restarted a crashed plugin",
+        ),
+        &["plugin_id"],
+    )
+    .expect("plugin restarts metric is valid");
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SupervisorConfig {
+    #[serde(default = "default_max_restarts")]
+    max_restarts: u32,
+    #[serde(default = "default_initial_backoff_millis")]
+    initial_backoff_millis: u64,
+    #[serde(default = "default_max_backoff_millis")]
+    max_backoff_millis: u64,
+    #[serde(default = "default_stable_run_seconds")]
+    stable_run_seconds: u64,
+    #[serde(default = "default_stop_timeout_seconds")]
+    stop_timeout_seconds: u64,
+    #[serde(default)]
+    plugins: Vec<ProcessSpec>,
+}
+
+fn default_max_restarts() -> u32 {
+    5
+}
+
+fn default_initial_backoff_millis() -> u64 {
+    500
+}
+
+fn default_max_backoff_millis() -> u64 {
+    30_000
+}
+
+fn default_stable_run_seconds() -> u64 {
+    60
+}
+
+fn default_stop_timeout_seconds() -> u64 {
+    10
+}
+
+impl SupervisorConfig {
+    /// Reads the `[supervisor]` section, or the defaults if there is none. A section that
+    /// fails to parse is an error: falling back to the defaults would quietly drop its plugins.
+    pub(crate) fn read() -> Result<Self, SupervisorError> {
+        let main_config = main_config();
+        let path = ["supervisor".to_string()];
+        match config::read_struct(main_config, &path) {
+            Some(config) => Ok(config),
+            None if main_config.sub(&path).is_some() => Err(SupervisorError::Config(
+                "section [supervisor] is invalid".to_string(),
+            )),
+            None => Ok(Self::defaults()),
+        }
+    }
+
+    fn defaults() -> Self {
+        Self {
+            max_restarts: default_max_restarts(),
+            initial_backoff_millis: default_initial_backoff_millis(),
+            max_backoff_millis: default_max_backoff_millis(),
+            stable_run_seconds: default_stable_run_seconds(),
+            stop_timeout_seconds: default_stop_timeout_seconds(),
+            plugins: vec![],
+        }
+    }
+
+    /// Delay before the given (1-based) restart attempt, doubling with every attempt.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        Duration::from_millis(
+            self.initial_backoff_millis
+                .saturating_mul(factor)
+                .min(self.max_backoff_millis),
+        )
+    }
+}
+
+/// What to launch: the plugin binary and the arguments it gets on top of the hub address.
+#[derive(Deserialize, Clone)]
+pub(crate) struct ProcessSpec {
+    pub id: String,
+    pub executable: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// Scene the plugin belongs to. Scene plugins are launched by the director when their
+    /// scene starts instead of at startup.
+    #[serde(default)]
+    pub scene: Option<String>,
+    /// Config delivered to the plugin when it connects, checked against the options it
+    /// declares.
+    #[serde(default)]
+    pub config: serde_json::Map<String, serde_json::Value>,
+    /// Scene node the process runs the plugin as. Every node gets a process of its own.
+    #[serde(skip)]
+    pub node_id: Option<String>,
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+impl ProcessSpec {
+    /// ID the process is supervised under: the node it runs as, or the plugin ID for plugins
+    /// not launched for a node.
+    pub(crate) fn key(&self) -> &str {
+        self.node_id.as_deref().unwrap_or(self.id.as_str())
+    }
+}
+
+impl fmt::Display for ProcessSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.node_id {
+            Some(node_id) => write!(f, "{id}/{node_id}", id = self.id),
+            None => write!(f, "{id}", id = self.id),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ProcessStatus {
+    Starting,
+    Running,
+    Backoff,
+    Exited,
+    Failed,
+    Stopped,
+}
+
+#[derive(Clone)]
+pub(crate) struct ProcessState {
+    pub status: ProcessStatus,
+    pub pid: Option<u32>,
+    pub restarts: u32,
+    pub last_exit_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub start_time: Option<DateTime<Local>>,
+    pub update_time: DateTime<Local>,
+}
+
+impl Default for ProcessState {
+    fn default() -> Self {
+        Self {
+            status: ProcessStatus::Starting,
+            pid: None,
+            restarts: 0,
+            last_exit_code: None,
+            last_error: None,
+            start_time: None,
+            update_time: Local::now(),
+        }
+    }
+}
+
+/// A plugin process owned by the supervisor.
+pub(crate) struct ManagedProcess {
+    pub spec: ProcessSpec,
+    state: Mutex<ProcessState>,
+    stop: CancellationToken,
+    restart: Notify,
+    /// Why a restart was requested, until the supervise loop picks the request up.
+    restart_reason: Mutex<Option<String>>,
+    /// Token issued to the running instance, which it opens its hub session with.
+    token: Mutex<Option<String>>,
+    /// Config delivered to the plugin when it connects. Starts out as the config of the spec
+    /// and follows the edits applied to the running plugin, so restarts keep them.
+    config: Mutex<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl ManagedProcess {
+    fn new(spec: ProcessSpec, stop: CancellationToken) -> Self {
+        Self {
+            config: Mutex::new(spec.config.clone()),
+            spec,
+            state: Mutex::new(ProcessState::default()),
+            stop,
+            restart: Notify::new(),
+            restart_reason: Mutex::new(None),
+            token: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn state(&self) -> ProcessState {
+        self.state
+            .lock()
+            .expect("process state lock poisoned")
+            .clone()
+    }
+
+    fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut ProcessState),
+    {
+        let mut state = self.state.lock().expect("process state lock poisoned");
+        f(&mut state);
+        state.update_time = Local::now();
+    }
+
+    fn token(&self) -> Option<String> {
+        self.token.lock().expect("token lock poisoned").clone()
+    }
+
+    fn set_token(&self, token: Option<String>) {
+        *self.token.lock().expect("token lock poisoned") = token;
+    }
+
+    fn config(&self) -> serde_json::Map<String, serde_json::Value> {
+        self.config.lock().expect("config lock poisoned").clone()
+    }
+
+    fn set_config(&self, config: serde_json::Map<String, serde_json::Value>) {
+        *self.config.lock().expect("config lock poisoned") = config;
+    }
+
+    fn request_restart(&self, reason: &str) {
+        *self
+            .restart_reason
+            .lock()
+            .expect("restart reason lock poisoned") = Some(reason.to_string());
+        self.restart.notify_one();
+    }
+
+    async fn restart_requested(&self) -> String {
+        loop {
+            self.restart.notified().await;
+            if let Some(reason) = self
+                .restart_reason
+                .lock()
+                .expect("restart reason lock poisoned")
+                .take()
+            {
+                return reason;
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum SupervisorError {
+    #[error("invalid supervisor config: {0}")]
+    Config(String),
+    #[error("process [{0}] is already supervised")]
+    Conflict(String),
+    #[error("process [{0}] not found")]
+    NotFound(String),
+    #[error("process [{0}] is not running")]
+    NotRunning(String),
+}
+
+/// Launches plugin binaries, restarting them with exponential backoff when they crash.
+pub(crate) struct Service {
+    config: SupervisorConfig,
+    /// Why the configured supervisor section was rejected. The supervisor then refuses to
+    /// start.
+    config_error: Option<String>,
+    processes: RwLock<HashMap<String, Arc<ManagedProcess>>>,
+    tracker: TaskTracker,
+    token: CancellationToken,
+}
+
+impl Service {
+    pub(crate) fn new(config: Result<SupervisorConfig, SupervisorError>) -> Self {
+        let (config, config_error) = match config {
+            Ok(config) => (config, None),
+            Err(SupervisorError::Config(reason)) => (SupervisorConfig::defaults(), Some(reason)),
+            Err(e) => (SupervisorConfig::defaults(), Some(e.to_string())),
+        };
+        Self {
+            config,
+            config_error,
+            processes: RwLock::new(HashMap::new()),
+            tracker: TaskTracker::new(),
+            token: CancellationToken::new(),
+        }
+    }
+
+    /// Processes that belong to the given scene: one for each of its graph nodes, plus the
+    /// plugins configured for it that no node runs. A node runs the plugin configured for the
+    /// scene under its plugin ID if there is one, the catalog plugin otherwise; the config of
+    /// the node goes over the configured one.
+    pub(crate) async fn scene_specs(&self, scene_id: &str, nodes: &[Node]) -> Vec<ProcessSpec> {
+        let configured: Vec<&ProcessSpec> = self
+            .config
+            .plugins
+            .iter()
+            .filter(|spec| spec.scene.as_deref() == Some(scene_id))
+            .collect();
+        let mut specs: Vec<ProcessSpec> = configured
+            .iter()
+            .filter(|spec| !nodes.iter().any(|node| node.plugin_id == spec.id))
+            .map(|spec| (*spec).clone())
+            .collect();
+        for node in nodes {
+            if let Some(spec) = configured.iter().find(|spec| spec.id == node.plugin_id) {
+                specs.push(ProcessSpec {
+                    config: self.scene_config(scene_id, node),
+                    node_id: Some(node.id.clone()),
+                    ..(*spec).clone()
+                });
+                continue;
+            }
+            match catalog::service()
+                .await
+                .find_plugin(node.plugin_id.as_str(), node.plugin_version.as_str())
+                .await
+            {
+                Ok(plugin) => specs.push(ProcessSpec {
+                    id: plugin.name,
+                    executable: plugin.executable,
+                    args: plugin.args.0,
+                    log_level: default_log_level(),
+                    scene: Some(scene_id.to_string()),
+                    config: node_config(node),
+                    node_id: Some(node.id.clone()),
+                }),
+                Err(e) => {
+                    log::warn!(
+                        "Supervisor: failed to resolve plugin [{plugin_id}@{version}] of scene [{scene_id}]: {e}",
+                        plugin_id = node.plugin_id,
+                        version = node.plugin_version
+                    );
+                }
+            }
+        }
+        specs
+    }
+
+    /// Config a node of the given scene runs its plugin with: the config of the plugin if it
+    /// is configured for the scene, overridden by the config of the node.
+    pub(crate) fn scene_config(
+        &self,
+        scene_id: &str,
+        node: &Node,
+    ) -> serde_json::Map<String, serde_json::Value> {
+        let mut config = self
+            .config
+            .plugins
+            .iter()
+            .find(|spec| spec.id == node.plugin_id && spec.scene.as_deref() == Some(scene_id))
+            .map(|spec| spec.config.clone())
+            .unwrap_or_default();
+        config.extend(node_config(node));
+        config
+    }
+
+    pub(crate) async fn list(&self) -> Vec<Arc<ManagedProcess>> {
+        self.processes.read().await.values().cloned().collect()
+    }
+
+    pub(crate) async fn get(&self, id: &str) -> Option<Arc<ManagedProcess>> {
+        self.processes.read().await.get(id).cloned()
+    }
+
+    /// Starts supervising a new plugin process, under the node it runs as.
+    pub(crate) async fn launch(&self, spec: ProcessSpec) -> Result<(), SupervisorError> {
+        let token = self.token.child_token();
+        let mut processes = self.processes.write().await;
+        if let Some(existing) = processes.get(spec.key())
+            && !matches!(
+                existing.state().status,
+                ProcessStatus::Exited | ProcessStatus::Failed | ProcessStatus::Stopped
+            )
+        {
+            return Err(SupervisorError::Conflict(spec.key().to_string()));
+        }
+        let process = Arc::new(ManagedProcess::new(spec, token));
+        processes.insert(process.spec.key().to_string(), process.clone());
+        let service = super::service();
+        self.tracker
+            .spawn(async move { service.supervise(process).await });
+        Ok(())
+    }
+
+    /// Stops a supervised process, giving it a grace period to exit before it gets killed.
+    pub(crate) async fn stop(&self, id: &str) -> Result<(), SupervisorError> {
+        match self.processes.read().await.get(id) {
+            Some(process) => {
+                process.stop.cancel();
+                Ok(())
+            }
+            None => Err(SupervisorError::NotFound(id.to_string())),
+        }
+    }
+
+    /// Tokens issued to the running processes of a plugin, along with the node each runs as.
+    pub(crate) async fn tokens(&self, plugin_id: &str) -> Vec<(Option<String>, String)> {
+        self.processes
+            .read()
+            .await
+            .values()
+            .filter(|process| process.spec.id == plugin_id)
+            .filter_map(|process| Some((process.spec.node_id.clone(), process.token()?)))
+            .collect()
+    }
+
+    /// Config of a supervised process, delivered to the plugin when it connects.
+    pub(crate) async fn plugin_config(
+        &self,
+        id: &str,
+    ) -> Option<serde_json::Map<String, serde_json::Value>> {
+        self.get(id).await.map(|process| process.config())
+    }
+
+    /// Replaces the config of a supervised process, once the running plugin has applied it.
+    pub(crate) async fn set_plugin_config(
+        &self,
+        id: &str,
+        config: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<(), SupervisorError> {
+        let process = self
+            .get(id)
+            .await
+            .ok_or_else(|| SupervisorError::NotFound(id.to_string()))?;
+        process.set_config(config);
+        Ok(())
+    }
+
+    /// Kills a running process, e.g. because it hung, so that it gets restarted like a crashed
+    /// one.
+    pub(crate) async fn restart(&self, id: &str, reason: &str) -> Result<(), SupervisorError> {
+        let process = self
+            .get(id)
+            .await
+            .ok_or_else(|| SupervisorError::NotFound(id.to_string()))?;
+        if process.state().status != ProcessStatus::Running {
+            return Err(SupervisorError::NotRunning(id.to_string()));
+        }
+        process.request_restart(reason);
+        Ok(())
+    }
+
+    async fn supervise(&self, process: Arc<ManagedProcess>) {
+        let id = process.spec.to_string();
+        let mut failures = 0;
+        loop {
+            // a restart requested for the previous run doesn't apply to this one
+            process
+                .restart_reason
+                .lock()
+                .expect("restart reason lock poisoned")
+                .take();
+            process.update(|state| {
+                state.status = ProcessStatus::Starting;
+                state.pid = None;
+            });
+            let started = Local::now();
+            // every instance gets a fresh token, one leaked by a previous instance is useless
+            let token = issue_token();
+            process.set_token(Some(token.clone()));
+            let exit = match self.spawn(&process.spec, token.as_str()) {
+                Ok(mut child) => {
+                    let pid = child.id();
+                    log::info!("Supervisor: plugin [{id}] started with pid [{pid:?}]");
+                    process.update(|state| {
+                        state.status = ProcessStatus::Running;
+                        state.pid = pid;
+                        state.start_time = Some(started);
+                    });
+                    tokio::select! {
+                        status = child.wait() => status.map_err(|e| e.to_string()),
+                        reason = process.restart_requested() => {
+                            log::warn!("Supervisor: killing plugin [{id}] to restart it: {reason}");
+                            process.update(|state| state.last_error = Some(reason));
+                            if let Err(e) = child.start_kill() {
+                                log::error!("Supervisor: failed to kill plugin [{id}]: {e}");
+                            }
+                            child.wait().await.map_err(|e| e.to_string())
+                        }
+                        _ = process.stop.cancelled() => {
+                            let status = self
+                                .terminate(&id, process.spec.key(), &mut child)
+                                .await;
+                            process.set_token(None);
+                            process.update(|state| {
+                                state.status = ProcessStatus::Stopped;
+                                state.pid = None;
+                                state.last_exit_code = status.and_then(|status| status.code());
+                            });
+                            log::info!("Supervisor: plugin [{id}] stopped");
+                            return;
+                        }
+                    }
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            process.set_token(None);
+
+            match &exit {
+                Ok(status) if status.success() => {
+                    log::info!("Supervisor: plugin [{id}] exited cleanly");
+                    process.update(|state| {
+                        state.status = ProcessStatus::Exited;
+                        state.pid = None;
+                        state.last_exit_code = status.code();
+                    });
+                    return;
+                }
+                Ok(status) => {
+                    log::warn!("Supervisor: plugin [{id}] crashed: {status}");
+                }
+                Err(e) => {
+                    log::error!("Supervisor: failed to run plugin [{id}]: {e}");
+                }
+            }
+
+            // crashes after a long enough run don't count against the restart budget
+            if (Local::now() - started).num_seconds() as u64 >= self.config.stable_run_seconds {
+                failures = 0;
+            }
+            failures += 1;
+            process.update(|state| {
+                state.pid = None;
+                match &exit {
+                    Ok(status) => state.last_exit_code = status.code(),
+                    Err(e) => state.last_error = Some(e.clone()),
+                }
+            });
+            if failures > self.config.max_restarts {
+                log::error!(
+                    "Supervisor: plugin [{id}] exceeded its restart budget of [{max}], giving up",
+                    max = self.config.max_restarts
+                );
+                process.update(|state| state.status = ProcessStatus::Failed);
+                return;
+            }
+
+            let backoff = self.config.backoff(failures);
+            log::debug!(
+                "Supervisor: restarting plugin [{id}] in [{backoff:?}], attempt [{failures}]"
+            );
+            process.update(|state| {
+                state.status = ProcessStatus::Backoff;
+                state.restarts += 1;
+            });
+            RESTARTS
+                .with_label_values(&[process.spec.id.as_str()])
+                .inc();
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = process.stop.cancelled() => {
+                    process.update(|state| state.status = ProcessStatus::Stopped);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn spawn(&self, spec: &ProcessSpec, token: &str) -> std::io::Result<Child> {
+        let hub_config = hub::service().config();
+        let mut command = Command::new(spec.executable.as_str());
+        command
+            .arg("--id")
+            .arg(spec.id.as_str())
+            .arg("--host")
+            .arg(hub_config.host())
+            .arg("--port")
+            .arg(hub_config.port().to_string())
+            .arg("--log-level")
+            .arg(spec.log_level.as_str())
+            .args(spec.args.iter())
+            .env(AUTH_TOKEN_ENV, token)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(tls) = hub_config.tls()
+            && !hub_config.host().starts_with(UNIX_SCHEME)
+        {
+            command.env(TLS_CA_ENV, tls.ca_path.as_str());
+            if let Some(server_name) = &tls.server_name {
+                command.env(TLS_SERVER_NAME_ENV, server_name);
+            }
+            if let (Some(cert_path), Some(key_path)) = (&tls.plugin_cert_path, &tls.plugin_key_path)
+            {
+                command
+                    .env(TLS_CERT_ENV, cert_path)
+                    .env(TLS_KEY_ENV, key_path);
+            }
+        }
+        if let Some(node_id) = &spec.node_id {
+            command.env(NODE_ID_ENV, node_id);
+        }
+        let mut child = command.spawn()?;
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(forward_output(spec.to_string(), stdout, log::Level::Debug));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(forward_output(spec.to_string(), stderr, log::Level::Warn));
+        }
+        Ok(child)
+    }
+
+    /// Stops a plugin process: asks the plugin to shut down through its hub session, and
+    /// kills it if it did not exit within the stop timeout.
+    async fn terminate(&self, id: &str, key: &str, child: &mut Child) -> Option<ExitStatus> {
+        if let Some(session) = hub::service().sessions().find_by_process_id(key).await {
+            hub::service().shut_down(&session).await;
+        }
+        let timeout = Duration::from_secs(self.config.stop_timeout_seconds);
+        match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => status.ok(),
+            Err(_) => {
+                log::warn!(
+                    "Supervisor: plugin [{id}] did not exit within [{timeout:?}], killing it"
+                );
+                if let Err(e) = child.kill().await {
+                    log::error!("Supervisor: failed to kill plugin [{id}]: {e}");
+                }
+                child.wait().await.ok()
+            }
+        }
+    }
+}
+
+fn node_config(node: &Node) -> serde_json::Map<String, serde_json::Value> {
+    node.config.0.as_object().cloned().unwrap_or_default()
+}
+
+/// A random token, hex encoded.
+fn issue_token() -> String {
+    rand::rng()
+        .random::<[u8; 32]>()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+async fn forward_output<R>(id: String, output: R, level: log::Level)
+where
+    R: AsyncRead + Unpin,
+{
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        log::log!(level, "Plugin process [{id}]: {line}");
+    }
+}
+
+#[async_trait]
+impl registry::Service for Service {
+    fn id(&self) -> String {
+        "supervisor-service".to_string()
+    }
+
+    fn register_metrics(&self, registry: &Registry) {
+        metrics::register(registry, RESTARTS.clone());
+    }
+
+    /// Degraded while a plugin is given up on after exceeding its restart budget.
+    async fn health(&self) -> Health {
+        let states: Vec<ProcessStatus> = self
+            .list()
+            .await
+            .iter()
+            .map(|process| process.state().status)
+            .collect();
+        let count = |status: ProcessStatus| states.iter().filter(|s| **s == status).count();
+        let failed = count(ProcessStatus::Failed);
+        let health = if failed > 0 {
+            Health::new(HealthStatus::Degraded)
+        } else {
+            Health::up()
+        };
+        health
+            .with_detail("running", count(ProcessStatus::Running))
+            .with_detail("backoff", count(ProcessStatus::Backoff))
+            .with_detail("failed", failed)
+    }
+
+    async fn start(&self, shutdown_token: CancellationToken) -> Result<(), RegistryError> {
+        if let Some(error) = &self.config_error {
+            return Err(RegistryError::ServiceError {
+                msg: "Supervisor config error".to_string(),
+                source: Box::new(SupervisorError::Config(error.clone())),
+            });
+        }
+        log::debug!("Starting plugin supervisor.");
+        for spec in self
+            .config
+            .plugins
+            .iter()
+            .filter(|spec| spec.scene.is_none())
+        {
+            if let Err(e) = self.launch(spec.clone()).await {
+                log::error!("Supervisor: failed to launch plugin [{spec}]: {e}");
+            }
+        }
+
+        shutdown_token.cancelled().await;
+        log::debug!("Plugin supervisor shutting down.");
+        self.token.cancel();
+        self.tracker.close();
+        self.tracker.wait().await;
+        log::debug!("Plugin supervisor exited");
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use crate::http;
+use crate::modules::{activity, director, hub, recording, supervisor};
 use flwrs_core::registry::ServiceRegistry;
 
 pub async fn build_registry() -> ServiceRegistry {
@@ -9,6 +10,26 @@ pub async fn build_registry() -> ServiceRegistry {
     log::debug!("Registering HTTP service");
     registry.register_service(http::server());
 
+    // Plugin hub
+    log::debug!("Registering plugin hub service");
+    registry.register_service(hub::service());
+
+    // Plugin supervisor
+    log::debug!("Registering plugin supervisor service");
+    registry.register_service(supervisor::service());
+
+    // Director
+    log::debug!("Registering director service");
+    registry.register_service(director::service());
+
+    // Recorder
+    log::debug!("Registering recording service");
+    registry.register_service(recording::service().await);
+
+    // Live activity
+    log::debug!("Registering activity service");
+    registry.register_service(activity::service());
+
     log::debug!("Registry build completed");
     registry
 }
@@ -5,4 +5,7 @@ use clap::Parser;
 pub struct CmdArgs {
     #[arg(short, long, default_value = "config.toml")]
     pub config: String,
+    /// Command of the application and its arguments, left for the application to parse.
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, hide = true)]
+    pub command: Vec<String>,
 }
\ No newline at end of file
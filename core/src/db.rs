@@ -1,6 +1,9 @@
 use std::str::Utf8Error;
+use std::sync::Mutex;
+use std::time::Duration;
 use crate::db::migrations::{MigrationDef, Version};
 use crate::db::sqlite::Migrator;
+use lazy_static::lazy_static;
 use thiserror::Error;
 
 mod sqlite;
@@ -16,13 +19,54 @@ pub enum DbError {
     #[error("migration malformed: {0}")]
     MigrationMalformed(String),
     #[error("failed to parse UTF8: {0}")]
-    UTF8ParseFailed(#[from] #[source] Utf8Error)
+    UTF8ParseFailed(#[from] #[source] Utf8Error),
+    #[error("no connection within {0:?}")]
+    Timeout(Duration),
 }
 
+/// Time a connectivity check waits for a connection.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+lazy_static! {
+    /// Databases built so far, for connectivity checks.
+    static ref DATABASES: Mutex<Vec<Database>> = Mutex::new(vec![]);
+}
+
+#[derive(Clone)]
 pub enum Database {
     SQLite(sqlite::Database)
 }
 
+impl Database {
+    pub fn id(&self) -> &str {
+        match self {
+            Database::SQLite(db) => db.id(),
+        }
+    }
+
+    /// Checks that a connection can be had from the pool.
+    pub async fn check(&self) -> Result<(), DbError> {
+        match self {
+            Database::SQLite(db) => {
+                match tokio::time::timeout(CHECK_TIMEOUT, db.get_connection()).await {
+                    Ok(conn) => conn.map(|_| ()),
+                    Err(_) => Err(DbError::Timeout(CHECK_TIMEOUT)),
+                }
+            }
+        }
+    }
+}
+
+/// Checks the connectivity of every database built so far.
+pub async fn check_databases() -> Vec<(String, Result<(), DbError>)> {
+    let databases = DATABASES.lock().expect("databases lock poisoned").clone();
+    let mut results = Vec::with_capacity(databases.len());
+    for db in databases {
+        results.push((db.id().to_string(), db.check().await));
+    }
+    results
+}
+
 pub async fn build_db(
     name: &str,
     up: Vec<MigrationDef>,
@@ -30,11 +74,13 @@ pub async fn build_db(
     schema_version: Option<Version>,
 ) -> Result<Database, DbError> {
     let config = sqlite::DbConfig::read(name);
-    match sqlite::build_main_db(config).await {
+    match sqlite::build_main_db(name, config).await {
         Ok(db) => {
             let migrator = Migrator::new(&db, up, down).await?;
             migrator.migrate_up(&db, schema_version).await?;
-            Ok(Database::SQLite(db))
+            let db = Database::SQLite(db);
+            DATABASES.lock().expect("databases lock poisoned").push(db.clone());
+            Ok(db)
         }
         Err(e) => Err(e),
     }
@@ -2,7 +2,11 @@ use crate::config;
 use crate::config::main_config;
 use crate::db::migrations::{MigrationDef, Version};
 use crate::db::DbError;
+use crate::metrics;
 use chrono::{DateTime, Local};
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{IntGauge, IntGaugeVec, Opts};
 use rust_embed::Embed;
 use serde::Deserialize;
 use sqlx::pool::PoolConnection;
@@ -26,19 +30,24 @@ impl DbConfig {
     }
 }
 
+#[derive(Clone)]
 pub struct Database {
     id: String,
     pub delegate: SqlitePool,
 }
 
 impl Database {
+    pub fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
     pub async fn get_connection(&self) -> Result<PoolConnection<Sqlite>, DbError> {
         let conn = self.delegate.acquire().await?;
         Ok(conn)
     }
 }
 
-pub async fn build_main_db(config: DbConfig) -> Result<Database, DbError> {
+pub async fn build_main_db(name: &str, config: DbConfig) -> Result<Database, DbError> {
     let db = SqlitePool::connect_with(
         SqliteConnectOptions::new()
             .filename(config.filename.unwrap())
@@ -46,12 +55,62 @@ pub async fn build_main_db(config: DbConfig) -> Result<Database, DbError> {
             .auto_vacuum(SqliteAutoVacuum::Incremental)
     ).await?;
 
+    metrics::register(metrics::registry(), PoolMetrics::new(name, db.clone()));
+
     Ok(Database {
-        id: "main".to_string(),
+        id: name.to_string(),
         delegate: db,
     })
 }
 
+/// Connection usage of a pool, read whenever metrics are gathered.
+struct PoolMetrics {
+    pool: SqlitePool,
+    connections: IntGaugeVec,
+    max_connections: IntGauge,
+}
+
+impl PoolMetrics {
+    fn new(name: &str, pool: SqlitePool) -> Self {
+        let connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Open connections of the database pool")
+                .const_label("db", name),
+            &["state"],
+        )
+        .expect("pool connections metric is valid");
+        let max_connections = IntGauge::with_opts(
+            Opts::new("db_pool_max_connections", "Connections the database pool may open")
+                .const_label("db", name),
+        )
+        .expect("pool max connections metric is valid");
+        Self {
+            pool,
+            connections,
+            max_connections,
+        }
+    }
+}
+
+impl Collector for PoolMetrics {
+    fn desc(&self) -> Vec<&Desc> {
+        let mut desc = self.connections.desc();
+        desc.extend(self.max_connections.desc());
+        desc
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let size = self.pool.size() as i64;
+        let idle = self.pool.num_idle() as i64;
+        self.connections.with_label_values(&["active"]).set(size - idle);
+        self.connections.with_label_values(&["idle"]).set(idle);
+        self.max_connections
+            .set(self.pool.options().get_max_connections() as i64);
+        let mut families = self.connections.collect();
+        families.extend(self.max_connections.collect());
+        families
+    }
+}
+
 #[derive(Embed)]
 #[folder = "resources/migrations/sqlite/"]
 struct MigrationDefs;
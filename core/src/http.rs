@@ -1,21 +1,38 @@
 use crate::config::main_config;
-use crate::registry::RegistryError;
-use crate::{config, registry};
+use crate::registry::{HealthStatus, RegistryError, RunState, ServiceStatus};
+use crate::{config, db, metrics, registry};
 use async_trait::async_trait;
-use axum::extract::Request;
-use axum::response::IntoResponse;
-use axum::routing::{MethodRouter, Route};
-use axum::Router;
-use serde::Deserialize;
+use axum::extract::{MatchedPath, Request};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, MethodRouter, Route};
+use axum::{middleware, Json, Router};
+use lazy_static::lazy_static;
+use prometheus::{HistogramOpts, HistogramVec, Registry, TEXT_FORMAT};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::convert::Infallible;
 use std::io;
+use std::time::Instant;
 use thiserror::Error;
 use tokio::net::TcpListener;
 use tokio_util::sync::CancellationToken;
 use tower_layer::Layer;
 use tower_service::Service;
 
+lazy_static! {
+    static ref REQUEST_DURATION: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "http_request_duration_seconds",
+            "Time taken to handle API requests",
+        ),
+        &["method", "route", "status"],
+    )
+    .expect("request duration metric is valid");
+}
+
 #[derive(Deserialize)]
 pub struct ServerConfig {
     host: String,
@@ -32,6 +49,33 @@ impl ServerConfig {
     }
 }
 
+#[derive(Serialize)]
+struct DatabaseHealth {
+    id: String,
+    status: HealthStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HealthReport {
+    status: HealthStatus,
+    services: Vec<ServiceStatus>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    databases: Vec<DatabaseHealth>,
+}
+
+impl HealthReport {
+    /// Answers 503 if the runtime is down, 200 otherwise.
+    fn into_response(self) -> impl IntoResponse {
+        let code = match self.status {
+            HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::OK,
+        };
+        (code, Json(self))
+    }
+}
+
 pub struct HttpServer {
     config: ServerConfig,
     api_delegate: Router,
@@ -117,6 +161,91 @@ impl HttpServer {
         router
     }
 
+    /// Times every API request, labelled with the route it matched rather than its path, so
+    /// path parameters don't blow up the label set.
+    async fn track_latency(request: Request, next: Next) -> Response {
+        let start = Instant::now();
+        let method = request.method().to_string();
+        let route = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or_default();
+        let response = next.run(request).await;
+        REQUEST_DURATION
+            .with_label_values(&[method.as_str(), route.as_str(), response.status().as_str()])
+            .observe(start.elapsed().as_secs_f64());
+        response
+    }
+
+    async fn metrics() -> impl IntoResponse {
+        ([(CONTENT_TYPE, TEXT_FORMAT)], metrics::gather())
+    }
+
+    /// Liveness: every registered service is still running.
+    async fn healthz() -> impl IntoResponse {
+        let services = registry::service_states();
+        let status = if services
+            .iter()
+            .all(|service| service.state == RunState::Running)
+        {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        };
+        HealthReport {
+            status,
+            services,
+            databases: vec![],
+        }
+        .into_response()
+    }
+
+    /// Readiness: every service runs and reports itself up, and every database hands out
+    /// connections. Not ready while shutting down.
+    async fn readyz() -> impl IntoResponse {
+        let services = registry::service_health().await;
+        let databases: Vec<DatabaseHealth> = db::check_databases()
+            .await
+            .into_iter()
+            .map(|(id, result)| match result {
+                Ok(_) => DatabaseHealth {
+                    id,
+                    status: HealthStatus::Up,
+                    error: None,
+                },
+                Err(e) => DatabaseHealth {
+                    id,
+                    status: HealthStatus::Down,
+                    error: Some(e.to_string()),
+                },
+            })
+            .collect();
+        let statuses = services
+            .iter()
+            .map(|service| match (service.state, &service.health) {
+                (RunState::Running, Some(health)) => health.status,
+                (RunState::Running, None) => HealthStatus::Up,
+                _ => HealthStatus::Down,
+            })
+            .chain(databases.iter().map(|db| db.status));
+        let status = if registry::shutting_down() {
+            HealthStatus::Down
+        } else {
+            statuses.fold(HealthStatus::Up, |worst, status| match (worst, status) {
+                (HealthStatus::Down, _) | (_, HealthStatus::Down) => HealthStatus::Down,
+                (HealthStatus::Degraded, _) | (_, HealthStatus::Degraded) => HealthStatus::Degraded,
+                _ => HealthStatus::Up,
+            })
+        };
+        HealthReport {
+            status,
+            services,
+            databases,
+        }
+        .into_response()
+    }
+
     async fn shutdown(token: CancellationToken) -> () {
         token.cancelled().await;
         log::debug!("HTTP server shutting down.");
@@ -140,8 +269,16 @@ impl registry::Service for HttpServer {
     }
 
     async fn start(&self, token: CancellationToken) -> Result<(), RegistryError> {
-        let root_router = self.root_delegate.clone();
-        let api_router = self.api_delegate.clone();
+        let root_router = self
+            .root_delegate
+            .clone()
+            .route("/metrics", get(Self::metrics))
+            .route("/healthz", get(Self::healthz))
+            .route("/readyz", get(Self::readyz));
+        let api_router = self
+            .api_delegate
+            .clone()
+            .route_layer(middleware::from_fn(Self::track_latency));
         let host = self.config.host.clone();
         let port = self.config.port;
         log::debug!("Starting HTTP server.");
@@ -174,4 +311,8 @@ impl registry::Service for HttpServer {
             }),
         }
     }
+
+    fn register_metrics(&self, registry: &Registry) {
+        metrics::register(registry, REQUEST_DURATION.clone());
+    }
 }
@@ -0,0 +1,36 @@
+use lazy_static::lazy_static;
+use prometheus::core::Collector;
+use prometheus::{Encoder, Registry, TextEncoder};
+
+/// Prefix of every metric the runtime exports.
+pub const NAMESPACE: &str = "flwrs";
+
+lazy_static! {
+    static ref REGISTRY: Registry = Registry::new_custom(Some(NAMESPACE.to_string()), None)
+        .expect("metrics namespace is valid");
+}
+
+/// The registry shared by all services, exported on `/metrics`.
+pub fn registry() -> &'static Registry {
+    &REGISTRY
+}
+
+/// Adds a collector to a registry. A collector that cannot be registered, e.g. because its
+/// metrics are registered already, is logged and left out of the export.
+pub fn register<C>(registry: &Registry, collector: C)
+where
+    C: Collector + 'static,
+{
+    if let Err(e) = registry.register(Box::new(collector)) {
+        log::warn!("Metrics: failed to register collector: {e}");
+    }
+}
+
+/// All metrics of the shared registry, in the Prometheus text format.
+pub fn gather() -> String {
+    let mut buffer = vec![];
+    if let Err(e) = TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer) {
+        log::error!("Metrics: failed to encode metrics: {e}");
+    }
+    String::from_utf8_lossy(&buffer).into_owned()
+}
@@ -1,8 +1,12 @@
+use crate::metrics;
 use async_trait::async_trait;
 use lazy_static::lazy_static;
+use prometheus::Registry;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt::Debug;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
 use thiserror::Error;
 use tokio::signal;
 use tokio_util::sync::CancellationToken;
@@ -10,6 +14,7 @@ use tokio_util::task::TaskTracker;
 
 lazy_static! {
     static ref SHUTDOWN: Arc<CancellationToken> = Arc::new(CancellationToken::new());
+    static ref REGISTRATIONS: RwLock<Vec<Arc<Registration>>> = RwLock::new(vec![]);
 }
 
 async fn shutdown() -> Result<(), RegistryError> {
@@ -39,25 +44,171 @@ async fn shutdown() -> Result<(), RegistryError> {
             log::debug!("received SIGINT, shutting down");
             SHUTDOWN.cancel();
         }
+        _ = SHUTDOWN.cancelled() => {
+            log::debug!("shutdown requested");
+        }
     }
 
     Ok(())
 }
 
+/// Where a registered service is in its life.
+#[derive(Serialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunState {
+    Registered,
+    Running,
+    Exited,
+    Failed,
+}
+
+/// A registered service and what became of it.
+struct Registration {
+    service: &'static dyn Service,
+    state: Mutex<(RunState, Option<String>)>,
+}
+
+impl Registration {
+    fn set_state(&self, state: RunState, error: Option<String>) {
+        *self.state.lock().expect("service state lock poisoned") = (state, error);
+    }
+
+    fn status(&self) -> ServiceStatus {
+        let (state, error) = self
+            .state
+            .lock()
+            .expect("service state lock poisoned")
+            .clone();
+        ServiceStatus {
+            id: self.service.id(),
+            state,
+            error,
+            health: None,
+        }
+    }
+}
+
+/// How healthy a service considers itself. A degraded service still does its job.
+#[derive(Serialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Degraded,
+    Down,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Health {
+    pub status: HealthStatus,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, String>,
+}
+
+impl Health {
+    pub fn new(status: HealthStatus) -> Self {
+        Self {
+            status,
+            details: BTreeMap::new(),
+        }
+    }
+
+    pub fn up() -> Self {
+        Self::new(HealthStatus::Up)
+    }
+
+    pub fn down(reason: &str) -> Self {
+        Self::new(HealthStatus::Down).with_detail("reason", reason)
+    }
+
+    pub fn with_detail<V: ToString>(mut self, key: &str, value: V) -> Self {
+        self.details.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+/// Run state of a registered service, and its own health report if asked for.
+#[derive(Serialize, Clone, Debug)]
+pub struct ServiceStatus {
+    pub id: String,
+    pub state: RunState,
+    /// Why the service failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<Health>,
+}
+
+/// Run states of all registered services, without asking them.
+pub fn service_states() -> Vec<ServiceStatus> {
+    REGISTRATIONS
+        .read()
+        .expect("registrations lock poisoned")
+        .iter()
+        .map(|registration| registration.status())
+        .collect()
+}
+
+/// Run states of all registered services, along with the health reported by the running
+/// ones.
+pub async fn service_health() -> Vec<ServiceStatus> {
+    let registrations: Vec<Arc<Registration>> = REGISTRATIONS
+        .read()
+        .expect("registrations lock poisoned")
+        .clone();
+    let mut statuses = Vec::with_capacity(registrations.len());
+    for registration in registrations {
+        let mut status = registration.status();
+        if status.state == RunState::Running {
+            status.health = Some(registration.service.health().await);
+        }
+        statuses.push(status);
+    }
+    statuses
+}
+
+/// Whether the registry was told to shut down.
+pub fn shutting_down() -> bool {
+    SHUTDOWN.is_cancelled()
+}
+
+/// Tells the registry to shut down, as a termination signal would.
+pub fn request_shutdown() {
+    SHUTDOWN.cancel();
+}
+
 pub struct ServiceRegistry {
-    services: Vec<Arc<&'static dyn Service>>,
+    services: Vec<Arc<Registration>>,
     tracker: TaskTracker,
 }
 
 impl ServiceRegistry {
     pub fn register_service(&mut self, service: &'static dyn Service) {
-        self.services.push(Arc::new(service));
+        service.register_metrics(metrics::registry());
+        let registration = Arc::new(Registration {
+            service,
+            state: Mutex::new((RunState::Registered, None)),
+        });
+        REGISTRATIONS
+            .write()
+            .expect("registrations lock poisoned")
+            .push(registration.clone());
+        self.services.push(registration);
     }
 
     pub async fn start(&self) -> Result<(), RegistryError> {
-        self.services.iter().for_each(|service| {
-            log::debug!("Starting service: [{id}]", id = service.id());
-            self.tracker.spawn(service.start(SHUTDOWN.as_ref().clone()));
+        self.services.iter().for_each(|registration| {
+            let registration = registration.clone();
+            log::debug!("Starting service: [{id}]", id = registration.service.id());
+            registration.set_state(RunState::Running, None);
+            self.tracker.spawn(async move {
+                match registration.service.start(SHUTDOWN.as_ref().clone()).await {
+                    Ok(_) => registration.set_state(RunState::Exited, None),
+                    Err(e) => {
+                        log::error!("Service [{id}] failed: {e}", id = registration.service.id());
+                        registration.set_state(RunState::Failed, Some(e.to_string()));
+                    }
+                }
+            });
         });
 
         log::debug!("Registry waiting on tasks to complete");
@@ -102,4 +253,14 @@ type RegistrySourceError = Box<dyn Error + Send>;
 pub trait Service: Sync + Send {
     fn id(&self) -> String;
     async fn start(&self, shutdown_token: CancellationToken) -> Result<(), RegistryError>;
+
+    /// Adds the metrics of the service to the shared registry. Called once, when the
+    /// service is registered.
+    fn register_metrics(&self, _registry: &Registry) {}
+
+    /// Reports whether the service can do its job, for the readiness probe. Only asked while
+    /// the service runs.
+    async fn health(&self) -> Health {
+        Health::up()
+    }
 }
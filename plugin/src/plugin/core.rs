@@ -1,18 +1,25 @@
+use crate::plugin::error::ReconfigureError;
+use crate::plugin::framing::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use crate::schema::common::plugin_type::Enum as PluginType;
+use crate::schema::common::Reconfigured;
 use crate::schema::schema::{
-    field_type::Enum as FieldType, FieldDefinition as PbFieldDefinition,
-    SchemaDefinition as PbSchemaDefinition,
+    field_type::Enum as FieldType, field_value::Value, ConfigOption as PbConfigOption, Field,
+    FieldDefinition as PbFieldDefinition, FieldValue, SchemaDefinition as PbSchemaDefinition,
 };
 use crate::schema::sink::Initialize as SinkInitialize;
 use crate::schema::source::Initialize as SourceInitialize;
 use crate::schema::transform::Initialize as TransformInitialize;
 use prost::alloc::boxed::Box as PbBox;
 use prost::alloc::string::String;
+use std::collections::HashMap;
 
 pub struct InitializeRequest {
-    id: String,
-    version: String,
-    in_schema: SchemaDefinition,
-    out_schema: SchemaDefinition,
+    pub(crate) id: String,
+    pub(crate) version: String,
+    pub(crate) description: Option<String>,
+    pub(crate) in_schema: SchemaDefinition,
+    pub(crate) out_schema: SchemaDefinition,
+    pub(crate) config_options: Vec<ConfigOption>,
 }
 
 impl InitializeRequest {
@@ -20,8 +27,10 @@ impl InitializeRequest {
         Self {
             id: String::new(),
             version: String::new(),
+            description: None,
             in_schema: SchemaDefinition::new(),
             out_schema: SchemaDefinition::new(),
+            config_options: Vec::new(),
         }
     }
 
@@ -35,10 +44,30 @@ impl InitializeRequest {
         self
     }
 
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
     pub fn with_schema(mut self, schema: SchemaDefinition) -> Self {
         self.in_schema = schema;
         self
     }
+
+    pub fn with_in_schema(mut self, schema: SchemaDefinition) -> Self {
+        self.in_schema = schema;
+        self
+    }
+
+    pub fn with_out_schema(mut self, schema: SchemaDefinition) -> Self {
+        self.out_schema = schema;
+        self
+    }
+
+    pub fn add_config_option(mut self, option: ConfigOption) -> Self {
+        self.config_options.push(option);
+        self
+    }
 }
 
 impl Into<SinkInitialize> for InitializeRequest {
@@ -47,6 +76,12 @@ impl Into<SinkInitialize> for InitializeRequest {
             plugin_id: self.id,
             plugin_version: self.version,
             schema: Some(self.in_schema.into()),
+            plugin_type: PluginType::Sink as i32,
+            auth_token: auth_token(),
+            node_id: node_id(),
+            min_protocol_version: MIN_PROTOCOL_VERSION.to_string(),
+            max_protocol_version: PROTOCOL_VERSION.to_string(),
+            config_options: config_options(self.config_options),
         }
     }
 }
@@ -57,6 +92,12 @@ impl Into<SourceInitialize> for InitializeRequest {
             plugin_id: self.id,
             plugin_version: self.version,
             schema: Some(self.out_schema.into()),
+            plugin_type: PluginType::Source as i32,
+            auth_token: auth_token(),
+            node_id: node_id(),
+            min_protocol_version: MIN_PROTOCOL_VERSION.to_string(),
+            max_protocol_version: PROTOCOL_VERSION.to_string(),
+            config_options: config_options(self.config_options),
         }
     }
 }
@@ -68,12 +109,18 @@ impl Into<TransformInitialize> for InitializeRequest {
             plugin_version: self.version,
             in_schema: Some(self.in_schema.into()),
             out_schema: Some(self.out_schema.into()),
+            plugin_type: PluginType::Transform as i32,
+            auth_token: auth_token(),
+            node_id: node_id(),
+            min_protocol_version: MIN_PROTOCOL_VERSION.to_string(),
+            max_protocol_version: PROTOCOL_VERSION.to_string(),
+            config_options: config_options(self.config_options),
         }
     }
 }
 
 pub struct SchemaDefinition {
-    fields: Vec<FieldDefinition>,
+    pub(crate) fields: Vec<FieldDefinition>,
 }
 
 impl SchemaDefinition {
@@ -107,11 +154,11 @@ impl Into<PbSchemaDefinition> for SchemaDefinition {
 
 #[derive(Clone)]
 pub struct FieldDefinition {
-    key: String,
-    description: Option<String>,
-    type_: FieldType,
-    nested_type_definition: Option<Box<FieldDefinition>>,
-    object_fields: Option<Vec<FieldDefinition>>,
+    pub(crate) key: String,
+    pub(crate) description: Option<String>,
+    pub(crate) type_: FieldType,
+    pub(crate) nested_type_definition: Option<Box<FieldDefinition>>,
+    pub(crate) object_fields: Option<Vec<FieldDefinition>>,
 }
 
 impl FieldDefinition {
@@ -193,7 +240,219 @@ impl Into<PbFieldDefinition> for Box<FieldDefinition> {
     }
 }
 
+/// A setting the plugin accepts, declared to the runtime when the plugin connects and listed
+/// in its manifest.
+#[derive(Clone)]
+pub struct ConfigOption {
+    pub(crate) key: String,
+    pub(crate) description: Option<String>,
+    pub(crate) type_: FieldType,
+    pub(crate) required: bool,
+    pub(crate) default_value: Option<String>,
+}
+
+impl ConfigOption {
+    pub fn new() -> Self {
+        Self {
+            key: String::new(),
+            description: None,
+            type_: FieldType::String,
+            required: false,
+            default_value: None,
+        }
+    }
+
+    pub fn with_key(mut self, key: String) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    pub fn with_type(mut self, type_: FieldType) -> Self {
+        self.type_ = type_;
+        self
+    }
+
+    pub fn with_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    pub fn with_default_value(mut self, default_value: String) -> Self {
+        self.default_value = Some(default_value);
+        self
+    }
+}
+
+impl Default for ConfigOption {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<ConfigOption> for PbConfigOption {
+    fn from(value: ConfigOption) -> Self {
+        Self {
+            key: value.key,
+            description: value.description.unwrap_or_default(),
+            r#type: value.type_ as i32,
+            required: value.required,
+            default_value: value.default_value.unwrap_or_default(),
+        }
+    }
+}
+
+fn config_options(options: Vec<ConfigOption>) -> Vec<PbConfigOption> {
+    options.into_iter().map(From::from).collect()
+}
+
+/// The config of the node the plugin runs as, delivered by the runtime when the plugin
+/// connects. Values are typed as their options were declared, and options the node leaves out
+/// carry their default. Empty if the runtime predates plugin config.
+#[derive(Clone, Debug, Default)]
+pub struct PluginConfig {
+    values: HashMap<String, FieldValue>,
+}
+
+impl PluginConfig {
+    pub fn new(fields: Vec<Field>) -> Self {
+        Self {
+            values: fields
+                .into_iter()
+                .filter_map(|field| Some((field.key, field.value?)))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&FieldValue> {
+        self.values.get(key)
+    }
+
+    pub fn string(&self, key: &str) -> Option<&str> {
+        match self.value(key)? {
+            Value::String(value) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn i32(&self, key: &str) -> Option<i32> {
+        match self.value(key)? {
+            Value::I32(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn u32(&self, key: &str) -> Option<u32> {
+        match self.value(key)? {
+            Value::U32(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn f32(&self, key: &str) -> Option<f32> {
+        match self.value(key)? {
+            Value::F32(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn bool(&self, key: &str) -> Option<bool> {
+        match self.value(key)? {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn bytes(&self, key: &str) -> Option<&[u8]> {
+        match self.value(key)? {
+            Value::Bytes(value) => Some(value.as_slice()),
+            _ => None,
+        }
+    }
+
+    fn value(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)?.value.as_ref()
+    }
+}
+
+/// Answer to a `Reconfigure` request, from what the plugin made of the new config.
+pub(crate) fn reconfigured(
+    request_id: String,
+    result: Result<(), ReconfigureError>,
+) -> Reconfigured {
+    match result {
+        Ok(_) => {
+            log::info!("Config updated");
+            Reconfigured {
+                request_id,
+                accepted: true,
+                message: String::new(),
+            }
+        }
+        Err(err) => {
+            log::warn!("Config rejected: {}", err);
+            Reconfigured {
+                request_id,
+                accepted: false,
+                message: err.to_string(),
+            }
+        }
+    }
+}
+
+/// Environment variable the runtime hands a launched plugin its auth token in.
+pub const AUTH_TOKEN_ENV: &str = "FLWRS_PLUGIN_TOKEN";
+
+/// Environment variable the runtime hands a plugin launched for a scene node the node ID in.
+pub const NODE_ID_ENV: &str = "FLWRS_NODE_ID";
+
+/// Environment variable with the CA certificates, PEM encoded, to verify the hub with. When it
+/// is set, the plugin connects to the hub over TLS.
+pub const TLS_CA_ENV: &str = "FLWRS_HUB_TLS_CA";
+
+/// Environment variable with the name expected in the hub certificate, if it's not the hub host.
+pub const TLS_SERVER_NAME_ENV: &str = "FLWRS_HUB_TLS_SERVER_NAME";
+
+/// Environment variables with the client certificate chain and key, PEM encoded, the plugin
+/// authenticates to the hub with over TLS.
+pub const TLS_CERT_ENV: &str = "FLWRS_PLUGIN_TLS_CERT";
+pub const TLS_KEY_ENV: &str = "FLWRS_PLUGIN_TLS_KEY";
+
+/// Scheme of hub addresses that point at a Unix domain socket, e.g. `unix:///run/flwrs/hub.sock`.
+pub const UNIX_SCHEME: &str = "unix://";
+
+/// The token the plugin authenticates with, empty if it was given none.
+fn auth_token() -> String {
+    std::env::var(AUTH_TOKEN_ENV).unwrap_or_default()
+}
+
+/// The scene node the plugin runs as, empty if it was not launched for one.
+fn node_id() -> String {
+    std::env::var(NODE_ID_ENV).unwrap_or_default()
+}
+
+/// Where the hub listens. `host` may also be a Unix domain socket address such as
+/// `unix:///run/flwrs/hub.sock`, in which case `port` is ignored.
 pub struct ConnectionConfig {
     pub host: String,
     pub port: u16,
 }
+
+impl ConnectionConfig {
+    /// The address the runners connect to.
+    pub fn address(&self) -> String {
+        if self.host.starts_with(UNIX_SCHEME) {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
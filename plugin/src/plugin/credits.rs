@@ -0,0 +1,155 @@
+use crate::schema::common::Credit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::{watch, Semaphore};
+
+/// Number of events a plugin lets the runtime deliver before waiting for the plugin to be
+/// done with some of them.
+pub const CREDIT_WINDOW: u32 = 64;
+
+/// Credit granted by the runtime. Every event sent to the runtime uses up one credit. While
+/// the runtime has the plugin paused, no credit is taken. Once closed, no more events are
+/// allowed.
+pub(crate) struct Credits {
+    available: Semaphore,
+    /// Set for runtimes speaking a protocol version without credit, which take events as
+    /// they come.
+    unlimited: AtomicBool,
+    paused: watch::Sender<bool>,
+}
+
+impl Credits {
+    pub(crate) fn new() -> Self {
+        Self {
+            available: Semaphore::new(0),
+            unlimited: AtomicBool::new(false),
+            paused: watch::Sender::new(false),
+        }
+    }
+
+    /// Waits until the runtime allows one more event and takes that credit. Returns `false`
+    /// once the credits are closed.
+    pub(crate) async fn acquire(&self) -> bool {
+        let mut paused = self.paused.subscribe();
+        loop {
+            paused
+                .wait_for(|paused| !paused)
+                .await
+                .expect("pause sender is never dropped");
+            if self.available.is_closed() {
+                return false;
+            }
+            if self.unlimited.load(Ordering::Acquire) {
+                return true;
+            }
+            let Ok(credit) = self.available.acquire().await else {
+                return false;
+            };
+            // paused while waiting for credit: leave the credit for after the resume
+            if !*self.paused.borrow() {
+                credit.forget();
+                return true;
+            }
+        }
+    }
+
+    /// Stops allowing events, waking up everything waiting for credit.
+    pub(crate) fn close(&self) {
+        self.available.close();
+        self.paused.send_replace(false);
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.available.is_closed()
+    }
+
+    /// Stops counting credit, for runtimes that grant none.
+    pub(crate) fn unlimit(&self) {
+        self.unlimited.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn grant(&self, credit: Credit) {
+        self.available.add_permits(credit.amount as usize);
+    }
+
+    pub(crate) fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    pub(crate) fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+}
+
+/// Counts events the plugin is done with and hands them back to the runtime as credit, in
+/// batches of half the window.
+pub(crate) struct CreditReturns {
+    batch: u32,
+    pending: u32,
+}
+
+impl CreditReturns {
+    pub(crate) fn new() -> Self {
+        Self {
+            batch: (CREDIT_WINDOW / 2).max(1),
+            pending: 0,
+        }
+    }
+
+    /// Credit the plugin opens its session with.
+    pub(crate) fn initial(&self) -> Credit {
+        Credit {
+            amount: CREDIT_WINDOW,
+        }
+    }
+
+    /// Records one processed event. Returns the credit to grant once a batch is complete.
+    pub(crate) fn processed(&mut self) -> Option<Credit> {
+        self.pending += 1;
+        if self.pending < self.batch {
+            return None;
+        }
+        let amount = std::mem::take(&mut self.pending);
+        Some(Credit { amount })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn closing_stops_waiting_for_credit() {
+        let credits = std::sync::Arc::new(Credits::new());
+        credits.grant(Credit { amount: 1 });
+        assert!(credits.acquire().await);
+
+        credits.pause();
+        let waiting = tokio::spawn({
+            let credits = credits.clone();
+            async move { credits.acquire().await }
+        });
+        credits.close();
+        assert!(!waiting.await.unwrap());
+        assert!(credits.is_closed());
+
+        credits.unlimit();
+        assert!(!credits.acquire().await);
+    }
+
+    #[test]
+    fn initial_credit_is_the_window() {
+        assert_eq!(CreditReturns::new().initial().amount, CREDIT_WINDOW);
+    }
+
+    #[test]
+    fn processed_events_are_returned_in_batches() {
+        let mut returns = CreditReturns::new();
+        let batch = CREDIT_WINDOW / 2;
+        for round in 0..3 {
+            for _ in 1..batch {
+                assert_eq!(returns.processed(), None, "round {round}");
+            }
+            assert_eq!(returns.processed(), Some(Credit { amount: batch }), "round {round}");
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::plugin::manifest::ManifestError;
 use prost::DecodeError;
 use std::fmt::{Display, Formatter};
 
@@ -17,6 +18,8 @@ pub enum Error {
     SetLoggerError(#[from] log::SetLoggerError),
     #[error("Invalid message: {0}")]
     InvalidMessage(#[from] DecodeError),
+    #[error("Manifest error: {0}")]
+    ManifestError(#[from] ManifestError),
 }
 
 #[derive(Debug)]
@@ -36,6 +39,23 @@ impl std::error::Error for InitializeError {
     }
 }
 
+#[derive(Debug)]
+pub struct ReconfigureError {
+    pub source: Box<dyn std::error::Error>,
+}
+
+impl Display for ReconfigureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source.to_string())
+    }
+}
+
+impl std::error::Error for ReconfigureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
 #[derive(Debug)]
 pub struct ShutdownError {
     pub source: Box<dyn std::error::Error>,
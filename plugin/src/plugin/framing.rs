@@ -0,0 +1,274 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use bytes::Bytes;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::Cursor;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Version of the runtime↔plugin protocol, carried in the header of every frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ProtocolVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+/// The original protocol.
+pub const PROTOCOL_VERSION_1_0: ProtocolVersion = ProtocolVersion::new(1, 0, 0);
+/// Adds heartbeats: the runtime pings, the plugin answers with a pong.
+pub const PROTOCOL_VERSION_1_1: ProtocolVersion = ProtocolVersion::new(1, 1, 0);
+/// Adds live reconfiguration: the runtime sends a new config, the plugin accepts or rejects it.
+pub const PROTOCOL_VERSION_1_2: ProtocolVersion = ProtocolVersion::new(1, 2, 0);
+/// Adds pausing: the runtime pauses and resumes a running plugin.
+pub const PROTOCOL_VERSION_1_3: ProtocolVersion = ProtocolVersion::new(1, 3, 0);
+/// Adds flow control: both sides grant each other credit for events, and plugins ack or nack
+/// the events delivered to them.
+pub const PROTOCOL_VERSION_1_4: ProtocolVersion = ProtocolVersion::new(1, 4, 0);
+
+/// Largest frame either side accepts, header included. The length of a frame is read before
+/// anything else about the peer is known, so it is checked before the frame is buffered.
+pub const MAX_FRAME_LEN: u32 = 16 * 1024 * 1024;
+
+/// Oldest protocol version this crate still speaks.
+pub const MIN_PROTOCOL_VERSION: ProtocolVersion = PROTOCOL_VERSION_1_0;
+/// Newest protocol version this crate speaks.
+pub const PROTOCOL_VERSION: ProtocolVersion = PROTOCOL_VERSION_1_4;
+
+impl ProtocolVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses a `major.minor.patch` version.
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.split('.').map(|part| part.parse::<u32>().ok());
+        let version = Self::new(parts.next()??, parts.next()??, parts.next()??);
+        match parts.next() {
+            None => Some(version),
+            Some(_) => None,
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(self)
+    }
+
+    /// The highest version both this crate and the other side, speaking `min..=max`, support.
+    pub fn negotiate(min: ProtocolVersion, max: ProtocolVersion) -> Option<ProtocolVersion> {
+        let version = max.min(PROTOCOL_VERSION);
+        (version >= min.max(MIN_PROTOCOL_VERSION)).then_some(version)
+    }
+
+    pub fn has_heartbeats(&self) -> bool {
+        *self >= PROTOCOL_VERSION_1_1
+    }
+
+    pub fn has_reconfigure(&self) -> bool {
+        *self >= PROTOCOL_VERSION_1_2
+    }
+
+    pub fn has_pause(&self) -> bool {
+        *self >= PROTOCOL_VERSION_1_3
+    }
+
+    pub fn has_credits(&self) -> bool {
+        *self >= PROTOCOL_VERSION_1_4
+    }
+
+    pub fn has_acks(&self) -> bool {
+        *self >= PROTOCOL_VERSION_1_4
+    }
+}
+
+impl Display for ProtocolVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Writes a single frame: `[packet length][header length][header][message]`,
+/// all lengths being little-endian `u32`s and the header being the protocol version.
+pub async fn write_frame<W>(writer: &mut W, version: ProtocolVersion, msg: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let version = version.to_string();
+    let mut header = vec![];
+    WriteBytesExt::write_u32::<LittleEndian>(&mut header, version.len() as u32)?;
+    std::io::Write::write_all(&mut header, version.as_bytes())?;
+
+    let total_len = header.len() + msg.len();
+    if total_len > MAX_FRAME_LEN as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Frame of [{total_len}] bytes is larger than [{MAX_FRAME_LEN}]"),
+        ));
+    }
+
+    let mut packet_len = vec![];
+    WriteBytesExt::write_u32::<LittleEndian>(&mut packet_len, total_len as u32)?;
+
+    let packet = [&packet_len, &header, msg].concat();
+    writer.write_all(packet.as_slice()).await?;
+    writer.flush().await
+}
+
+/// Reads a single frame written by [`write_frame`], along with the protocol version it was
+/// written in. Returns `None` if the other side closed the connection. Frames longer than
+/// [`MAX_FRAME_LEN`] are rejected before they are read.
+pub async fn read_frame<R>(reader: &mut R) -> io::Result<Option<(ProtocolVersion, Bytes)>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut packet_len_bytes = vec![0u8; 4];
+    match reader.read_exact(&mut packet_len_bytes).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut packet_len_reader = Cursor::new(&packet_len_bytes);
+    let packet_len = ReadBytesExt::read_u32::<LittleEndian>(&mut packet_len_reader)?;
+    if packet_len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Frame of [{packet_len}] bytes is larger than [{MAX_FRAME_LEN}]"),
+        ));
+    }
+
+    let mut buf = vec![0; packet_len as usize];
+    reader.read_exact(&mut buf).await?;
+    let buf_bytes = Bytes::from(buf);
+
+    let mut reader = Cursor::new(&buf_bytes);
+    let header_len = ReadBytesExt::read_u32::<LittleEndian>(&mut reader)?;
+    let msg_start = match header_len.checked_add(4) {
+        Some(msg_start) if msg_start <= packet_len => msg_start as usize,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Header length mismatch")),
+    };
+    let mut header_buf = vec![0u8; header_len as usize];
+    std::io::Read::read_exact(&mut reader, &mut header_buf)?;
+    let version = match std::str::from_utf8(&header_buf)
+        .ok()
+        .and_then(ProtocolVersion::parse)
+    {
+        Some(version) if version.is_supported() => version,
+        _ => {
+            return Err(io::Error::other(format!(
+                "Unsupported protocol version [{}]",
+                String::from_utf8_lossy(&header_buf)
+            )));
+        }
+    };
+
+    Ok(Some((version, buf_bytes.slice(msg_start..))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(packet_len: u32, header_len: u32, rest: &[u8]) -> Vec<u8> {
+        [&packet_len.to_le_bytes()[..], &header_len.to_le_bytes()[..], rest].concat()
+    }
+
+    #[tokio::test]
+    async fn reads_what_write_frame_writes() {
+        let mut buf = vec![];
+        write_frame(&mut buf, PROTOCOL_VERSION_1_4, b"message").await.unwrap();
+        let (version, msg) = read_frame(&mut buf.as_slice()).await.unwrap().unwrap();
+        assert_eq!(version, PROTOCOL_VERSION_1_4);
+        assert_eq!(&msg[..], b"message");
+        assert!(read_frame(&mut [].as_slice()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_frames_over_the_limit_before_reading_them() {
+        let buf = (MAX_FRAME_LEN + 1).to_le_bytes();
+        let err = read_frame(&mut buf.as_slice()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut sink = vec![];
+        let msg = vec![0u8; MAX_FRAME_LEN as usize];
+        let err = write_frame(&mut sink, PROTOCOL_VERSION, &msg).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(sink.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_header_lengths_past_the_frame() {
+        for header_len in [5, u32::MAX - 3, u32::MAX] {
+            let buf = frame(8, header_len, b"1.4.");
+            let err = read_frame(&mut buf.as_slice()).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{header_len}");
+        }
+    }
+
+    #[test]
+    fn parse_reads_three_part_versions() {
+        assert_eq!(ProtocolVersion::parse("1.4.0"), Some(PROTOCOL_VERSION_1_4));
+        assert_eq!(ProtocolVersion::parse("2.10.3"), Some(ProtocolVersion::new(2, 10, 3)));
+    }
+
+    #[test]
+    fn parse_rejects_anything_else() {
+        for version in ["", "1", "1.4", "1.4.0.1", "1.x.0", "1.4.", "-1.4.0", " 1.4.0"] {
+            assert_eq!(ProtocolVersion::parse(version), None, "{version:?}");
+        }
+    }
+
+    #[test]
+    fn parse_reads_what_display_writes() {
+        let version = ProtocolVersion::new(1, 3, 7);
+        assert_eq!(ProtocolVersion::parse(version.to_string().as_str()), Some(version));
+    }
+
+    #[test]
+    fn negotiate_picks_the_highest_common_version() {
+        assert_eq!(
+            ProtocolVersion::negotiate(PROTOCOL_VERSION_1_0, ProtocolVersion::new(9, 0, 0)),
+            Some(PROTOCOL_VERSION)
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(PROTOCOL_VERSION_1_0, PROTOCOL_VERSION_1_2),
+            Some(PROTOCOL_VERSION_1_2)
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(ProtocolVersion::new(0, 1, 0), PROTOCOL_VERSION_1_0),
+            Some(PROTOCOL_VERSION_1_0)
+        );
+    }
+
+    #[test]
+    fn negotiate_fails_without_a_common_version() {
+        assert_eq!(
+            ProtocolVersion::negotiate(
+                ProtocolVersion::new(2, 0, 0),
+                ProtocolVersion::new(2, 1, 0)
+            ),
+            None
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(
+                ProtocolVersion::new(0, 1, 0),
+                ProtocolVersion::new(0, 9, 0)
+            ),
+            None
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(PROTOCOL_VERSION_1_3, PROTOCOL_VERSION_1_2),
+            None
+        );
+    }
+
+    #[test]
+    fn credits_and_acks_come_with_1_4() {
+        assert!(!PROTOCOL_VERSION_1_3.has_credits());
+        assert!(!PROTOCOL_VERSION_1_3.has_acks());
+        assert!(PROTOCOL_VERSION_1_4.has_credits());
+        assert!(PROTOCOL_VERSION_1_4.has_acks());
+    }
+}
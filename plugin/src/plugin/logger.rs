@@ -3,6 +3,9 @@ use crate::schema::common::log_level::Enum;
 use crate::schema::common::{
     log_level::Enum as LogLevel, plugin_type::Enum as PluginType, LogEvent,
 };
+use crate::schema::sink::{sink_message::Payload as SinkPayload, SinkMessage};
+use crate::schema::source::{source_message::Payload as SourcePayload, SourceMessage};
+use crate::schema::transform::{transform_message::Payload as TransformPayload, TransformMessage};
 use lazy_static::lazy_static;
 use log::{Level, Metadata, Record, SetLoggerError};
 use prost::Message;
@@ -33,10 +36,14 @@ impl PluginLogger {
         self.level = level;
     }
 
-    pub(crate) fn initialize(id: &str, log_level: Level) -> Result<(), SetLoggerError> {
+    pub(crate) fn initialize(
+        id: &str,
+        plugin_type: PluginType,
+        log_level: Level,
+    ) -> Result<(), SetLoggerError> {
         let mut logger = LOGGER.write().expect("Was expecting to lock");
         logger.set_plugin_id(id.into());
-        logger.set_plugin_type(PluginType::Sink);
+        logger.set_plugin_type(plugin_type);
         logger.set_level(log_level.into());
         log::set_logger(LOG_WRAPPER.as_ref())?;
         Ok(())
@@ -49,22 +56,35 @@ impl log::Log for PluginLogger {
     }
 
     fn log(&self, record: &Record) {
-        let log_level: LogLevel = self.level.into();
+        let log_level: LogLevel = record.level().into();
         let msg = LogEvent {
             plugin_id: self.plugin_id.to_string(),
             plugin_type: self.plugin_type as i32,
             log_level: log_level as i32,
-            message: record.args().as_str().unwrap_or("<no message>").to_string(),
+            message: record.args().to_string(),
             details: vec![],
         };
+        let packet = match self.plugin_type {
+            PluginType::Source => SourceMessage {
+                payload: Some(SourcePayload::Log(msg.clone())),
+            }
+            .encode_to_vec(),
+            PluginType::Sink => SinkMessage {
+                payload: Some(SinkPayload::Log(msg.clone())),
+            }
+            .encode_to_vec(),
+            PluginType::Transform => TransformMessage {
+                payload: Some(TransformPayload::Log(msg.clone())),
+            }
+            .encode_to_vec(),
+            PluginType::Undefined => {
+                println!("Logger not initialized: {}", msg.message);
+                return;
+            }
+        };
 
         tokio::task::spawn(async move {
-            match MSG_CLIENT
-                .read()
-                .await
-                .send(msg.encode_to_vec().as_slice())
-                .await
-            {
+            match MSG_CLIENT.read().await.send(packet.as_slice()).await {
                 Ok(_) => {}
                 Err(e) => {
                     println!("Failed to send log message: {}", e); // TODO handle this better
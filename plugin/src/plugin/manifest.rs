@@ -0,0 +1,244 @@
+use crate::plugin::core::{ConfigOption, FieldDefinition, InitializeRequest, SchemaDefinition};
+use crate::schema::common::plugin_type::Enum as PluginType;
+use crate::schema::schema::{
+    field_type::Enum as FieldType, FieldDefinition as PbFieldDefinition,
+    SchemaDefinition as PbSchemaDefinition,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Command line flag that asks a plugin binary to print its manifest and exit.
+pub const DESCRIBE_FLAG: &str = "--describe";
+
+/// Whether the plugin binary was started in describe mode.
+pub fn describe_requested() -> bool {
+    std::env::args().any(|arg| arg == DESCRIBE_FLAG)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ManifestError {
+    #[error("I/O error: {0}")]
+    IOError(#[from] std::io::Error),
+    #[error("Failed to write manifest: {0}")]
+    SerializeError(#[from] toml::ser::Error),
+    #[error("Failed to read manifest: {0}")]
+    DeserializeError(#[from] toml::de::Error),
+    #[error("Invalid manifest: {0}")]
+    Invalid(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ManifestPluginType {
+    Source,
+    Transform,
+    Sink,
+}
+
+impl From<ManifestPluginType> for PluginType {
+    fn from(value: ManifestPluginType) -> Self {
+        match value {
+            ManifestPluginType::Source => PluginType::Source,
+            ManifestPluginType::Transform => PluginType::Transform,
+            ManifestPluginType::Sink => PluginType::Sink,
+        }
+    }
+}
+
+/// Description of a plugin binary: what it is, which schemas it speaks and which settings it
+/// accepts. Plugins ship it as a TOML file and print it in describe mode.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Manifest {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub plugin_type: ManifestPluginType,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub in_schema: Vec<ManifestField>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub out_schema: Vec<ManifestField>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config: Vec<ManifestConfigOption>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestField {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Lowercase `FieldType` name, e.g. `string` or `map`.
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Element type of an `array` or `map` field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nested: Option<Box<ManifestField>>,
+    /// Fields of an `object` field.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<ManifestField>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestConfigOption {
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Lowercase `FieldType` name, e.g. `string` or `u32`.
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+impl Manifest {
+    /// Builds the manifest out of what the plugin announces when it initializes.
+    pub fn new(plugin_type: PluginType, request: &InitializeRequest) -> Self {
+        let plugin_type = match plugin_type {
+            PluginType::Source => ManifestPluginType::Source,
+            PluginType::Transform => ManifestPluginType::Transform,
+            _ => ManifestPluginType::Sink,
+        };
+        let in_schema = match plugin_type {
+            ManifestPluginType::Source => vec![],
+            _ => manifest_fields(&request.in_schema),
+        };
+        let out_schema = match plugin_type {
+            ManifestPluginType::Sink => vec![],
+            _ => manifest_fields(&request.out_schema),
+        };
+        Self {
+            id: request.id.clone(),
+            plugin_type,
+            version: request.version.clone(),
+            description: request.description.clone(),
+            in_schema,
+            out_schema,
+            config: request.config_options.iter().map(From::from).collect(),
+        }
+    }
+
+    pub fn from_toml(manifest: &str) -> Result<Self, ManifestError> {
+        let manifest: Self = toml::from_str(manifest)?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, ManifestError> {
+        Self::from_toml(std::fs::read_to_string(path)?.as_str())
+    }
+
+    pub fn to_toml(&self) -> Result<String, ManifestError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// In-schema in its protocol form, `None` if the plugin declares none.
+    pub fn in_schema(&self) -> Result<Option<PbSchemaDefinition>, ManifestError> {
+        schema_definition(&self.in_schema)
+    }
+
+    /// Out-schema in its protocol form, `None` if the plugin declares none.
+    pub fn out_schema(&self) -> Result<Option<PbSchemaDefinition>, ManifestError> {
+        schema_definition(&self.out_schema)
+    }
+
+    fn validate(&self) -> Result<(), ManifestError> {
+        if self.id.is_empty() {
+            return Err(ManifestError::Invalid("plugin ID is missing".to_string()));
+        }
+        if self.version.is_empty() {
+            return Err(ManifestError::Invalid(
+                "plugin version is missing".to_string(),
+            ));
+        }
+        self.in_schema()?;
+        self.out_schema()?;
+        for option in self.config.iter() {
+            field_type(option.type_.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+fn manifest_fields(schema: &SchemaDefinition) -> Vec<ManifestField> {
+    schema.fields.iter().map(From::from).collect()
+}
+
+fn type_name(type_: FieldType) -> String {
+    type_.as_str_name().to_lowercase()
+}
+
+fn field_type(name: &str) -> Result<FieldType, ManifestError> {
+    FieldType::from_str_name(name.to_uppercase().as_str())
+        .ok_or_else(|| ManifestError::Invalid(format!("unknown field type [{name}]")))
+}
+
+fn schema_definition(
+    fields: &[ManifestField],
+) -> Result<Option<PbSchemaDefinition>, ManifestError> {
+    if fields.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(PbSchemaDefinition {
+        fields: fields
+            .iter()
+            .map(PbFieldDefinition::try_from)
+            .collect::<Result<Vec<_>, _>>()?,
+    }))
+}
+
+impl From<&FieldDefinition> for ManifestField {
+    fn from(value: &FieldDefinition) -> Self {
+        Self {
+            key: value.key.clone(),
+            description: value.description.clone(),
+            type_: type_name(value.type_),
+            nested: value
+                .nested_type_definition
+                .as_ref()
+                .map(|nested| Box::new(ManifestField::from(nested.as_ref()))),
+            fields: value
+                .object_fields
+                .iter()
+                .flatten()
+                .map(From::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<&ManifestField> for PbFieldDefinition {
+    type Error = ManifestError;
+
+    fn try_from(value: &ManifestField) -> Result<Self, Self::Error> {
+        Ok(Self {
+            key: value.key.clone(),
+            description: value.description.clone().unwrap_or_default(),
+            r#type: field_type(value.type_.as_str())? as i32,
+            nested_type_definition: match &value.nested {
+                None => None,
+                Some(nested) => Some(Box::new(PbFieldDefinition::try_from(nested.as_ref())?)),
+            },
+            object_fields: value
+                .fields
+                .iter()
+                .map(PbFieldDefinition::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+}
+
+impl From<&ConfigOption> for ManifestConfigOption {
+    fn from(value: &ConfigOption) -> Self {
+        Self {
+            key: value.key.clone(),
+            description: value.description.clone(),
+            type_: type_name(value.type_),
+            required: value.required,
+            default: value.default_value.clone(),
+        }
+    }
+}
@@ -1,4 +1,8 @@
 pub(crate) mod logger;
 pub(crate) mod msg_client;
+pub(crate) mod tls;
 pub mod error;
-pub mod core;
\ No newline at end of file
+pub mod core;
+pub mod credits;
+pub mod framing;
+pub mod manifest;
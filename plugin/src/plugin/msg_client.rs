@@ -1,26 +1,38 @@
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use crate::plugin::core::UNIX_SCHEME;
+use crate::plugin::framing;
+use crate::plugin::framing::{ProtocolVersion, MIN_PROTOCOL_VERSION};
+use crate::plugin::tls;
 use bytes::Bytes;
 use lazy_static::lazy_static;
 use std::io;
-use std::io::Cursor;
 use std::sync::Arc;
-use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use std::sync::Mutex as StdMutex;
+use tokio::io::{AsyncRead, AsyncWrite, ReadHalf, WriteHalf};
 use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 use tokio::sync::{Mutex, RwLock};
 
 lazy_static! {
     pub(crate) static ref MSG_CLIENT: Arc<RwLock<MessagingClient>> =
         Arc::new(RwLock::new(MessagingClient::default()));
-    pub(crate) static ref PROTOCOL_VERSION: Bytes = Bytes::from("1.0.0");
 }
 
+/// A connection to the hub, over TCP or a Unix domain socket.
+pub(crate) trait Socket: AsyncRead + AsyncWrite + Send + Sync + Unpin {}
+
+impl<T> Socket for T where T: AsyncRead + AsyncWrite + Send + Sync + Unpin {}
+
 pub(crate) struct MessagingClient {
-    socket_in: Option<Mutex<ReadHalf<TcpStream>>>,
-    socket_out: Option<Mutex<WriteHalf<TcpStream>>>,
+    socket_in: Option<Mutex<ReadHalf<Box<dyn Socket>>>>,
+    socket_out: Option<Mutex<WriteHalf<Box<dyn Socket>>>>,
+    /// Protocol version of the session, settled by the first frame the hub sends. Until
+    /// then the oldest version is spoken, which every hub understands.
+    version: StdMutex<Option<ProtocolVersion>>,
 }
 
 impl MessagingClient {
-    pub(crate) fn new(socket: Option<TcpStream>) -> Self {
+    pub(crate) fn new(socket: Option<Box<dyn Socket>>) -> Self {
         let (socket_in, socket_out) = match socket {
             Some(socket) => {
                 let (socket_in, socket_out) = tokio::io::split(socket);
@@ -31,6 +43,7 @@ impl MessagingClient {
         Self {
             socket_in,
             socket_out,
+            version: StdMutex::new(None),
         }
     }
 
@@ -41,25 +54,9 @@ impl MessagingClient {
                 "MessagingClient not connected",
             )),
             Some(socket) => {
-                let mut header = vec![];
-                WriteBytesExt::write_u32::<LittleEndian>(
-                    &mut header,
-                    PROTOCOL_VERSION.len() as u32,
-                )?;
-                std::io::Write::write(&mut header, &PROTOCOL_VERSION)?;
-
-                let total_len = header.len() + msg.len();
-
-                let mut packet_len = vec![];
-                WriteBytesExt::write_u32::<LittleEndian>(&mut packet_len, total_len as u32)?;
-
-                let packet = [&packet_len, &header, msg].concat();
-
+                let version = self.protocol_version();
                 let mut sock = socket.lock().await;
-                match sock.write(packet.as_slice()).await {
-                    Ok(_) => Ok(()),
-                    Err(e) => Err(e),
-                }
+                framing::write_frame(&mut *sock, version, msg).await
             }
         }
     }
@@ -72,61 +69,53 @@ impl MessagingClient {
             )),
             Some(socket) => {
                 let mut sock = socket.lock().await;
-                let mut packet_len_bytes = vec![0u8; 4];
-                let bytes_read = sock.read(&mut packet_len_bytes).await?;
-                if bytes_read < 4 {
-                    return Ok(None);
+                match framing::read_frame(&mut *sock).await? {
+                    Some((version, bytes)) => {
+                        self.settle_version(version)?;
+                        Ok(Some(bytes))
+                    }
+                    None => Ok(None),
                 }
-                let mut packet_len_reader = Cursor::new(&packet_len_bytes);
-                let packet_len =
-                    match ReadBytesExt::read_u32::<LittleEndian>(&mut packet_len_reader) {
-                        Ok(len) => Ok(len),
-                        Err(e) => Err(e),
-                    }?;
-
-                let mut buf = vec![0; packet_len as usize];
-                sock.read_exact(&mut buf).await?;
-                let buf_bytes = Bytes::from(buf);
+            }
+        }
+    }
 
-                let mut reader = Cursor::new(&buf_bytes);
-                let header_len = ReadBytesExt::read_u32::<LittleEndian>(&mut reader)?;
-                // will support more protocol versions/headers if necessary in the future
-                let mut header_buf = vec![0u8; header_len as usize];
-                let bytes_read = std::io::Read::read(&mut reader, &mut header_buf)?;
-                if bytes_read < header_len as usize {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "Header length mismatch",
-                    ));
-                }
-                let header = Bytes::from(header_buf);
-                if PROTOCOL_VERSION.ne(&header) {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "Protocol version mismatch",
-                    ));
-                }
+    /// Protocol version the hub agreed to, once it answered.
+    pub(crate) fn version(&self) -> Option<ProtocolVersion> {
+        *self.version.lock().expect("protocol version lock poisoned")
+    }
 
-                let msg_len = packet_len - header_len - 4;
-                let mut msg_buf = vec![0u8; msg_len as usize];
-                let bytes_read = std::io::Read::read(&mut reader, &mut msg_buf)?;
-                if bytes_read < msg_len as usize {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "Message length mismatch",
-                    ));
-                }
+    /// Protocol version spoken with the hub: the agreed one, or the oldest until the hub
+    /// answered.
+    pub(crate) fn protocol_version(&self) -> ProtocolVersion {
+        self.version().unwrap_or(MIN_PROTOCOL_VERSION)
+    }
 
-                Ok(Some(Bytes::from(msg_buf)))
+    fn settle_version(&self, version: ProtocolVersion) -> io::Result<()> {
+        let mut settled = self.version.lock().expect("protocol version lock poisoned");
+        match *settled {
+            None => {
+                log::debug!("Speaking protocol version [{version}] with the hub");
+                *settled = Some(version);
+                Ok(())
             }
+            Some(settled) if settled == version => Ok(()),
+            Some(settled) => Err(io::Error::other(format!(
+                "Protocol version mismatch: [{version}], expected [{settled}]"
+            ))),
         }
     }
 
+    /// Connects to the hub at `host:port`, or at a Unix domain socket if `addr` starts with
+    /// [`UNIX_SCHEME`]. TCP connections are secured with TLS if the runtime asks for it.
     pub(crate) async fn connect(&mut self, addr: &str) -> io::Result<()> {
         if self.socket_in.is_some() && self.socket_out.is_some() {
             return Ok(());
         }
-        let socket = TcpStream::connect(addr).await?;
+        let socket: Box<dyn Socket> = match addr.strip_prefix(UNIX_SCHEME) {
+            Some(path) => connect_unix(path).await?,
+            None => tls::secure(TcpStream::connect(addr).await?, host_of(addr)).await?,
+        };
         let (socket_in, socket_out) = tokio::io::split(socket);
         self.socket_in = Some(Mutex::new(socket_in));
         self.socket_out = Some(Mutex::new(socket_out));
@@ -134,6 +123,27 @@ impl MessagingClient {
     }
 }
 
+/// The host part of a `host:port` address.
+fn host_of(addr: &str) -> &str {
+    addr.rsplit_once(':')
+        .map_or(addr, |(host, _)| host)
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+}
+
+#[cfg(unix)]
+async fn connect_unix(path: &str) -> io::Result<Box<dyn Socket>> {
+    Ok(Box::new(UnixStream::connect(path).await?))
+}
+
+#[cfg(not(unix))]
+async fn connect_unix(_path: &str) -> io::Result<Box<dyn Socket>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Unix domain sockets are not supported on this platform",
+    ))
+}
+
 impl Default for MessagingClient {
     fn default() -> Self {
         Self::new(None)
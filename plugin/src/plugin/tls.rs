@@ -0,0 +1,56 @@
+use crate::plugin::core::{TLS_CA_ENV, TLS_CERT_ENV, TLS_KEY_ENV, TLS_SERVER_NAME_ENV};
+use crate::plugin::msg_client::Socket;
+use rustls::crypto::ring;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, RootCertStore};
+use std::io;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+/// Secures a connection to the hub with TLS if the runtime handed the plugin a CA to verify
+/// the hub with, see [`TLS_CA_ENV`]. Otherwise the connection is used as is.
+pub(crate) async fn secure(stream: TcpStream, host: &str) -> io::Result<Box<dyn Socket>> {
+    let Ok(ca_path) = std::env::var(TLS_CA_ENV) else {
+        return Ok(Box::new(stream));
+    };
+    let mut roots = RootCertStore::empty();
+    for cert in certs(ca_path.as_str())? {
+        roots.add(cert).map_err(io::Error::other)?;
+    }
+    let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_root_certificates(roots);
+    let config = match (std::env::var(TLS_CERT_ENV), std::env::var(TLS_KEY_ENV)) {
+        (Ok(cert_path), Ok(key_path)) => builder
+            .with_client_auth_cert(
+                certs(cert_path.as_str())?,
+                PrivateKeyDer::from_pem_file(key_path.as_str())
+                    .map_err(|e| pem_error(key_path.as_str(), e))?,
+            )
+            .map_err(io::Error::other)?,
+        _ => builder.with_no_client_auth(),
+    };
+    let server_name = std::env::var(TLS_SERVER_NAME_ENV).unwrap_or_else(|_| host.to_string());
+    let server_name = ServerName::try_from(server_name)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let stream = TlsConnector::from(Arc::new(config))
+        .connect(server_name, stream)
+        .await?;
+    Ok(Box::new(stream))
+}
+
+fn certs(path: &str) -> io::Result<Vec<CertificateDer<'static>>> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect())
+        .map_err(|e| pem_error(path, e))
+}
+
+fn pem_error(path: &str, e: rustls::pki_types::pem::Error) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("failed to read [{path}]: {e}"),
+    )
+}
@@ -0,0 +1,34 @@
+use crate::plugin::error::SinkError;
+use tokio::sync::mpsc;
+
+/// How a sink finished an event.
+pub(crate) enum Outcome {
+    Ack(String),
+    Nack(String, String),
+}
+
+/// Settles events with the runtime. Events that are not acked are delivered again, so a sink
+/// that finishes events in the background has to ack or nack each of them exactly once.
+#[derive(Clone)]
+pub struct Acknowledger {
+    outcomes: mpsc::UnboundedSender<Outcome>,
+}
+
+impl Acknowledger {
+    pub(crate) fn new() -> (Self, mpsc::UnboundedReceiver<Outcome>) {
+        let (outcomes, outcomes_rx) = mpsc::unbounded_channel();
+        (Self { outcomes }, outcomes_rx)
+    }
+
+    /// The event was handled and can be forgotten by the runtime.
+    pub fn ack(&self, event_id: &str) {
+        let _ = self.outcomes.send(Outcome::Ack(event_id.to_string()));
+    }
+
+    /// The event could not be handled; the runtime delivers it again later.
+    pub fn nack(&self, event_id: &str, err: &SinkError) {
+        let _ = self
+            .outcomes
+            .send(Outcome::Nack(event_id.to_string(), err.to_string()));
+    }
+}
@@ -1,2 +1,3 @@
+pub mod acks;
 pub mod runner;
 pub mod plugin;
@@ -1,19 +1,55 @@
-use crate::plugin::error::{InitializeError, ShutdownError, SinkError};
+use crate::plugin::error::{InitializeError, ReconfigureError, ShutdownError, SinkError};
 use crate::schema::common::{log_level::Enum as LogLevel};
 use crate::schema::sink::SinkEvent;
-use crate::plugin::core::InitializeRequest;
+use crate::plugin::core::{InitializeRequest, PluginConfig};
+use crate::sink::acks::Acknowledger;
 
 pub trait Sink {
+    /// Declares the plugin: its ID, version, schema and the settings it accepts. Sent to the
+    /// runtime when the plugin connects, and printed as the manifest in describe mode.
+    fn describe(&self, plugin_id: String) -> InitializeRequest;
+
+    /// Called once the runtime delivered the config of the node the plugin runs as, before
+    /// the first event.
     fn initialize(
         &mut self,
         plugin_id: String,
         log_level: LogLevel,
-    ) -> Result<InitializeRequest, InitializeError>;
+        config: PluginConfig,
+    ) -> Result<(), InitializeError>;
+
+    /// Called between events when the config of the node changed while the plugin runs.
+    /// Returning an error rejects the new config, and the plugin keeps the one it has. Sinks
+    /// that can't change their settings while running reject every change.
+    fn reconfigure(&mut self, _config: PluginConfig) -> Result<(), ReconfigureError> {
+        Err(ReconfigureError {
+            source: "plugin does not support live reconfiguration".into(),
+        })
+    }
+
+    /// Called when the runtime pauses the plugin. No events arrive until it is resumed, so
+    /// sinks that batch events or finish them in the background flush them here.
+    fn pause(&mut self) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    /// Called when the runtime resumes the plugin, before the next event.
+    fn resume(&mut self) -> Result<(), SinkError> {
+        Ok(())
+    }
 
     fn shutdown(&mut self) -> Result<(), ShutdownError>;
     
     fn version(&self) -> String;
 
     fn consume_event(&mut self, event: SinkEvent) -> Result<(), SinkError>;
+
+    /// Called once before the first event. By default an event is acked as soon as
+    /// `consume_event` returns `Ok` and nacked when it fails. Sinks that finish events in the
+    /// background keep the acknowledger, return `true` and settle every event they accepted
+    /// themselves; events `consume_event` fails for are still nacked by the runner.
+    fn defer_acks(&mut self, _acks: Acknowledger) -> bool {
+        false
+    }
 }
 
@@ -1,14 +1,22 @@
-use crate::plugin::core::ConnectionConfig;
+use crate::plugin::core::{reconfigured, ConnectionConfig, PluginConfig};
+use crate::plugin::credits::CreditReturns;
 use crate::plugin::error::Error;
+use crate::plugin::framing::ProtocolVersion;
 use crate::plugin::logger::PluginLogger;
+use crate::plugin::manifest::Manifest;
 use crate::plugin::msg_client::MSG_CLIENT;
 use crate::schema::common::log_level::Enum as LogLevel;
+use crate::schema::common::plugin_type::Enum as PluginType;
+use crate::schema::common::{Ack, Credit, Nack, Pong};
 use crate::schema::sink::sink_message::Payload;
 use crate::schema::sink::{
     runtime_sink_message::Payload as RuntimeSinkMessagePayload, RuntimeSinkMessage, SinkMessage,
 };
+use crate::sink::acks::{Acknowledger, Outcome};
 use crate::sink::plugin::Sink;
 use prost::Message;
+use std::io;
+use tokio::sync::mpsc;
 
 pub struct SinkRunnerConfig {
     pub plugin_id: String,
@@ -34,15 +42,13 @@ where
         MSG_CLIENT
             .write()
             .await
-            .connect(
-                format!(
-                    "{}:{}",
-                    config.hub_connection.host, config.hub_connection.port
-                )
-                .as_str(),
-            )
+            .connect(config.hub_connection.address().as_str())
             .await?;
-        PluginLogger::initialize(config.plugin_id.as_str(), config.log_level.into())?;
+        PluginLogger::initialize(
+            config.plugin_id.as_str(),
+            PluginType::Sink,
+            config.log_level.into(),
+        )?;
         Ok(Self::new(config.plugin_id, plugin, config.log_level))
     }
 
@@ -54,19 +60,18 @@ where
         }
     }
 
+    /// Prints the plugin manifest to stdout without connecting to a hub.
+    #[allow(dead_code)]
+    pub fn describe(plugin: T, plugin_id: String) -> Result<(), Error> {
+        let request = plugin.describe(plugin_id);
+        println!("{}", Manifest::new(PluginType::Sink, &request).to_toml()?);
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub async fn run(&mut self) -> Result<(), Error> {
         // send hello to runtime
-        let payload = match self
-            .plugin
-            .initialize(self.plugin_id.clone(), self.log_level)
-        {
-            Ok(result) => result,
-            Err(err) => {
-                log::error!("Error initializing plugin: {}", err);
-                return Err(Error::InitError(err));
-            }
-        };
+        let payload = self.plugin.describe(self.plugin_id.clone());
         let hello_msg = SinkMessage {
             payload: Some(Payload::Initialize(payload.into())),
         };
@@ -84,45 +89,79 @@ where
             }
         };
 
-        loop {
-            let bytes = match MSG_CLIENT.read().await.receive().await {
-                Ok(bytes) => match bytes {
-                    None => {
-                        continue;
-                    }
-                    Some(bytes) => bytes,
-                },
-                Err(err) => {
-                    log::error!("Error receiving message: {}", err);
+        let config = receive_config().await?;
+        if let Err(err) = self
+            .plugin
+            .initialize(self.plugin_id.clone(), self.log_level, config)
+        {
+            log::error!("Error initializing plugin: {}", err);
+            return Err(Error::InitError(err));
+        }
+
+        let version = MSG_CLIENT.read().await.protocol_version();
+        let credit_returns = CreditReturns::new();
+        if version.has_credits() {
+            send_credit(credit_returns.initial()).await?;
+        }
+
+        let (acks, outcomes) = Acknowledger::new();
+        let deferred = self.plugin.defer_acks(acks.clone());
+        let settler = tokio::spawn(settle_loop(version, credit_returns, outcomes));
+        // heartbeats have to be answered while an event is consumed, so messages are
+        // received apart from consuming
+        let (messages, messages_rx) = mpsc::unbounded_channel();
+        let receiver = tokio::spawn(receive_loop(messages));
+        let result = self.consume_loop(messages_rx, acks, deferred).await;
+        receiver.abort();
+        settler.abort();
+        result
+    }
+
+    async fn consume_loop(
+        &mut self,
+        mut messages: mpsc::UnboundedReceiver<RuntimeSinkMessagePayload>,
+        acks: Acknowledger,
+        deferred: bool,
+    ) -> Result<(), Error> {
+        while let Some(pyld) = messages.recv().await {
+            match pyld {
+                // noop for sink, heartbeats are answered by the receive loop
+                RuntimeSinkMessagePayload::Initialize(_) | RuntimeSinkMessagePayload::Ping(_) => {
                     continue;
                 }
-            };
-            let msg = match RuntimeSinkMessage::decode(bytes) {
-                Ok(message) => message,
-                Err(err) => {
-                    log::error!("Error parsing message: {}", err);
+                RuntimeSinkMessagePayload::Reconfigure(request) => {
+                    let result = self.plugin.reconfigure(PluginConfig::new(request.config));
+                    send_message(Payload::Reconfigured(reconfigured(
+                        request.request_id,
+                        result,
+                    )))
+                    .await?;
                     continue;
                 }
-            };
-            let pyld = match msg.payload {
-                Some(payload) => payload,
-                None => {
-                    log::error!("Message payload is missing");
+                RuntimeSinkMessagePayload::Pause(_) => {
+                    log::debug!("Received pause message");
+                    if let Err(err) = self.plugin.pause() {
+                        log::error!("Error pausing: {}", err);
+                    }
                     continue;
                 }
-            };
-
-            match pyld {
-                RuntimeSinkMessagePayload::Initialize(_) => {
-                    // noop for sink
+                RuntimeSinkMessagePayload::Resume(_) => {
+                    log::debug!("Received resume message");
+                    if let Err(err) = self.plugin.resume() {
+                        log::error!("Error resuming: {}", err);
+                    }
                     continue;
                 }
                 RuntimeSinkMessagePayload::Event(payload) => {
                     log::debug!("Received event: {:?}", payload.plugin_id.clone());
-                    let result = self.plugin.consume_event(payload);
-                    if let Err(err) = result {
-                        log::error!("Error processing event: {}", err);
-                        continue;
+                    let event_id = payload.event_id.clone();
+                    match self.plugin.consume_event(payload) {
+                        Ok(_) if deferred => {}
+                        Ok(_) => acks.ack(&event_id),
+                        Err(err) => {
+                            log::error!("Error processing event: {}", err);
+                            acks.nack(&event_id, &err);
+                        }
                     }
                     continue;
                 }
@@ -131,12 +170,145 @@ where
                     let result = self.plugin.shutdown();
                     if let Err(err) = result {
                         log::error!("Error shutting down: {}", err);
-                        break Err(Error::ShutdownError(err));
+                        return Err(Error::ShutdownError(err));
                     }
                     log::info!("Plugin shutdown: {}", self.plugin_id);
-                    break Ok(());
+                    return Ok(());
+                }
+            }
+        }
+        log::error!("Connection to the runtime lost");
+        if let Err(err) = self.plugin.shutdown() {
+            log::error!("Error shutting down: {}", err);
+        }
+        Err(Error::IOError(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            "Connection to the runtime lost",
+        )))
+    }
+}
+
+/// Reads messages from the runtime. Pings are answered right away, so a sink busy with an
+/// event still answers heartbeats; everything else is passed on to the consume loop. Exits
+/// after forwarding a shutdown, or once the connection is gone, which closes the channel to the
+/// consume loop.
+async fn receive_loop(messages: mpsc::UnboundedSender<RuntimeSinkMessagePayload>) {
+    loop {
+        let bytes = match MSG_CLIENT.read().await.receive().await {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => {
+                log::info!("Connection to the runtime closed");
+                return;
+            }
+            Err(err) => {
+                log::error!("Error receiving message: {}", err);
+                return;
+            }
+        };
+        let msg = match RuntimeSinkMessage::decode(bytes) {
+            Ok(message) => message,
+            Err(err) => {
+                log::error!("Error parsing message: {}", err);
+                continue;
+            }
+        };
+        let pyld = match msg.payload {
+            Some(payload) => payload,
+            None => {
+                log::error!("Message payload is missing");
+                continue;
+            }
+        };
+
+        match pyld {
+            RuntimeSinkMessagePayload::Ping(ping) => {
+                let _ = send_message(Payload::Pong(Pong { nonce: ping.nonce })).await;
+            }
+            RuntimeSinkMessagePayload::Shutdown(shutdown) => {
+                let _ = messages.send(RuntimeSinkMessagePayload::Shutdown(shutdown));
+                return;
+            }
+            pyld => {
+                if messages.send(pyld).is_err() {
+                    return;
                 }
             }
         }
     }
 }
+
+/// Reports settled events to the runtime and hands their credit back, so the runtime only
+/// delivers more once the sink is really done with earlier events. Runtimes speaking a
+/// protocol version without acks or credit are told neither.
+async fn settle_loop(
+    version: ProtocolVersion,
+    mut credit_returns: CreditReturns,
+    mut outcomes: mpsc::UnboundedReceiver<Outcome>,
+) {
+    while let Some(outcome) = outcomes.recv().await {
+        if version.has_acks() {
+            let payload = match outcome {
+                Outcome::Ack(event_id) => Payload::Ack(Ack { event_id }),
+                Outcome::Nack(event_id, message) => Payload::Nack(Nack { event_id, message }),
+            };
+            let msg = SinkMessage {
+                payload: Some(payload),
+            };
+            if let Err(err) = MSG_CLIENT
+                .read()
+                .await
+                .send(msg.encode_to_vec().as_slice())
+                .await
+            {
+                log::error!("Error sending acknowledgement: {}", err);
+            }
+        }
+        if version.has_credits()
+            && let Some(credit) = credit_returns.processed()
+        {
+            let _ = send_credit(credit).await;
+        }
+    }
+}
+
+/// Waits for the runtime to answer the hello message, and returns the config it delivers.
+async fn receive_config() -> Result<PluginConfig, Error> {
+    loop {
+        let bytes = match MSG_CLIENT.read().await.receive().await? {
+            Some(bytes) => bytes,
+            None => {
+                return Err(Error::IOError(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Connection closed before the runtime answered",
+                )));
+            }
+        };
+        match RuntimeSinkMessage::decode(bytes)?.payload {
+            Some(RuntimeSinkMessagePayload::Initialize(response)) => {
+                return Ok(PluginConfig::new(response.config));
+            }
+            _ => log::warn!("Message received before the initialize response ignored"),
+        }
+    }
+}
+
+/// Lets the runtime deliver more events.
+async fn send_credit(credit: Credit) -> Result<(), Error> {
+    send_message(Payload::Credit(credit)).await
+}
+
+async fn send_message(payload: Payload) -> Result<(), Error> {
+    let msg = SinkMessage {
+        payload: Some(payload),
+    };
+    if let Err(err) = MSG_CLIENT
+        .read()
+        .await
+        .send(msg.encode_to_vec().as_slice())
+        .await
+    {
+        log::error!("Error sending message: {}", err);
+        return Err(Error::IOError(err));
+    }
+    Ok(())
+}
@@ -1,19 +1,44 @@
+use crate::plugin::credits::Credits;
 use crate::plugin::error::SourceError;
 use crate::plugin::msg_client::MSG_CLIENT;
 use crate::schema::source::source_message::Payload;
 use crate::schema::source::{SourceEvent, SourceMessage};
 use prost::Message;
+use std::sync::Arc;
 
 pub struct LocalSink {
     _plugin_id: String,
+    credits: Arc<Credits>,
 }
 
 impl LocalSink {
     pub fn new(plugin_id: String) -> Self {
-        Self { _plugin_id: plugin_id }
+        Self {
+            _plugin_id: plugin_id,
+            credits: Arc::new(Credits::new()),
+        }
+    }
+
+    pub(crate) fn credits(&self) -> Arc<Credits> {
+        self.credits.clone()
     }
 
+    /// Whether the runtime asked the plugin to shut down, or the connection to it is gone.
+    /// Sources that wait on something other than the sink check this to know when to return
+    /// from `run`.
+    pub fn is_closed(&self) -> bool {
+        self.credits.is_closed()
+    }
+
+    /// Sends an event to the runtime. Waits for credit first, so a source producing faster
+    /// than the scene consumes is slowed down here. Fails once the runtime asked the plugin to
+    /// shut down, or the connection to it is gone.
     pub async fn event(&self, evt: SourceEvent) -> Result<(), SourceError> {
+        if !self.credits.acquire().await {
+            return Err(SourceError {
+                source: "sink is closed".into(),
+            });
+        }
         let msg = SourceMessage {
             payload: Some(Payload::Event(evt)),
         };
@@ -1,20 +1,54 @@
 use tokio::sync::Mutex;
-use crate::plugin::core::InitializeRequest;
-use crate::plugin::error::{InitializeError, ShutdownError, SourceError};
+use crate::plugin::core::{InitializeRequest, PluginConfig};
+use crate::plugin::error::{InitializeError, ReconfigureError, ShutdownError, SourceError};
 use crate::schema::common::log_level::Enum as LogLevel;
 use crate::source::local_sink::LocalSink;
 
 pub trait Source<'a> {
+    /// Declares the plugin: its ID, version, schema and the settings it accepts. Sent to the
+    /// runtime when the plugin connects, and printed as the manifest in describe mode.
+    fn describe(&self, plugin_id: String) -> InitializeRequest;
+
+    /// Called once the runtime delivered the config of the node the plugin runs as, before
+    /// the plugin is run.
     fn initialize(
         &mut self,
         plugin_id: String,
         log_level: LogLevel,
+        config: PluginConfig,
         sink: &'a Mutex<LocalSink>,
-    ) -> Result<InitializeRequest, InitializeError>;
+    ) -> Result<(), InitializeError>;
+
+    /// Called when the config of the node changed while the plugin runs. Takes `&self` like
+    /// `run`, which may still be running. Returning an error rejects the new config, and the
+    /// plugin keeps the one it has. Sources that can't change their settings while running
+    /// reject every change.
+    fn reconfigure(&self, _config: PluginConfig) -> Result<(), ReconfigureError> {
+        Err(ReconfigureError {
+            source: "plugin does not support live reconfiguration".into(),
+        })
+    }
+
+    /// Called when the runtime pauses the plugin. Events sent while paused wait until the
+    /// plugin is resumed, so `run` needs no changes; sources that poll something upstream
+    /// may stop polling here.
+    fn pause(&self) -> Result<(), SourceError> {
+        Ok(())
+    }
+
+    /// Called when the runtime resumes the plugin.
+    fn resume(&self) -> Result<(), SourceError> {
+        Ok(())
+    }
 
     fn shutdown(&mut self) -> Result<(), ShutdownError>;
 
     fn version(&self) -> String;
 
+    /// Produces events into the sink. May block for as long as the source produces, provided
+    /// the plugin runs on a multi-thread runtime: credit keeps coming in meanwhile, while the
+    /// `pause`, `resume` and `reconfigure` hooks wait for `run` to return. Once the runtime asks
+    /// the plugin to shut down, the sink is closed and `run` is expected to return; `shutdown`
+    /// is called after that.
     fn run(&self) -> Result<(), SourceError>;
 }
@@ -1,14 +1,22 @@
-use crate::plugin::core::ConnectionConfig;
+use crate::plugin::core::{reconfigured, ConnectionConfig, PluginConfig};
+use crate::plugin::credits::Credits;
 use crate::plugin::error::Error;
 use crate::plugin::logger::{PluginLogger};
+use crate::plugin::manifest::Manifest;
 use crate::plugin::msg_client::MSG_CLIENT;
 use crate::schema::common::log_level::Enum as LogLevel;
+use crate::schema::common::plugin_type::Enum as PluginType;
+use crate::schema::common::Pong;
 use crate::schema::source::runtime_source_message::Payload;
+use crate::schema::source::source_message::Payload as SourceMessagePayload;
 use crate::schema::source::{RuntimeSourceMessage, SourceMessage};
 use crate::source::local_sink::LocalSink;
 use crate::source::plugin::Source;
 use prost::Message;
+use std::io;
 use std::sync::Arc;
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use tokio::sync::{Mutex, RwLock};
 
 pub struct SourceRunnerConfig {
@@ -25,6 +33,7 @@ where
     plugin_id: String,
     log_level: LogLevel,
     local_sink: Arc<Mutex<LocalSink>>,
+    credits: Arc<Credits>,
 }
 
 impl<T> SourceRunner<T>
@@ -36,41 +45,39 @@ where
         MSG_CLIENT
             .write()
             .await
-            .connect(
-                format!(
-                    "{}:{}",
-                    config.hub_connection.host, config.hub_connection.port
-                )
-                .as_str(),
-            )
+            .connect(config.hub_connection.address().as_str())
             .await?;
-        PluginLogger::initialize(config.plugin_id.as_str(), config.log_level.into())?;
+        PluginLogger::initialize(
+            config.plugin_id.as_str(),
+            PluginType::Source,
+            config.log_level.into(),
+        )?;
         Ok(Self::new(config.plugin_id, plugin, config.log_level))
     }
 
     pub(crate) fn new(id: String, plugin: T, log_level: LogLevel) -> Self {
+        let local_sink = LocalSink::new(id.clone());
         Self {
             plugin: RwLock::new(plugin),
             log_level,
-            plugin_id: id.clone(),
-            local_sink: Arc::new(Mutex::new(LocalSink::new(id))),
+            plugin_id: id,
+            credits: local_sink.credits(),
+            local_sink: Arc::new(Mutex::new(local_sink)),
         }
     }
 
+    /// Prints the plugin manifest to stdout without connecting to a hub.
+    #[allow(dead_code)]
+    pub fn describe(plugin: T, plugin_id: String) -> Result<(), Error> {
+        let request = plugin.describe(plugin_id);
+        println!("{}", Manifest::new(PluginType::Source, &request).to_toml()?);
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub async fn run(&self) -> Result<(), Error> {
         // send hello to runtime
-        let payload = match self.plugin.write().await.initialize(
-            self.plugin_id.clone(),
-            self.log_level,
-            self.local_sink.as_ref(),
-        ) {
-            Ok(result) => result,
-            Err(err) => {
-                log::error!("Error initializing plugin: {}", err);
-                return Err(Error::InitError(err));
-            }
-        };
+        let payload = self.plugin.read().await.describe(self.plugin_id.clone());
         let hello_msg = SourceMessage {
             payload: Some(crate::schema::source::source_message::Payload::Initialize(
                 payload.into(),
@@ -90,77 +97,204 @@ where
             }
         };
 
+        let config = receive_config().await?;
+        if let Err(err) = self.plugin.write().await.initialize(
+            self.plugin_id.clone(),
+            self.log_level,
+            config,
+            self.local_sink.as_ref(),
+        ) {
+            log::error!("Error initializing plugin: {}", err);
+            return Err(Error::InitError(err));
+        }
+        if !MSG_CLIENT.read().await.protocol_version().has_credits() {
+            self.credits.unlimit();
+        }
+
+        let (control_tx, mut control) = mpsc::unbounded_channel();
+        let receiver = tokio::spawn(receive_loop(self.credits.clone(), control_tx));
+        let result = self.serve(&mut control).await;
+        receiver.abort();
+
+        if let Err(err) = self.plugin.write().await.shutdown() {
+            log::error!("Error shutting down: {}", err);
+            return Err(Error::ShutdownError(err));
+        }
+        log::info!("Plugin shutdown: {}", self.plugin_id);
+        result
+    }
+
+    /// Runs the plugin alongside the consume loop until the runtime asks for a shutdown or the
+    /// connection is gone, and the plugin is no longer running. A plugin that crashes while
+    /// the sink is open is run again.
+    async fn serve(&self, control: &mut UnboundedReceiver<Payload>) -> Result<(), Error> {
+        let consume = self.consume_loop(control);
+        tokio::pin!(consume);
+        let mut running = true;
         loop {
-            let join_result = tokio::try_join!(self.consume_loop(), self.run_plugin());
-            match join_result {
-                Ok(_) => {
-                    // both exited cleanly
-                    break Ok(());
-                }
-                Err(err) => {
-                    // if plugin crashed - try again
-                    if let Error::SourceError(_) = err {
-                        log::error!("Plugin crashed: {}", err.to_string());
-                        continue;
+            tokio::select! {
+                result = &mut consume => return result,
+                result = self.run_plugin(), if running => match result {
+                    Ok(()) => running = false,
+                    Err(err) if self.credits.is_closed() => {
+                        log::debug!("Plugin stopped: {}", err);
+                        running = false;
                     }
-                    // consume loop crashed - exit
-                    let result = self.plugin.write().await.shutdown();
-                    if let Err(err) = result {
-                        log::error!("Error shutting down: {}", err);
-                        return Err(Error::ShutdownError(err));
-                    }
-                    break Err(err);
-                }
+                    Err(err) => log::error!("Plugin crashed: {}", err),
+                },
             }
         }
     }
 
-    async fn consume_loop(&self) -> Result<(), Error> {
-        loop {
-            let bytes = match MSG_CLIENT.read().await.receive().await {
-                Ok(bytes) => match bytes {
-                    None => {
-                        continue;
+    /// Handles the messages of the runtime that go to the plugin. Returns once the runtime
+    /// asks for a shutdown, or with an error once the connection is gone.
+    async fn consume_loop(&self, control: &mut UnboundedReceiver<Payload>) -> Result<(), Error> {
+        while let Some(payload) = control.recv().await {
+            match payload {
+                Payload::Reconfigure(request) => {
+                    let result = self
+                        .plugin
+                        .read()
+                        .await
+                        .reconfigure(PluginConfig::new(request.config));
+                    let answer = SourceMessage {
+                        payload: Some(SourceMessagePayload::Reconfigured(reconfigured(
+                            request.request_id,
+                            result,
+                        ))),
+                    };
+                    if let Err(err) = MSG_CLIENT
+                        .read()
+                        .await
+                        .send(answer.encode_to_vec().as_slice())
+                        .await
+                    {
+                        log::error!("Error sending reconfigure result: {}", err);
                     }
-                    Some(bytes) => bytes,
-                },
-                Err(err) => {
-                    log::error!("Error receiving message: {}", err);
-                    continue;
                 }
-            };
-            let msg_result = RuntimeSourceMessage::decode(bytes);
-            if let Err(err) = msg_result {
-                log::error!("Error parsing message: {}", err);
-                continue;
+                Payload::Pause(_) => {
+                    if let Err(err) = self.plugin.read().await.pause() {
+                        log::error!("Error pausing: {}", err);
+                    }
+                }
+                Payload::Resume(_) => {
+                    log::debug!("Received resume message");
+                    if let Err(err) = self.plugin.read().await.resume() {
+                        log::error!("Error resuming: {}", err);
+                    }
+                    self.credits.resume();
+                }
+                Payload::Shutdown(_) => {
+                    log::debug!("Received shutdown message");
+                    return Ok(());
+                }
+                Payload::Initialize(_) | Payload::Credit(_) | Payload::Ping(_) => {}
             }
-            let msg = msg_result.expect("Message should be valid");
+        }
+        Err(Error::IOError(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            "Connection to the runtime lost",
+        )))
+    }
+
+    /// Runs the plugin. `run` may block for as long as the source produces: on a multi-thread
+    /// runtime the worker hands its other tasks off first, so the receive loop keeps taking
+    /// credit from the runtime meanwhile.
+    async fn run_plugin(&self) -> Result<(), Error> {
+        let plugin = self.plugin.read().await;
+        let result = match Handle::current().runtime_flavor() {
+            RuntimeFlavor::MultiThread => tokio::task::block_in_place(|| plugin.run()),
+            _ => plugin.run(),
+        };
+        result.map_err(Error::SourceError)
+    }
+}
 
-            if msg.payload.is_none() {
+/// Takes the messages of the runtime off the connection. Credit and pings are handled here, so
+/// they keep flowing while the plugin runs; the rest goes to the consume loop. Once the runtime
+/// asks for a shutdown or the connection is gone, the credits are closed so that a running
+/// plugin stops emitting.
+async fn receive_loop(credits: Arc<Credits>, control: UnboundedSender<Payload>) {
+    loop {
+        let bytes = match MSG_CLIENT.read().await.receive().await {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => {
+                log::info!("Connection to the runtime closed");
+                break;
+            }
+            Err(err) => {
+                log::error!("Error receiving message: {}", err);
+                break;
+            }
+        };
+        let payload = match RuntimeSourceMessage::decode(bytes) {
+            Ok(RuntimeSourceMessage {
+                payload: Some(payload),
+            }) => payload,
+            Ok(_) => {
                 log::error!("Message payload is missing");
                 continue;
             }
-
-            match msg.payload.unwrap() {
-                Payload::Initialize(_) => {}
-                Payload::Shutdown(_) => {
-                    log::debug!("Received shutdown message");
-                    let result = self.plugin.write().await.shutdown();
-                    if let Err(err) = result {
-                        log::error!("Error shutting down: {}", err);
-                        return Err(Error::ShutdownError(err));
-                    }
-                    log::info!("Plugin shutdown: {}", self.plugin_id);
-                    return Ok(());
+            Err(err) => {
+                log::error!("Error parsing message: {}", err);
+                continue;
+            }
+        };
+        match payload {
+            Payload::Initialize(_) => {}
+            Payload::Credit(credit) => {
+                log::trace!("Received credit: {}", credit.amount);
+                credits.grant(credit);
+            }
+            Payload::Ping(ping) => {
+                let pong = SourceMessage {
+                    payload: Some(SourceMessagePayload::Pong(Pong { nonce: ping.nonce })),
+                };
+                if let Err(err) = MSG_CLIENT
+                    .read()
+                    .await
+                    .send(pong.encode_to_vec().as_slice())
+                    .await
+                {
+                    log::error!("Error sending pong: {}", err);
+                }
+            }
+            Payload::Shutdown(shutdown) => {
+                credits.close();
+                let _ = control.send(Payload::Shutdown(shutdown));
+                return;
+            }
+            payload => {
+                if let Payload::Pause(_) = payload {
+                    log::debug!("Received pause message");
+                    credits.pause();
+                }
+                if control.send(payload).is_err() {
+                    break;
                 }
             }
         }
     }
+    credits.close();
+}
 
-    async fn run_plugin(&self) -> Result<(), Error> {
-        if let Err(err) = self.plugin.read().await.run() {
-            return Err(Error::SourceError(err));
+/// Waits for the runtime to answer the hello message, and returns the config it delivers.
+async fn receive_config() -> Result<PluginConfig, Error> {
+    loop {
+        let bytes = match MSG_CLIENT.read().await.receive().await? {
+            Some(bytes) => bytes,
+            None => {
+                return Err(Error::IOError(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Connection closed before the runtime answered",
+                )));
+            }
+        };
+        match RuntimeSourceMessage::decode(bytes)?.payload {
+            Some(Payload::Initialize(response)) => {
+                return Ok(PluginConfig::new(response.config));
+            }
+            _ => log::warn!("Message received before the initialize response ignored"),
         }
-        Ok(())
     }
 }
@@ -1,20 +1,36 @@
+use crate::plugin::credits::Credits;
 use crate::plugin::error::TransformError;
 use crate::plugin::msg_client::MSG_CLIENT;
-use crate::schema::source::source_message::Payload;
-use crate::schema::source::{SourceEvent, SourceMessage};
+use crate::schema::transform::transform_message::Payload;
+use crate::schema::transform::{TransformEvent, TransformMessage};
 use prost::Message;
+use std::sync::Arc;
 
 pub struct LocalSink {
     _plugin_id: String,
+    credits: Arc<Credits>,
 }
 
 impl LocalSink {
     pub fn new(plugin_id: String) -> Self {
-        Self { _plugin_id: plugin_id }
+        Self {
+            _plugin_id: plugin_id,
+            credits: Arc::new(Credits::new()),
+        }
+    }
+
+    pub(crate) fn credits(&self) -> Arc<Credits> {
+        self.credits.clone()
     }
 
-    pub async fn event(&self, evt: SourceEvent) -> Result<(), TransformError> {
-        let msg = SourceMessage {
+    /// Sends a transformed event to the runtime, once there is credit for it.
+    pub async fn event(&self, evt: TransformEvent) -> Result<(), TransformError> {
+        if !self.credits.acquire().await {
+            return Err(TransformError {
+                source: "sink is closed".into(),
+            });
+        }
+        let msg = TransformMessage {
             payload: Some(Payload::Event(evt)),
         };
         match MSG_CLIENT
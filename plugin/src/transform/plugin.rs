@@ -1,15 +1,42 @@
-use crate::plugin::core::InitializeRequest;
-use crate::plugin::error::{InitializeError, ShutdownError, TransformError};
+use crate::plugin::core::{InitializeRequest, PluginConfig};
+use crate::plugin::error::{InitializeError, ReconfigureError, ShutdownError, TransformError};
 use crate::schema::transform::TransformEvent;
 use tokio::sync::Mutex;
 
 pub trait Transform<'a> {
+    /// Declares the plugin: its ID, version, schemas and the settings it accepts. Sent to the
+    /// runtime when the plugin connects, and printed as the manifest in describe mode.
+    fn describe(&self, plugin_id: String) -> InitializeRequest;
+
+    /// Called once the runtime delivered the config of the node the plugin runs as, before
+    /// the first event.
     fn initialize(
         &mut self,
         plugin_id: String,
         log_level: crate::schema::common::log_level::Enum,
+        config: PluginConfig,
         sink: &'a Mutex<crate::transform::local_sink::LocalSink>,
-    ) -> Result<InitializeRequest, InitializeError>;
+    ) -> Result<(), InitializeError>;
+
+    /// Called between events when the config of the node changed while the plugin runs.
+    /// Returning an error rejects the new config, and the plugin keeps the one it has.
+    /// Transforms that can't change their settings while running reject every change.
+    fn reconfigure(&mut self, _config: PluginConfig) -> Result<(), ReconfigureError> {
+        Err(ReconfigureError {
+            source: "plugin does not support live reconfiguration".into(),
+        })
+    }
+
+    /// Called when the runtime pauses the plugin. No events arrive until it is resumed, so
+    /// transforms that hold events back flush them here.
+    fn pause(&mut self) -> Result<(), TransformError> {
+        Ok(())
+    }
+
+    /// Called when the runtime resumes the plugin, before the next event.
+    fn resume(&mut self) -> Result<(), TransformError> {
+        Ok(())
+    }
 
     fn shutdown(&mut self) -> Result<(), ShutdownError>;
 
@@ -1,16 +1,23 @@
-use crate::plugin::core::ConnectionConfig;
+use crate::plugin::core::{reconfigured, ConnectionConfig, PluginConfig};
+use crate::plugin::credits::{CreditReturns, Credits};
 use crate::plugin::error::Error;
+use crate::plugin::framing::ProtocolVersion;
 use crate::plugin::logger::PluginLogger;
+use crate::plugin::manifest::Manifest;
 use crate::plugin::msg_client::MSG_CLIENT;
 use crate::schema::common::log_level::Enum as LogLevel;
+use crate::schema::common::plugin_type::Enum as PluginType;
+use crate::schema::common::{Ack, Credit, Nack, Pong};
 use crate::schema::transform::transform_message::Payload;
 use crate::schema::transform::{
-    runtime_transform_message::Payload as RuntimeTransformMessagePayload, RuntimeTransformMessage, TransformMessage,
+    runtime_transform_message::Payload as RuntimeTransformMessagePayload, RuntimeTransformMessage,
+    TransformEvent, TransformMessage,
 };
 use crate::transform::plugin::Transform;
 use prost::Message;
+use std::io;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
 
 pub struct TransformRunnerConfig {
     pub plugin_id: String,
@@ -26,6 +33,7 @@ where
     plugin_id: String,
     log_level: LogLevel,
     local_sink: Arc<Mutex<crate::transform::local_sink::LocalSink>>,
+    credits: Arc<Credits>,
 }
 
 impl<T> TransformRunner<T>
@@ -37,41 +45,42 @@ where
         MSG_CLIENT
             .write()
             .await
-            .connect(
-                format!(
-                    "{}:{}",
-                    config.hub_connection.host, config.hub_connection.port
-                )
-                .as_str(),
-            )
+            .connect(config.hub_connection.address().as_str())
             .await?;
-        PluginLogger::initialize(config.plugin_id.as_str(), config.log_level.into())?;
+        PluginLogger::initialize(
+            config.plugin_id.as_str(),
+            PluginType::Transform,
+            config.log_level.into(),
+        )?;
         Ok(Self::new(config.plugin_id, plugin, config.log_level))
     }
 
     pub(crate) fn new(id: String, plugin: T, log_level: LogLevel) -> Self {
+        let local_sink = crate::transform::local_sink::LocalSink::new(id.clone());
         Self {
             plugin,
-            plugin_id: id.clone(),
+            plugin_id: id,
             log_level,
-            local_sink: Arc::new(Mutex::new(crate::transform::local_sink::LocalSink::new(id))),
+            credits: local_sink.credits(),
+            local_sink: Arc::new(Mutex::new(local_sink)),
         }
     }
 
+    /// Prints the plugin manifest to stdout without connecting to a hub.
+    #[allow(dead_code)]
+    pub fn describe(plugin: T, plugin_id: String) -> Result<(), Error> {
+        let request = plugin.describe(plugin_id);
+        println!(
+            "{}",
+            Manifest::new(PluginType::Transform, &request).to_toml()?
+        );
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub async fn run(&mut self) -> Result<(), Error> {
         // send hello to runtime
-        let payload = match self.plugin.initialize(
-            self.plugin_id.clone(),
-            self.log_level,
-            self.local_sink.as_ref(),
-        ) {
-            Ok(result) => result,
-            Err(err) => {
-                log::error!("Error initializing plugin: {}", err);
-                return Err(Error::InitError(err));
-            }
-        };
+        let payload = self.plugin.describe(self.plugin_id.clone());
         let hello_msg = TransformMessage {
             payload: Some(Payload::Initialize(payload.into())),
         };
@@ -89,45 +98,110 @@ where
             }
         };
 
-        loop {
-            let bytes = match MSG_CLIENT.read().await.receive().await {
-                Ok(bytes) => match bytes {
-                    None => {
-                        continue;
-                    }
-                    Some(bytes) => bytes,
-                },
-                Err(err) => {
-                    log::error!("Error receiving message: {}", err);
+        let config = receive_config().await?;
+        if let Err(err) = self.plugin.initialize(
+            self.plugin_id.clone(),
+            self.log_level,
+            config,
+            self.local_sink.as_ref(),
+        ) {
+            log::error!("Error initializing plugin: {}", err);
+            return Err(Error::InitError(err));
+        }
+        let version = MSG_CLIENT.read().await.protocol_version();
+        if !version.has_credits() {
+            self.credits.unlimit();
+        }
+
+        // credit has to keep coming in while events wait for it, so messages are received
+        // apart from processing
+        let (messages, messages_rx) = mpsc::unbounded_channel();
+        let receiver = tokio::spawn(receive_loop(self.credits.clone(), messages));
+        let result = self.process_loop(version, messages_rx).await;
+        receiver.abort();
+        result
+    }
+
+    async fn process_loop(
+        &mut self,
+        version: ProtocolVersion,
+        mut messages: mpsc::UnboundedReceiver<RuntimeTransformMessagePayload>,
+    ) -> Result<(), Error> {
+        let mut credit_returns = CreditReturns::new();
+        if version.has_credits() {
+            send_credit(credit_returns.initial()).await?;
+        }
+
+        while let Some(pyld) = messages.recv().await {
+            match pyld {
+                // credit and heartbeats are handled by the receive loop
+                RuntimeTransformMessagePayload::Initialize(_)
+                | RuntimeTransformMessagePayload::Credit(_)
+                | RuntimeTransformMessagePayload::Ping(_) => {
                     continue;
                 }
-            };
-            let msg = match RuntimeTransformMessage::decode(bytes) {
-                Ok(message) => message,
-                Err(err) => {
-                    log::error!("Error parsing message: {}", err);
+                RuntimeTransformMessagePayload::Reconfigure(request) => {
+                    let result = self.plugin.reconfigure(PluginConfig::new(request.config));
+                    send_message(Payload::Reconfigured(reconfigured(
+                        request.request_id,
+                        result,
+                    )))
+                    .await?;
                     continue;
                 }
-            };
-            let pyld = match msg.payload {
-                Some(payload) => payload,
-                None => {
-                    log::error!("Message payload is missing");
+                // credit is paused by the receive loop, so outputs stop right away
+                RuntimeTransformMessagePayload::Pause(_) => {
+                    if let Err(err) = self.plugin.pause() {
+                        log::error!("Error pausing: {}", err);
+                    }
                     continue;
                 }
-            };
-
-            match pyld {
-                RuntimeTransformMessagePayload::Initialize(_) => {
-                    // noop for sink
+                RuntimeTransformMessagePayload::Resume(_) => {
+                    log::debug!("Received resume message");
+                    if let Err(err) = self.plugin.resume() {
+                        log::error!("Error resuming: {}", err);
+                    }
+                    self.credits.resume();
                     continue;
                 }
                 RuntimeTransformMessagePayload::Event(payload) => {
                     log::debug!("Received event: {:?}", payload.plugin_id.clone());
-                    let result = self.plugin.process_event(payload);
-                    if let Err(err) = result {
-                        log::error!("Error processing event: {}", err);
-                        continue;
+                    let event_id = payload.event_id.clone();
+                    let trace_id = payload.trace_id.clone();
+                    let settled = match self.plugin.process_event(payload) {
+                        // waits for credit, holding back the upstream until the scene
+                        // takes the output
+                        Ok(event) => match self
+                            .local_sink
+                            .lock()
+                            .await
+                            .event(derive_event(event, event_id.as_str(), trace_id))
+                            .await
+                        {
+                            Ok(_) => Payload::Ack(Ack { event_id }),
+                            Err(err) => {
+                                log::error!("Error sending event: {}", err);
+                                Payload::Nack(Nack {
+                                    event_id,
+                                    message: err.to_string(),
+                                })
+                            }
+                        },
+                        Err(err) => {
+                            log::error!("Error processing event: {}", err);
+                            Payload::Nack(Nack {
+                                event_id,
+                                message: err.to_string(),
+                            })
+                        }
+                    };
+                    if version.has_acks() {
+                        send_message(settled).await?;
+                    }
+                    if version.has_credits()
+                        && let Some(credit) = credit_returns.processed()
+                    {
+                        send_credit(credit).await?;
                     }
                     continue;
                 }
@@ -136,12 +210,139 @@ where
                     let result = self.plugin.shutdown();
                     if let Err(err) = result {
                         log::error!("Error shutting down: {}", err);
-                        break Err(Error::ShutdownError(err));
+                        return Err(Error::ShutdownError(err));
                     }
                     log::info!("Plugin shutdown: {}", self.plugin_id);
-                    break Ok(());
+                    return Ok(());
                 }
             }
         }
+        log::error!("Connection to the runtime lost");
+        if let Err(err) = self.plugin.shutdown() {
+            log::error!("Error shutting down: {}", err);
+        }
+        Err(Error::IOError(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            "Connection to the runtime lost",
+        )))
+    }
+}
+
+/// Waits for the runtime to answer the hello message, and returns the config it delivers.
+async fn receive_config() -> Result<PluginConfig, Error> {
+    loop {
+        let bytes = match MSG_CLIENT.read().await.receive().await? {
+            Some(bytes) => bytes,
+            None => {
+                return Err(Error::IOError(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Connection closed before the runtime answered",
+                )));
+            }
+        };
+        match RuntimeTransformMessage::decode(bytes)?.payload {
+            Some(RuntimeTransformMessagePayload::Initialize(response)) => {
+                return Ok(PluginConfig::new(response.config));
+            }
+            _ => log::warn!("Message received before the initialize response ignored"),
+        }
+    }
+}
+
+/// Reads messages from the runtime. Credit is applied, paused and pings are answered right away,
+/// so a transform busy with an event still answers heartbeats; everything else is passed on to
+/// the process loop. Exits after forwarding a shutdown, or once the connection is gone, which
+/// closes the channel to the process loop. Either way the credits are closed, so an event
+/// waiting for credit is given up on.
+async fn receive_loop(
+    credits: Arc<Credits>,
+    messages: mpsc::UnboundedSender<RuntimeTransformMessagePayload>,
+) {
+    loop {
+        let bytes = match MSG_CLIENT.read().await.receive().await {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => {
+                log::info!("Connection to the runtime closed");
+                break;
+            }
+            Err(err) => {
+                log::error!("Error receiving message: {}", err);
+                break;
+            }
+        };
+        let msg = match RuntimeTransformMessage::decode(bytes) {
+            Ok(message) => message,
+            Err(err) => {
+                log::error!("Error parsing message: {}", err);
+                continue;
+            }
+        };
+        let pyld = match msg.payload {
+            Some(payload) => payload,
+            None => {
+                log::error!("Message payload is missing");
+                continue;
+            }
+        };
+
+        match pyld {
+            RuntimeTransformMessagePayload::Credit(credit) => {
+                log::trace!("Received credit: {}", credit.amount);
+                credits.grant(credit);
+            }
+            RuntimeTransformMessagePayload::Ping(ping) => {
+                let _ = send_message(Payload::Pong(Pong { nonce: ping.nonce })).await;
+            }
+            RuntimeTransformMessagePayload::Shutdown(shutdown) => {
+                let _ = messages.send(RuntimeTransformMessagePayload::Shutdown(shutdown));
+                break;
+            }
+            pyld => {
+                if let RuntimeTransformMessagePayload::Pause(_) = pyld {
+                    log::debug!("Received pause message");
+                    credits.pause();
+                }
+                if messages.send(pyld).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    credits.close();
+}
+
+/// Links an output event to the event it was processed from, unless the plugin did. The
+/// runtime assigns a fresh event ID to outputs that carry none or the input's.
+fn derive_event(mut event: TransformEvent, parent_id: &str, trace_id: String) -> TransformEvent {
+    if event.event_id == parent_id {
+        event.event_id = String::new();
+    }
+    if event.parent_ids.is_empty() {
+        event.parent_ids = vec![parent_id.to_string()];
+    }
+    if event.trace_id.is_empty() {
+        event.trace_id = trace_id;
+    }
+    event
+}
+
+/// Lets the runtime deliver more events.
+async fn send_credit(credit: Credit) -> Result<(), Error> {
+    send_message(Payload::Credit(credit)).await
+}
+
+async fn send_message(payload: Payload) -> Result<(), Error> {
+    let msg = TransformMessage {
+        payload: Some(payload),
+    };
+    if let Err(err) = MSG_CLIENT
+        .read()
+        .await
+        .send(msg.encode_to_vec().as_slice())
+        .await
+    {
+        log::error!("Error sending message: {}", err);
+        return Err(Error::IOError(err));
     }
+    Ok(())
 }
@@ -13,56 +13,80 @@ use std::time::Duration;
 #[command(version, about, long_about = None)]
 pub(crate) struct Args {
     /// Plugin ID
-    #[arg(short, long, required = true)]
-    id: String,
+    #[arg(short, long, required_unless_present = "describe")]
+    id: Option<String>,
 
     /// Hostname/IP of the hub. Can be empty for localhost
-    #[arg(short, long, required = true, default_value = "")]
+    #[arg(long, required_unless_present = "describe", default_value = "")]
     host: String,
 
     /// Port of the hub
-    #[arg(short, long, required = true)]
-    port: u16,
+    #[arg(short, long, required_unless_present = "describe")]
+    port: Option<u16>,
 
     /// Log level
-    #[arg(short, long, required = true, default_value = "info")]
+    #[arg(
+        short,
+        long,
+        required_unless_present = "describe",
+        default_value = "info"
+    )]
     log_level: String,
 
     /// HTTP client timeout (seconds)
-    #[arg(short, long, required = false, default_value = "60")]
+    #[arg(long, required = false, default_value = "60")]
     http_timeout_seconds: usize,
 
     /// HTTP client read timeout (seconds)
-    #[arg(short, long, required = false, default_value = "30")]
+    #[arg(long, required = false, default_value = "30")]
     http_read_timeout_seconds: usize,
 
     /// HTTP client connect timeout (seconds)
-    #[arg(short, long, required = false, default_value = "30")]
+    #[arg(long, required = false, default_value = "30")]
     http_connect_timeout_seconds: usize,
 
     /// HTTP client enable verbose logging
-    #[arg(short, long, required = false, default_value = "false")]
+    #[arg(long, required = false, default_value = "false")]
     http_verbose_logging: bool,
+
+    /// Maximum number of HTTP requests in flight
+    #[arg(long, required = false, default_value = "32")]
+    http_max_in_flight_requests: usize,
+
+    /// Print the plugin manifest and exit without connecting to the hub
+    #[arg(long, required = false, default_value = "false")]
+    describe: bool,
 }
 
 #[tokio::main]
 async fn main() {
-    println!("Starting plugin...");
     let args = Args::parse();
+    let plugin_id = args
+        .id
+        .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_string());
 
     let settings = PluginSettings {
         connect_timeout: Duration::from_secs(args.http_connect_timeout_seconds as u64),
         read_timeout: Duration::from_secs(args.http_read_timeout_seconds as u64),
         timeout: Duration::from_secs(args.http_timeout_seconds as u64),
         verbose_logging: args.http_verbose_logging,
+        max_in_flight_requests: args.http_max_in_flight_requests,
     };
-    let plugin = match plugin::Plugin::new(args.id.as_str(), settings) {
+    let plugin = match plugin::Plugin::new(plugin_id.as_str(), settings) {
         Ok(plugin) => plugin,
         Err(err) => {
             eprintln!("Failed to initialize plugin: {}", err);
             return;
         }
     };
+    if args.describe {
+        if let Err(err) = SinkRunner::describe(plugin, plugin_id) {
+            eprintln!("Failed to describe plugin: {}", err);
+        }
+        return;
+    }
+    println!("Starting plugin...");
+
     let log_level = match args.log_level.as_str() {
         "trace" => LogLevel::Trace,
         "debug" => LogLevel::Debug,
@@ -72,11 +96,11 @@ async fn main() {
         _ => LogLevel::Info,
     };
     let cfg = SinkRunnerConfig {
-        plugin_id: args.id,
+        plugin_id,
         log_level,
         hub_connection: ConnectionConfig {
             host: args.host,
-            port: args.port,
+            port: args.port.unwrap_or_default(),
         },
     };
     let mut runner = match SinkRunner::initialize(plugin, cfg).await {
@@ -1,52 +1,76 @@
-use crate::schema::build_schema;
+use crate::schema::{build_config_options, build_schema};
 use crate::schema::{BODY_KEY, HEADERS_KEY, METHOD_KEY, URL_KEY};
+use crate::schema::{
+    CONNECT_TIMEOUT_KEY, MAX_IN_FLIGHT_REQUESTS_KEY, READ_TIMEOUT_KEY, TIMEOUT_KEY,
+    VERBOSE_LOGGING_KEY,
+};
 use bytes::Bytes;
-use flwrs_plugin::plugin::core::InitializeRequest;
-use flwrs_plugin::plugin::error::{InitializeError, ShutdownError, SinkError};
+use flwrs_plugin::plugin::core::{InitializeRequest, PluginConfig};
+use flwrs_plugin::plugin::error::{InitializeError, ReconfigureError, ShutdownError, SinkError};
 use flwrs_plugin::schema::common::log_level::Enum as LogLevel;
 use flwrs_plugin::schema::schema::field_value::Value;
 use flwrs_plugin::schema::schema::{Field, PluginPayload};
 use flwrs_plugin::schema::sink::SinkEvent;
+use flwrs_plugin::sink::acks::Acknowledger;
 use flwrs_plugin::sink::plugin::Sink;
 use reqwest::{Client, ClientBuilder, Method, RequestBuilder};
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
 
+#[derive(Clone)]
 pub(crate) struct PluginSettings {
     pub connect_timeout: Duration,
     pub verbose_logging: bool,
     pub read_timeout: Duration,
     pub timeout: Duration,
+    pub max_in_flight_requests: usize,
+}
+
+impl PluginSettings {
+    /// Overrides the settings the plugin was launched with by the ones in the node config.
+    fn apply(&mut self, config: &PluginConfig) {
+        if let Some(seconds) = config.u32(CONNECT_TIMEOUT_KEY) {
+            self.connect_timeout = Duration::from_secs(seconds as u64);
+        }
+        if let Some(verbose) = config.bool(VERBOSE_LOGGING_KEY) {
+            self.verbose_logging = verbose;
+        }
+        if let Some(seconds) = config.u32(READ_TIMEOUT_KEY) {
+            self.read_timeout = Duration::from_secs(seconds as u64);
+        }
+        if let Some(seconds) = config.u32(TIMEOUT_KEY) {
+            self.timeout = Duration::from_secs(seconds as u64);
+        }
+        if let Some(max) = config.u32(MAX_IN_FLIGHT_REQUESTS_KEY) {
+            self.max_in_flight_requests = max as usize;
+        }
+    }
 }
 
 pub(crate) struct Plugin {
     id: String,
+    settings: PluginSettings,
     client: Client,
+    in_flight: Arc<Semaphore>,
+    acks: Option<Acknowledger>,
 }
 
 impl Plugin {
     pub fn new(id: &str, settings: PluginSettings) -> Result<Self, SinkError> {
-        let client = match ClientBuilder::new()
-            .connect_timeout(settings.connect_timeout)
-            .connection_verbose(settings.verbose_logging)
-            .read_timeout(settings.read_timeout)
-            .timeout(settings.timeout)
-            .user_agent(APP_USER_AGENT)
-            .build()
-        {
-            Ok(client) => client,
-            Err(e) => {
-                return Err(SinkError {
-                    source: Box::new(e),
-                });
-            }
-        };
+        let client = build_client(&settings).map_err(|e| SinkError {
+            source: Box::new(e),
+        })?;
         Ok(Self {
             id: id.to_string(),
+            in_flight: Arc::new(Semaphore::new(settings.max_in_flight_requests.max(1))),
+            settings,
             client,
+            acks: None,
         })
     }
 
@@ -63,19 +87,70 @@ impl Plugin {
         request = request.body(parsed_payload.body);
         Ok(request)
     }
+
+    /// Applies a node config. Requests already in flight keep the client and the request
+    /// slot they were sent with, so they finish under the old settings instead of being
+    /// dropped. Nothing changes if the new client can't be built.
+    fn configure(&mut self, config: &PluginConfig) -> reqwest::Result<()> {
+        let mut settings = self.settings.clone();
+        settings.apply(config);
+        self.client = build_client(&settings)?;
+        if settings.max_in_flight_requests != self.settings.max_in_flight_requests {
+            self.in_flight = Arc::new(Semaphore::new(settings.max_in_flight_requests.max(1)));
+        }
+        self.settings = settings;
+        Ok(())
+    }
 }
 
 impl Sink for Plugin {
+    fn describe(&self, plugin_id: String) -> InitializeRequest {
+        let mut request = InitializeRequest::new()
+            .with_id(plugin_id)
+            .with_version(VERSION.to_string())
+            .with_description("Sends an HTTP request for every event".to_string())
+            .with_schema(build_schema());
+        for option in build_config_options() {
+            request = request.add_config_option(option);
+        }
+        request
+    }
+
     fn initialize(
         &mut self,
         plugin_id: String,
         _: LogLevel,
-    ) -> Result<InitializeRequest, InitializeError> {
+        config: PluginConfig,
+    ) -> Result<(), InitializeError> {
         self.id = plugin_id;
-        Ok(InitializeRequest::new()
-            .with_id(self.id.clone())
-            .with_version(VERSION.to_string())
-            .with_schema(build_schema()))
+        if config.is_empty() {
+            return Ok(());
+        }
+        self.configure(&config).map_err(|e| InitializeError {
+            source: Box::new(e),
+        })
+    }
+
+    fn reconfigure(&mut self, config: PluginConfig) -> Result<(), ReconfigureError> {
+        self.configure(&config).map_err(|e| ReconfigureError {
+            source: Box::new(e),
+        })
+    }
+
+    fn pause(&mut self) -> Result<(), SinkError> {
+        // waits for the requests in flight, so their events are settled before the endpoint
+        // goes down for maintenance
+        let in_flight = self.in_flight.clone();
+        let slots = self.settings.max_in_flight_requests.max(1) as u32;
+        let idle = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(in_flight.acquire_many_owned(slots))
+        })
+        .map_err(|e| SinkError {
+            source: Box::new(e),
+        })?;
+        drop(idle);
+        log::debug!("Requests in flight finished");
+        Ok(())
     }
 
     fn shutdown(&mut self) -> Result<(), ShutdownError> {
@@ -87,6 +162,12 @@ impl Sink for Plugin {
         VERSION.to_string()
     }
 
+    fn defer_acks(&mut self, acks: Acknowledger) -> bool {
+        // requests finish in the background, an event is acked once its request succeeded
+        self.acks = Some(acks);
+        true
+    }
+
     fn consume_event(&mut self, event: SinkEvent) -> Result<(), SinkError> {
         if event.plugin_id != self.id {
             return Err(SinkError {
@@ -111,25 +192,67 @@ impl Sink for Plugin {
         log::trace!("Received event: {:?}", payload);
         let request = self.build_request(payload)?;
 
-        tokio::task::spawn(send_request(request));
+        // Holds the runner until a request slot frees up. The runner grants the hub credit
+        // only for consumed events, so a slow endpoint slows the scene down instead of
+        // piling up requests.
+        let in_flight = self.in_flight.clone();
+        let permit = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(in_flight.acquire_owned())
+        })
+        .map_err(|e| SinkError {
+            source: Box::new(e),
+        })?;
+        tokio::task::spawn(send_request(
+            request,
+            permit,
+            self.acks.clone(),
+            event.event_id,
+        ));
 
         Ok(())
     }
 }
 
-async fn send_request(request: RequestBuilder) {
-    let response = match request.send().await {
-        Ok(response) => response,
+fn build_client(settings: &PluginSettings) -> reqwest::Result<Client> {
+    ClientBuilder::new()
+        .connect_timeout(settings.connect_timeout)
+        .connection_verbose(settings.verbose_logging)
+        .read_timeout(settings.read_timeout)
+        .timeout(settings.timeout)
+        .user_agent(APP_USER_AGENT)
+        .build()
+}
+
+async fn send_request(
+    request: RequestBuilder,
+    _permit: OwnedSemaphorePermit,
+    acks: Option<Acknowledger>,
+    event_id: String,
+) {
+    let result = match request.send().await {
+        Ok(response) => response.error_for_status().map(|_| ()),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(_) => {
+            log::trace!("Request sent successfully");
+            if let Some(acks) = acks {
+                acks.ack(&event_id);
+            }
+        }
         Err(e) => {
             log::error!("Error sending request: {}", e);
-            return;
+            if let Some(acks) = acks {
+                acks.nack(
+                    &event_id,
+                    &SinkError {
+                        source: Box::new(e),
+                    },
+                );
+            }
         }
     };
-
-    match response.error_for_status() {
-        Ok(_) => log::trace!("Request sent successfully"),
-        Err(e) => log::error!("Error sending request: {}", e),
-    };
 }
 
 struct ParsedPayload {
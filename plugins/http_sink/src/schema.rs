@@ -1,4 +1,4 @@
-use flwrs_plugin::plugin::core::{FieldDefinition, SchemaDefinition};
+use flwrs_plugin::plugin::core::{ConfigOption, FieldDefinition, SchemaDefinition};
 use flwrs_plugin::schema::schema::field_type::Enum as FieldType;
 
 pub(crate) const URL_KEY: &str = "url";
@@ -6,6 +6,12 @@ pub(crate) const METHOD_KEY: &str = "method";
 pub(crate) const HEADERS_KEY: &str = "headers";
 pub(crate) const BODY_KEY: &str = "body";
 
+pub(crate) const TIMEOUT_KEY: &str = "http-timeout-seconds";
+pub(crate) const READ_TIMEOUT_KEY: &str = "http-read-timeout-seconds";
+pub(crate) const CONNECT_TIMEOUT_KEY: &str = "http-connect-timeout-seconds";
+pub(crate) const VERBOSE_LOGGING_KEY: &str = "http-verbose-logging";
+pub(crate) const MAX_IN_FLIGHT_REQUESTS_KEY: &str = "http-max-in-flight-requests";
+
 pub(crate) fn build_schema() -> SchemaDefinition {
     SchemaDefinition::new().with_fields(vec![
         FieldDefinition::new()
@@ -33,3 +39,33 @@ pub(crate) fn build_schema() -> SchemaDefinition {
             .with_type(FieldType::Bytes),
     ])
 }
+
+pub(crate) fn build_config_options() -> Vec<ConfigOption> {
+    vec![
+        ConfigOption::new()
+            .with_key(TIMEOUT_KEY.into())
+            .with_description("HTTP client timeout (seconds)".into())
+            .with_type(FieldType::U32)
+            .with_default_value("60".into()),
+        ConfigOption::new()
+            .with_key(READ_TIMEOUT_KEY.into())
+            .with_description("HTTP client read timeout (seconds)".into())
+            .with_type(FieldType::U32)
+            .with_default_value("30".into()),
+        ConfigOption::new()
+            .with_key(CONNECT_TIMEOUT_KEY.into())
+            .with_description("HTTP client connect timeout (seconds)".into())
+            .with_type(FieldType::U32)
+            .with_default_value("30".into()),
+        ConfigOption::new()
+            .with_key(VERBOSE_LOGGING_KEY.into())
+            .with_description("HTTP client enable verbose logging".into())
+            .with_type(FieldType::Bool)
+            .with_default_value("false".into()),
+        ConfigOption::new()
+            .with_key(MAX_IN_FLIGHT_REQUESTS_KEY.into())
+            .with_description("Maximum number of HTTP requests in flight".into())
+            .with_type(FieldType::U32)
+            .with_default_value("32".into()),
+    ]
+}